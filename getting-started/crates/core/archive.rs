@@ -0,0 +1,402 @@
+//! --search-archives: 不解压到磁盘，直接搜索 tar、tar.gz 和 zip（包括 jar）中的文件。
+//! 归档中的文件用 `bundle.zip!/src/main.rs` 这样的虚拟路径表示，遍历目录时展开，搜索时流式读取。
+//! gzip 由 flate2 解压，tar 和 zip 分别由 tar、zip 库解析
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use flate2::read::MultiGzDecoder;
+use tar::EntryType;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// 虚拟路径中归档路径和归档内路径的分隔符
+pub(crate) const SEPARATOR: &str = "!/";
+
+/// 支持的归档格式
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ArchiveKind {
+    Tar,
+    TarGz,
+    /// zip 以及 jar/war 这类 zip 格式的包
+    Zip,
+}
+
+impl ArchiveKind {
+    /// 按扩展名判断归档格式，不是支持的归档返回 None
+    pub(crate) fn from_path(path: &Path) -> Option<ArchiveKind> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else if name.ends_with(".zip") || name.ends_with(".jar") || name.ends_with(".war") {
+            Some(ArchiveKind::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+/// 归档中的一个文件
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ArchiveEntry {
+    /// 归档内的路径，去掉了开头的 `./`
+    pub(crate) name: String,
+    /// 解压后的大小
+    pub(crate) size: u64,
+}
+
+/// 归档中所有的普通文件，按在归档中的顺序，目录、链接等其他类型的条目不会列出
+pub(crate) fn list_entries(path: &Path, kind: ArchiveKind) -> io::Result<Vec<ArchiveEntry>> {
+    let mut entries = vec![];
+    match kind {
+        ArchiveKind::Tar | ArchiveKind::TarGz => {
+            for entry in tar::Archive::new(open_tar(path, kind)?).entries()? {
+                let entry = entry?;
+                if let Some(name) = tar_entry_name(&entry) {
+                    entries.push(ArchiveEntry { name, size: entry.size() });
+                }
+            }
+        }
+        ArchiveKind::Zip => {
+            let mut zip = ZipArchive::new(File::open(path)?).map_err(io::Error::from)?;
+            for i in 0..zip.len() {
+                let file = zip.by_index_raw(i).map_err(io::Error::from)?;
+                if !file.is_dir() {
+                    entries.push(ArchiveEntry { name: file.name().to_string(), size: file.size() });
+                }
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// 归档中文件的虚拟路径，比如 `bundle.zip!/src/main.rs`
+pub(crate) fn virtual_path(archive: &Path, entry: &str) -> PathBuf {
+    PathBuf::from(format!("{}{SEPARATOR}{entry}", archive.display()))
+}
+
+/// 把虚拟路径拆成归档路径和归档内路径，`!/` 前面不是存在的归档文件时返回 None
+pub(crate) fn split_virtual_path(path: &Path) -> Option<(PathBuf, String)> {
+    let path = path.to_str()?;
+    path.match_indices(SEPARATOR).find_map(|(i, _)| {
+        let archive = Path::new(&path[..i]);
+        let entry = &path[i + SEPARATOR.len()..];
+        (ArchiveKind::from_path(archive).is_some() && archive.is_file() && !entry.is_empty())
+            .then(|| (archive.to_path_buf(), entry.to_string()))
+    })
+}
+
+/// 把待搜索的路径分成连续的几组，同一个归档中相邻的文件为一组，其他文件各自一组。
+/// 并行搜索时每个线程领取整组，归档只由一个线程按顺序读取，不会因为跳着读而反复解压
+pub(crate) fn group_by_archive(paths: &[PathBuf]) -> Vec<Range<usize>> {
+    let mut groups: Vec<Range<usize>> = vec![];
+    let mut last_archive = None;
+    for (i, path) in paths.iter().enumerate() {
+        let archive = split_virtual_path(path).map(|(archive, _)| archive);
+        match groups.last_mut() {
+            Some(group) if archive.is_some() && archive == last_archive => group.end = i + 1,
+            _ => groups.push(i..i + 1),
+        }
+        last_archive = archive;
+    }
+    groups
+}
+
+/// 打开归档中的文件，每个搜索线程一个。
+/// 遍历时归档中的文件按顺序排列，所以 tar 的读取位置会保留下来，按顺序搜索时整个归档只需要读一遍，
+/// zip 则保留打开的文件和中央目录
+#[derive(Default)]
+pub(crate) struct ArchiveReader {
+    open: Option<(PathBuf, Opened)>,
+}
+
+enum Opened {
+    /// 解压后的 tar 数据流，读取位置总是在某个头部的开头
+    Tar(Box<dyn Read + Send>),
+    Zip(ZipArchive<File>),
+}
+
+/// 打开的归档不能共享，克隆出来的是空的
+impl Clone for ArchiveReader {
+    fn clone(&self) -> ArchiveReader {
+        ArchiveReader::default()
+    }
+}
+
+impl fmt::Debug for ArchiveReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ArchiveReader")
+            .field("open", &self.open.as_ref().map(|(path, _)| path))
+            .finish()
+    }
+}
+
+impl ArchiveReader {
+    /// 找到归档中的文件，用读取解压后内容的 reader 调用 f。
+    /// 出错时丢弃打开的归档，tar 的读取位置已经不可靠，下次从头读取
+    pub(crate) fn read_entry<T>(
+        &mut self,
+        archive: &Path,
+        entry: &str,
+        mut f: impl FnMut(&mut dyn Read) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let result = self.read_entry_impl(archive, entry, &mut f);
+        if result.is_err() {
+            self.open = None;
+        }
+        result
+    }
+
+    fn read_entry_impl<T>(
+        &mut self,
+        archive: &Path,
+        entry: &str,
+        f: &mut impl FnMut(&mut dyn Read) -> io::Result<T>,
+    ) -> io::Result<T> {
+        let Some(kind) = ArchiveKind::from_path(archive) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a supported archive"));
+        };
+        let is_open = self.open.as_ref().is_some_and(|(path, _)| path == archive);
+        match kind {
+            ArchiveKind::Tar | ArchiveKind::TarGz => {
+                // 先从当前位置往后找，找不到再从头找一遍
+                let mut reopened = false;
+                if !is_open {
+                    self.open = Some((archive.to_path_buf(), Opened::Tar(open_tar(archive, kind)?)));
+                    reopened = true;
+                }
+                loop {
+                    let Some((_, Opened::Tar(ref mut stream))) = self.open else { unreachable!() };
+                    if let Some(result) = read_tar_entry(stream, entry, f)? {
+                        return Ok(result);
+                    }
+                    if reopened {
+                        return Err(no_entry(entry));
+                    }
+                    self.open = Some((archive.to_path_buf(), Opened::Tar(open_tar(archive, kind)?)));
+                    reopened = true;
+                }
+            }
+            ArchiveKind::Zip => {
+                if !is_open {
+                    let zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::from)?;
+                    self.open = Some((archive.to_path_buf(), Opened::Zip(zip)));
+                }
+                let Some((_, Opened::Zip(ref mut zip))) = self.open else { unreachable!() };
+                let mut file = match zip.by_name(entry) {
+                    Ok(file) => file,
+                    Err(ZipError::FileNotFound) => return Err(no_entry(entry)),
+                    Err(err) => return Err(err.into()),
+                };
+                f(&mut file)
+            }
+        }
+    }
+}
+
+fn no_entry(entry: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("no entry '{entry}' in archive"))
+}
+
+/// 解压后的 tar 数据流
+fn open_tar(path: &Path, kind: ArchiveKind) -> io::Result<Box<dyn Read + Send>> {
+    let file = BufReader::new(File::open(path)?);
+    Ok(if kind == ArchiveKind::TarGz {
+        // 多个 gzip member 连在一起时全部解压
+        Box::new(MultiGzDecoder::new(file))
+    } else {
+        Box::new(file)
+    })
+}
+
+/// tar 中普通文件在归档内的路径，去掉了开头的 `./`，目录、链接等其他类型的条目返回 None
+fn tar_entry_name<R: Read>(entry: &tar::Entry<'_, R>) -> Option<String> {
+    if !matches!(entry.header().entry_type(), EntryType::Regular | EntryType::Continuous) {
+        return None;
+    }
+    let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+    let name = name.trim_start_matches("./");
+    (!name.is_empty() && !name.ends_with('/')).then(|| name.to_string())
+}
+
+/// 从 tar 数据流的当前位置往后找到指定的文件，用它的内容调用 f，找不到返回 None。
+/// tar::Archive 只能从头开始遍历，所以每次在数据流的当前位置新建一个，
+/// 调用 f 之后跳过文件剩下的内容和补齐到 512 字节的部分，让数据流停在下一个头部
+fn read_tar_entry<T>(
+    stream: &mut Box<dyn Read + Send>,
+    name: &str,
+    f: &mut impl FnMut(&mut dyn Read) -> io::Result<T>,
+) -> io::Result<Option<T>> {
+    let mut archive = tar::Archive::new(&mut *stream);
+    let mut found = None;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if tar_entry_name(&entry).as_deref() == Some(name) {
+            let result = f(&mut entry)?;
+            io::copy(&mut entry, &mut io::sink())?;
+            found = Some((result, entry.size()));
+            break;
+        }
+    }
+    let Some((result, size)) = found else {
+        return Ok(None);
+    };
+    io::copy(&mut stream.take(size.next_multiple_of(512) - size), &mut io::sink())?;
+    Ok(Some(result))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use zip::write::SimpleFileOptions;
+    use zip::{CompressionMethod, ZipWriter};
+    use super::*;
+
+    /// 跨越多个压缩块的内容
+    fn large_contents() -> String {
+        (0..5000).map(|i| format!("line {i}: {}\n", i * i % 977)).collect()
+    }
+
+    fn tar_header(entry_type: EntryType, size: usize) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(entry_type);
+        header.set_size(size as u64);
+        header.set_mode(0o644);
+        header
+    }
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut gz = GzEncoder::new(vec![], Compression::default());
+        gz.write_all(data).unwrap();
+        gz.finish().unwrap()
+    }
+
+    #[test]
+    fn tar() {
+        let long_name = format!("src/{}/main.rs", "nested".repeat(20));
+        let large = large_contents();
+        let mut builder = tar::Builder::new(vec![]);
+        let mut append = |path: &str, entry_type: EntryType, data: &[u8]| {
+            builder.append_data(&mut tar_header(entry_type, data.len()), path, data).unwrap();
+        };
+        append("./src/", EntryType::Directory, b"");
+        append("./src/lib.rs", EntryType::Regular, b"pub mod a");
+        // 超过 100 字节的路径用 GNU 长文件名保存
+        append(&long_name, EntryType::Regular, b"fn main()\n");
+        append("src/empty.rs", EntryType::Regular, b"");
+        builder.append_pax_extensions([("path", b"src/large.txt".as_slice())]).unwrap();
+        builder.append_data(&mut tar_header(EntryType::Regular, large.len()), "ignored", large.as_bytes()).unwrap();
+        builder.append_link(&mut tar_header(EntryType::Symlink, 0), "link", "src/lib.rs").unwrap();
+        let tar = builder.into_inner().unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.tar");
+        std::fs::write(&path, &tar).unwrap();
+        // 两个 gzip member 连在一起
+        let (first, second) = tar.split_at(tar.len() / 2);
+        let gz_path = dir.path().join("bundle.tgz");
+        std::fs::write(&gz_path, [gzip(first), gzip(second)].concat()).unwrap();
+
+        let expected = vec![
+            ArchiveEntry { name: "src/lib.rs".to_string(), size: 9 },
+            ArchiveEntry { name: long_name.clone(), size: 10 },
+            ArchiveEntry { name: "src/empty.rs".to_string(), size: 0 },
+            ArchiveEntry { name: "src/large.txt".to_string(), size: large.len() as u64 },
+        ];
+        assert_eq!(expected, list_entries(&path, ArchiveKind::Tar).unwrap());
+        assert_eq!(expected, list_entries(&gz_path, ArchiveKind::TarGz).unwrap());
+
+        let archives = &mut ArchiveReader::default();
+        // 按顺序读取时接着上次的位置往后找，只读了一部分的文件也会跳过剩下的内容
+        assert_eq!("pub", read_prefix(archives, &gz_path, "src/lib.rs", 3));
+        assert_eq!("fn main()\n", read(archives, &gz_path, &long_name).unwrap());
+        assert_eq!(large, read(archives, &gz_path, "src/large.txt").unwrap());
+        // 倒着读会从头重新打开归档
+        assert_eq!("", read(archives, &gz_path, "src/empty.rs").unwrap());
+        assert_eq!("pub mod a", read(archives, &gz_path, "src/lib.rs").unwrap());
+        assert_eq!("pub mod a", read(archives, &path, "src/lib.rs").unwrap());
+        assert_eq!(large, read(archives, &path, "src/large.txt").unwrap());
+        assert_eq!(io::ErrorKind::NotFound, read(archives, &path, "link").unwrap_err().kind());
+
+        let entry = virtual_path(&gz_path, "src/lib.rs");
+        assert_eq!(Some((gz_path.clone(), "src/lib.rs".to_string())), split_virtual_path(&entry));
+        assert_eq!(None, split_virtual_path(&virtual_path(&dir.path().join("missing.tar"), "a")));
+        std::fs::write(&path, &tar[..700]).unwrap();
+        assert!(list_entries(&path, ArchiveKind::Tar).is_err());
+    }
+
+    fn read(archives: &mut ArchiveReader, archive: &Path, entry: &str) -> io::Result<String> {
+        archives.read_entry(archive, entry, |rdr| {
+            let mut contents = String::new();
+            rdr.read_to_string(&mut contents)?;
+            Ok(contents)
+        })
+    }
+
+    /// 只读取文件开头的 n 个字节
+    fn read_prefix(archives: &mut ArchiveReader, archive: &Path, entry: &str, n: usize) -> String {
+        let mut buf = vec![0; n];
+        archives.read_entry(archive, entry, |rdr| rdr.read_exact(&mut buf)).unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn zip() {
+        let large = large_contents();
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.add_directory("META-INF/", stored).unwrap();
+        zip.start_file("README", stored).unwrap();
+        zip.write_all(b"stored\n").unwrap();
+        zip.start_file("src/Hello.java", deflated).unwrap();
+        zip.write_all(large.as_bytes()).unwrap();
+        let mut zip = zip.finish().unwrap().into_inner();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.jar");
+        std::fs::write(&path, &zip).unwrap();
+        let names: Vec<String> = list_entries(&path, ArchiveKind::Zip).unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(vec!["README", "src/Hello.java"], names);
+
+        let archives = &mut ArchiveReader::default();
+        assert_eq!(large, read(archives, &path, "src/Hello.java").unwrap());
+        assert_eq!("stored\n", read(archives, &path, "README").unwrap());
+        assert_eq!(io::ErrorKind::NotFound, read(archives, &path, "src/Missing.java").unwrap_err().kind());
+
+        // 内容被改动后 CRC 校验失败
+        let readme = zip.windows(7).position(|w| w == b"stored\n").unwrap();
+        zip[readme] = b'S';
+        std::fs::write(&path, &zip).unwrap();
+        let result = ArchiveReader::default().read_entry(&path, "README", |rdr| rdr.read_to_end(&mut vec![]));
+        assert_eq!(io::ErrorKind::InvalidData, result.unwrap_err().kind());
+        std::fs::write(&path, b"not a zip").unwrap();
+        assert!(list_entries(&path, ArchiveKind::Zip).is_err());
+    }
+
+    #[test]
+    fn archive_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a.tar"), dir.path().join("b.zip"));
+        std::fs::write(&a, b"").unwrap();
+        std::fs::write(&b, b"").unwrap();
+        let paths = [
+            dir.path().join("plain.rs"),
+            virtual_path(&a, "x"),
+            virtual_path(&a, "y"),
+            virtual_path(&b, "x"),
+            dir.path().join("other.rs"),
+            dir.path().join("other.rs"),
+            virtual_path(&a, "z"),
+        ];
+        assert_eq!(vec![0..1, 1..3, 3..4, 4..5, 5..6, 6..7], group_by_archive(&paths));
+        assert!(group_by_archive(&[]).is_empty());
+    }
+}
@@ -0,0 +1,179 @@
+//! 文件路径的 glob 和文件类型，用于 --rules 中规则的 glob/type 以及 --pre-glob。
+//! glob 由 globset 匹配，和 ripgrep 一样 `*` 不匹配 `/`，不包含 `/` 的 glob 只匹配文件名
+
+use std::path::Path;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use crate::archive::SEPARATOR;
+
+/// 按 glob 和文件类型过滤文件，glob 和 type 都设置时文件需要同时满足，都没有设置时匹配所有文件
+#[derive(Clone, Debug, Default)]
+pub(crate) struct PathFilter {
+    /// `!` 开头的 glob，匹配其中一个的文件被排除
+    excludes: Globs,
+    /// 其余的 glob，文件需要匹配其中一个
+    includes: Globs,
+    /// 文件需要匹配其中一个文件类型的 glob
+    types: Globs,
+}
+
+impl PathFilter {
+    /// 添加一个 glob，`!` 开头的 glob 排除匹配的文件
+    pub(crate) fn add_glob(&mut self, glob: &str) -> Result<(), String> {
+        match glob.strip_prefix('!') {
+            Some(pattern) => self.excludes.add(glob, pattern),
+            None => self.includes.add(glob, glob),
+        }
+    }
+
+    /// 添加一个文件类型，文件类型可以重复添加
+    pub(crate) fn add_type(&mut self, name: &str) -> Result<(), String> {
+        let Some(&(_, globs)) = TYPES.iter().find(|(n, _)| *n == name) else {
+            let names: Vec<&str> = TYPES.iter().map(|(n, _)| *n).collect();
+            return Err(format!("unknown file type '{name}', expected one of {}", names.join(", ")));
+        };
+        globs.iter().try_for_each(|glob| self.types.add(glob, glob))
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.excludes.set.is_empty() && self.includes.set.is_empty() && self.types.set.is_empty()
+    }
+
+    /// 归档中文件的虚拟路径（比如 `bundle.zip!/src/main.rs`）分成归档路径和归档内路径两段匹配：
+    /// 任意一段匹配排除的 glob 就排除，glob 和文件类型各自只需要有一段匹配，
+    /// 所以 `-g '*.jar'` 搜索 jar 中的所有文件，`-t zip -g '*.java'` 只搜索 zip 中的 java 文件
+    pub(crate) fn is_match(&self, path: &Path) -> bool {
+        let path = path.strip_prefix(".").unwrap_or(path).to_string_lossy();
+        let segments: Vec<&str> = path.split(SEPARATOR).collect();
+        let is_match = |globs: &Globs| segments.iter().any(|segment| globs.set.is_match(segment));
+        if is_match(&self.excludes) {
+            return false;
+        }
+        if !self.includes.set.is_empty() && !is_match(&self.includes) {
+            return false;
+        }
+        self.types.set.is_empty() || is_match(&self.types)
+    }
+}
+
+/// 可以使用的文件类型
+const TYPES: &[(&str, &[&str])] = &[
+    ("c", &["*.c", "*.h"]),
+    ("cpp", &["*.cpp", "*.cc", "*.cxx", "*.hpp", "*.hh", "*.hxx", "*.h"]),
+    ("css", &["*.css", "*.scss"]),
+    ("go", &["*.go"]),
+    ("html", &["*.html", "*.htm"]),
+    ("java", &["*.java"]),
+    ("js", &["*.js", "*.jsx", "*.mjs", "*.cjs"]),
+    ("json", &["*.json"]),
+    ("md", &["*.md", "*.markdown"]),
+    ("py", &["*.py", "*.pyi"]),
+    ("ruby", &["*.rb"]),
+    ("rust", &["*.rs"]),
+    ("sh", &["*.sh", "*.bash", "*.zsh"]),
+    ("sql", &["*.sql"]),
+    ("tar", &["*.tar", "*.tar.gz", "*.tgz"]),
+    ("toml", &["*.toml", "Cargo.lock"]),
+    ("ts", &["*.ts", "*.tsx"]),
+    ("txt", &["*.txt"]),
+    ("yaml", &["*.yaml", "*.yml"]),
+    ("zip", &["*.zip", "*.jar", "*.war"]),
+];
+
+/// 一组 glob，每次添加后重新构建 GlobSet，glob 只在解析参数和规则文件时添加
+#[derive(Clone, Debug, Default)]
+struct Globs {
+    globs: Vec<globset::Glob>,
+    set: GlobSet,
+}
+
+impl Globs {
+    /// glob 是用户写的原始 glob，用于错误信息，pattern 是去掉 `!` 之后的部分
+    fn add(&mut self, glob: &str, pattern: &str) -> Result<(), String> {
+        let pattern = pattern.strip_prefix("./").unwrap_or(pattern);
+        if pattern.is_empty() {
+            return Err(format!("invalid glob '{glob}': empty glob"));
+        }
+        // 和 ripgrep 一样，不包含 `/` 的 glob 匹配任意目录下的文件名
+        let pattern = if pattern.contains('/') { pattern.to_string() } else { format!("**/{pattern}") };
+        let compiled = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build()
+            .map_err(|err| format!("invalid glob '{glob}': {}", err.kind()))?;
+        let mut builder = GlobSetBuilder::new();
+        for g in self.globs.iter().chain([&compiled]) {
+            builder.add(g.clone());
+        }
+        self.set = builder.build().map_err(|err| format!("invalid glob '{glob}': {}", err.kind()))?;
+        self.globs.push(compiled);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob(glob: &str, path: &str) -> bool {
+        let mut filter = PathFilter::default();
+        filter.add_glob(glob).unwrap();
+        filter.is_match(Path::new(path))
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob("*.rs", "./src/main.rs"));
+        assert!(!glob("*.rs", "src/main.rs.bak"));
+        assert!(glob("src/*.rs", "./src/main.rs"));
+        assert!(!glob("src/*.rs", "src/bin/main.rs"));
+        assert!(glob("src/**/*.rs", "src/main.rs"));
+        assert!(glob("src/**/*.rs", "src/a/b/main.rs"));
+        assert!(glob("**/tests/**", "crates/core/tests/a.rs"));
+        assert!(glob("*.{rs,t{o,x}ml}", "Cargo.toml"));
+        assert!(glob("file[0-9].txt", "file7.txt"));
+        assert!(!glob("file[!0-9].txt", "file7.txt"));
+        assert!(glob("a?c", "abc"));
+        assert!(!glob("!*.md", "README.md"));
+        assert!(glob("!*.md", "main.rs"));
+        for invalid in ["{a,b", "a}", "[ab", "", "!", "a\\"] {
+            assert!(PathFilter::default().add_glob(invalid).is_err(), "{invalid}");
+        }
+        assert_eq!(
+            Err("invalid glob '[z-a]': invalid range; 'z' > 'a'".to_string()),
+            PathFilter::default().add_glob("[z-a]"),
+        );
+    }
+
+    #[test]
+    fn types() {
+        let mut filter = PathFilter::default();
+        filter.add_type("rust").unwrap();
+        filter.add_type("toml").unwrap();
+        assert!(filter.is_match(Path::new("./src/main.rs")));
+        assert!(filter.is_match(Path::new("Cargo.lock")));
+        assert!(!filter.is_match(Path::new("README.md")));
+        filter.add_glob("!build.rs").unwrap();
+        assert!(!filter.is_match(Path::new("build.rs")));
+        assert!(filter.add_type("cobol").unwrap_err().starts_with("unknown file type 'cobol'"));
+    }
+
+    #[test]
+    fn archive_entries() {
+        let filter = |globs: &[&str], types: &[&str]| {
+            let mut filter = PathFilter::default();
+            globs.iter().for_each(|glob| filter.add_glob(glob).unwrap());
+            types.iter().for_each(|name| filter.add_type(name).unwrap());
+            filter
+        };
+        let entry = Path::new("./dist/bundle.jar!/src/Main.java");
+        assert!(filter(&["*.java"], &[]).is_match(entry));
+        assert!(filter(&["*.jar"], &[]).is_match(entry));
+        assert!(filter(&["src/*.java"], &[]).is_match(entry));
+        assert!(!filter(&["*.rs"], &[]).is_match(entry));
+        assert!(!filter(&["!*.jar"], &[]).is_match(entry));
+        assert!(!filter(&["!src/**"], &[]).is_match(entry));
+        assert!(filter(&["*.java"], &["zip"]).is_match(entry));
+        assert!(!filter(&["*.java"], &["tar"]).is_match(entry));
+        assert!(filter(&[], &["tar"]).is_match(Path::new("release.tar.gz!/README.md")));
+    }
+}
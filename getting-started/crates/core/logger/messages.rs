@@ -1,3 +1,28 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 是否有通过 err_message! 输出过错误信息
+static ERRORED: AtomicBool = AtomicBool::new(false);
+
+/// 搜索过程中的非致命错误（比如遍历目录时无权限访问某个文件）的输出宏，输出格式为 "gs: {message}"
+/// 和直接使用 eprintln! 相比，会和标准输出的内容互斥输出，并且会记录发生过错误，程序结束时可以据此调整退出码
+#[macro_export]
+macro_rules! err_message {
+    ($($tt:tt)*) => {
+        $crate::logger::messages::set_errored();
+        $crate::eprintln_locked!($($tt)*);
+    }
+}
+
+/// 是否有通过 err_message! 输出过错误信息
+pub(crate) fn errored() -> bool {
+    ERRORED.load(Ordering::Relaxed)
+}
+
+/// 记录发生过错误，由 err_message! 调用
+pub(crate) fn set_errored() {
+    ERRORED.store(true, Ordering::Relaxed);
+}
+
 // 这个宏用于同步（锁）将信息输出到标准输出或标准错误
 #[macro_export]
 macro_rules! eprintln_locked {
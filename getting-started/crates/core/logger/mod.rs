@@ -1,2 +1,2 @@
 pub(crate) mod logger;
-mod messages;
+pub(crate) mod messages;
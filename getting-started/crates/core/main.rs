@@ -2,11 +2,14 @@
 // 引入标准库中的类，标准库中部分类是预导入的不需要声明，这里显式导入的都是未预导入的
 use std::{io::Write, process::ExitCode};
 use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
 use anyhow::anyhow;
 use log::info;
 use lexopt::{prelude::*};
-use termcolor::ColorChoice;
-use grep::printer::StandardBuilder;
+use termcolor::{ColorChoice, WriteColor};
+use grep::printer::{SarifLevel, Stats};
 use grep::regex::RegexMatcherBuilder;
 use grep::searcher::SearcherBuilder;
 
@@ -19,7 +22,10 @@ use crate::search::{PatternMatcher, Printer};
 // mod options 表示从 options.rs 或 options/mod.rs 中查找模块代码
 // 这里是通过 options 模块的 mod.rs 以及 mod.rs 中通过 mod 声明的模块，将全部代码联系起来的
 mod options;
+mod archive;
+mod glob;
 mod logger;
+mod rules;
 mod search;
 
 /// 二进制执行，比如: gs -i --debug grep ./crates/grep
@@ -63,13 +69,23 @@ fn run(result: ParseResult<HiArgs>) -> anyhow::Result<ExitCode> {
     // Types：列举配置的所有文件类型
     // Generate：生成帮助文档等
     // 这里只展示 Search
-    let matched = match args.mode() {
-        Mode::Search(_) if !args.matches_possible() => false,
-        Mode::Search(mode) if args.threads() == 1 => search(&mut args, mode)?,
-        Mode::Search(mode) => search_parallel(&args, mode)?,
-        Mode::Files => false   //先忽略
+    // severity 是结果中最高的严重程度，只有单线程搜索时的 SARIF 和 --rules 才有
+    let (matched, severity) = match args.mode() {
+        Mode::Search(_) if !args.matches_possible() => (false, None),
+        Mode::Search(mode) if args.threads() == 1 => {
+            let wtr = args.stdout();
+            search(&mut args, mode, wtr)?
+        }
+        Mode::Search(mode) => (search_parallel(&mut args, mode)?, None),
+        Mode::Files => (files(&mut args)?, None),
+        Mode::DebugRegex => (debug_regex(&args)?, None),
     };
-    let exit_code = if matched {
+    // 和 ripgrep 一样，搜索过程中发生过错误（比如无权限访问某个文件）时退出码为 2
+    let exit_code = if logger::messages::errored() {
+        ExitCode::from(2)
+    } else if args.rules() {
+        rules_exit_code(severity)
+    } else if matched {
         ExitCode::from(0)
     } else {
         ExitCode::from(1)
@@ -77,6 +93,17 @@ fn run(result: ParseResult<HiArgs>) -> anyhow::Result<ExitCode> {
     Ok(exit_code)
 }
 
+/// --rules 的退出码取决于结果中最高的严重程度，方便在 CI 中只让 error 或 warning 导致失败：
+/// error 为 3，warning 为 1，没有结果或者只有 note/none 为 0。
+/// 注意 warning 的 1 和普通搜索没有匹配时的退出码相同，--rules 没有结果时退出码是 0
+fn rules_exit_code(severity: Option<SarifLevel>) -> ExitCode {
+    match severity {
+        Some(SarifLevel::Error) => ExitCode::from(3),
+        Some(SarifLevel::Warning) => ExitCode::from(1),
+        Some(SarifLevel::Note) | Some(SarifLevel::None) | None => ExitCode::from(0),
+    }
+}
+
 fn special(mode: SpecialMode) -> anyhow::Result<ExitCode> {
     let output = match mode {
         SpecialMode::HelpShort => options::generate_help_short(),
@@ -89,28 +116,191 @@ fn special(mode: SpecialMode) -> anyhow::Result<ExitCode> {
     Ok(ExitCode::SUCCESS)
 }
 
-fn search(args: &mut HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
+/// 搜索结果输出到 wtr，返回是否有匹配，以及结果中最高的严重程度
+fn search<W: WriteColor>(args: &mut HiArgs, mode: SearchMode, wtr: W) -> anyhow::Result<(bool, Option<SarifLevel>)> {
+    let started_at = Instant::now();
     let mut matched = false;
+    let mut stats = args.stats();
     //1 创建 SearchWorker
     let mut search_worker = args.search_worker(
         args.matcher()?,
         args.searcher()?,
-        args.printer(mode, args.stdout()),
+        args.printer(mode, wtr),
     )?;
     // 2 递归查找
     let paths = args.paths();
     for path_buf in paths {
-        // 执行搜索、输出等流程
-        let search_result = search_worker.search(path_buf.as_path())?;
+        // 执行搜索、输出等流程，单个文件搜索出错（比如一行超过了 --heap-limit）时报告错误并继续搜索其他文件
+        let search_result = match search_worker.search(path_buf.as_path()) {
+            Ok(search_result) => search_result,
+            Err(err) => {
+                err_message!("{}: {}", path_buf.display(), err);
+                continue;
+            }
+        };
         matched = matched || search_result.has_match();
-        // ripgrep 还支持统计功能，但是这里不展示了
+        // 汇总每次搜索的统计数据
+        if let Some(ref mut stats) = stats {
+            if let Some(search_stats) = search_result.stats() {
+                *stats += search_stats;
+            }
+        }
+    }
+    search_worker.printer().end()?;
+    if let Some(ref stats) = stats {
+        print_stats(stats, started_at, args.stdout())?;
+    }
+    Ok((matched, search_worker.printer().max_level()))
+}
+
+/// 打印 --stats 统计数据，search_time 是各次搜索耗时之和，process_time 是从开始搜索到现在的总耗时
+fn print_stats<W: Write>(
+    stats: &Stats,
+    started_at: Instant,
+    mut wtr: W,
+) -> std::io::Result<()> {
+    let elapsed = started_at.elapsed();
+    write!(
+        wtr,
+        "
+{matches} matches
+{lines} matched lines
+{searches_with_match} files contained matches
+{searches} files searched
+{bytes_printed} bytes printed
+{bytes_searched} bytes searched
+{search_time:0.6} seconds spent searching
+{process_time:0.6} seconds
+",
+        matches = stats.matches(),
+        lines = stats.matched_lines(),
+        searches_with_match = stats.searches_with_match(),
+        searches = stats.searches(),
+        bytes_printed = stats.bytes_printed(),
+        bytes_searched = stats.bytes_searched(),
+        search_time = stats.elapsed().as_secs_f64(),
+        process_time = elapsed.as_secs_f64(),
+    )
+}
+
+/// --files: 打印会搜索的文件路径，开启 --search-archives 时包括归档中文件的虚拟路径
+fn files(args: &mut HiArgs) -> anyhow::Result<bool> {
+    let paths = args.paths();
+    let stdout = args.stdout();
+    let mut wtr = stdout.lock();
+    for path in paths.iter() {
+        writeln!(wtr, "{}", path.display())?;
+    }
+    Ok(!paths.is_empty())
+}
+
+/// --debug-regex: 打印正则匹配器的诊断信息，只支持默认的 Rust Regex 引擎
+fn debug_regex(args: &HiArgs) -> anyhow::Result<bool> {
+    match args.matcher()? {
+        PatternMatcher::RustRegex(m) => writeln!(args.stdout(), "{}", m.explain())?,
+        #[cfg(feature = "pcre2")]
+        PatternMatcher::PCRE2(_) => {
+            anyhow::bail!("--debug-regex is only supported by the default regex engine")
+        }
     }
-    Ok(matched)
+    Ok(true)
 }
 
-fn search_parallel(args: &HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
-    //TODO
-    Ok(false)
+/// 多线程搜索，每个线程从待搜索的文件列表中依次领取文件，把结果写到自己的缓冲，搜索完一个文件就整体输出
+fn search_parallel(args: &mut HiArgs, mode: SearchMode) -> anyhow::Result<bool> {
+    let started_at = Instant::now();
+    let paths = args.paths();
+    let args = &*args;
+    let matcher = args.matcher()?;
+    let bufwtr = args.buffer_writer();
+    // 同一个归档中的文件由一个线程按顺序搜索，线程每次领取一组
+    let groups = archive::group_by_archive(&paths);
+    // 下一个待领取的组序号
+    let next = AtomicUsize::new(0);
+    let matched = AtomicBool::new(false);
+    let stats = Mutex::new(args.stats());
+    std::thread::scope(|s| -> anyhow::Result<()> {
+        let mut handles = vec![];
+        for _ in 0..args.threads() {
+            let mut search_worker = args.search_worker(
+                matcher.clone(),
+                args.searcher()?,
+                args.printer(mode, bufwtr.buffer()),
+            )?;
+            let (paths, groups, bufwtr, next, matched, stats) = (&paths, &groups, &bufwtr, &next, &matched, &stats);
+            handles.push(s.spawn(move || -> std::io::Result<()> {
+                let mut worker_stats = args.stats();
+                while let Some(group) = groups.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    for index in group.clone() {
+                        let path_buf = &paths[index];
+                        search_worker.printer().get_mut().clear();
+                        match search_worker.search(path_buf.as_path()) {
+                            Ok(search_result) => {
+                                if search_result.has_match() {
+                                    matched.store(true, Ordering::Relaxed);
+                                }
+                                if let (Some(stats), Some(search_stats)) = (worker_stats.as_mut(), search_result.stats()) {
+                                    *stats += search_stats;
+                                }
+                            }
+                            // 出错的文件也要输出（可能已经输出了部分结果），按顺序输出时不能跳过它的序号
+                            Err(err) => {
+                                err_message!("{}: {}", path_buf.display(), err);
+                            }
+                        }
+                        bufwtr.print(index, search_worker.printer().get_mut())?;
+                    }
+                }
+                if let (Some(stats), Some(worker_stats)) = (stats.lock().unwrap().as_mut(), worker_stats) {
+                    *stats += worker_stats;
+                }
+                Ok(())
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap()?;
+        }
+        Ok(())
+    })?;
+    if let Some(ref stats) = *stats.lock().unwrap() {
+        print_stats(stats, started_at, args.stdout())?;
+    }
+    Ok(matched.load(Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::lowargs::LowArgs;
+
+    /// --rules 的退出码取决于找到的结果中最高的严重程度
+    #[test]
+    fn rules_exit_codes() {
+        let temp = tempfile::tempdir().unwrap();
+        let file = temp.path().join("a.rs");
+        std::fs::write(&file, "x.unwrap();\n// TODO\n").unwrap();
+        let exit_code = |rules: &str| {
+            let path = temp.path().join("rules.toml");
+            std::fs::write(&path, rules).unwrap();
+            let low = LowArgs {
+                rules: Some(path),
+                positional: vec![file.clone().into_os_string()],
+                ..LowArgs::default()
+            };
+            let mut args = HiArgs::from_low_args(low).unwrap();
+            let Mode::Search(mode) = args.mode() else { panic!("not a search") };
+            let (_, severity) = search(&mut args, mode, termcolor::NoColor::new(vec![])).unwrap();
+            rules_exit_code(severity)
+        };
+        let rule = |id: &str, pattern: &str, severity: &str| {
+            format!("[[rule]]\nid = '{id}'\npattern = '{pattern}'\nseverity = '{severity}'\n")
+        };
+        let (error, warning, note) = (rule("e", "unwrap", "error"), rule("w", "TODO", "warning"), rule("n", "x", "note"));
+        assert_eq!(ExitCode::from(3), exit_code(&format!("{error}{warning}{note}")));
+        assert_eq!(ExitCode::from(1), exit_code(&format!("{warning}{note}")));
+        assert_eq!(ExitCode::from(0), exit_code(&note));
+        assert_eq!(ExitCode::from(0), exit_code(&rule("e", "FIXME", "error")));
+    }
 }
 
 // 条件编译宏，这里表示只有在执行cargo test才会编译和运行tests模块
@@ -1,6 +1,11 @@
+use std::path::PathBuf;
+use std::time::Duration;
 use bstr::ByteVec;
+use grep::printer::HyperlinkFormat;
 use crate::options::{Category, Flag, FlagValue};
-use crate::options::lowargs::{CaseMode, LoggingMode, LowArgs, PatternSource};
+use crate::options::lowargs::{
+    CaseMode, EngineChoice, LoggingMode, LowArgs, Mode, PatternSource, SortMode, SortModeKind,
+};
 
 /// 这里使用常量存储了所有预设的命令行选项
 /// ripgrep 支持很多命令行选项，提供了非常丰富的功能，但是这里只展示几种常用的选项
@@ -8,17 +13,56 @@ pub(super) const FLAGS: &[&dyn Flag] = &[   //FLAGS是一个数组引用，数
     &Help,
     &Version,
     &Debug,
+    &DebugRegex,
     &IgnoreCase,
     &CaseSensitive,
     &SmartCase,
     // &Color,
+    &ByteOffset,
     &Column,
+    &Crlf,
+    &DfaSizeLimit,
+    &Engine,
+    &FieldContextSeparator,
+    &FieldMatchSeparator,
+    &File,
+    &Files,
+    &Follow,
+    &FollowFile,
+    &Format,
+    &Glob,
     &Heading,
+    &HeapLimit,
+    &HyperlinkFormatFlag,
     &LineNumber,
     &LineNumberNo,
+    &MaxColumns,
+    &MaxColumnsPreview,
+    &MaxDepth,
+    &MaxFilesize,
+    &NoUnicode,
+    &NullData,
+    &OneFileSystem,
     &PathSeparator,
+    &PatternColors,
+    &PCRE2,
+    &PollInterval,
+    &Pre,
+    &PreGlob,
+    &Pretty,
+    &RegexSizeLimit,
     &Regexp,
+    &Rules,
+    &Sarif,
+    &SearchArchives,
+    &Sort,
+    &Sortr,
+    &Stats,
     &Threads,
+    &Type,
+    &WithFilename,
+    &WithFilenameNo,
+    &VerboseRegex,
 ];
 
 /// -h/--help
@@ -90,6 +134,27 @@ impl Flag for Debug {
     }
 }
 
+/// --debug-regex
+/// 不执行搜索，而是打印正则匹配器的诊断信息（最终的正则、字面量优化策略、不可能匹配的字节），用于排查搜索慢的原因
+#[derive(Debug)]
+struct DebugRegex;
+
+impl Flag for DebugRegex {
+    fn name_long(&self) -> &'static str {
+        "debug-regex"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Logging
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Print how the regex will be matched instead of searching."
+    }
+    fn update(&self, _: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.mode = Mode::DebugRegex;
+        Ok(())
+    }
+}
+
 /// -i/--ignore-case
 #[derive(Debug)]
 struct IgnoreCase;
@@ -202,6 +267,33 @@ impl Flag for SmartCase {
 //     }
 // }
 
+/// -b/--byte-offset
+/// 打印匹配行行首在文件中的字节偏移（从 0 开始），方便其他工具直接定位
+#[derive(Debug)]
+struct ByteOffset;
+
+impl Flag for ByteOffset {
+    fn name_long(&self) -> &'static str {
+        "byte-offset"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'b')
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-byte-offset")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Print the byte offset for each matching line."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.byte_offset = v.unwrap_switch();
+        Ok(())
+    }
+}
+
 /// --column
 #[derive(Debug)]
 struct Column;
@@ -225,114 +317,145 @@ impl Flag for Column {
     }
 }
 
-/// --heading
+/// --crlf
+/// 使用 CRLF 作为行终止符，这样 `$` 可以匹配在 `\r\n` 之前，且输出时不会打印 `\r`
 #[derive(Debug)]
-struct Heading;
+struct Crlf;
 
-impl Flag for Heading {
+impl Flag for Crlf {
     fn name_long(&self) -> &'static str {
-        "heading"
+        "crlf"
     }
     fn name_negated(&self) -> Option<&'static str> {
-        Some("no-heading")
+        Some("no-crlf")
     }
     fn doc_category(&self) -> Category {
-        Category::Output
+        Category::Search
     }
     fn doc_short(&self) -> &'static str {
-        r"Print matches grouped by each file."
+        r"Use CRLF line terminators (nice for Windows)."
     }
     fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
-        args.heading = Some(v.unwrap_switch());
+        args.crlf = v.unwrap_switch();
+        // --crlf 和 --null-data 互斥，后出现的选项覆盖前面的选项
+        if args.crlf {
+            args.null_data = false;
+        }
         Ok(())
     }
 }
 
-/// -n/--line-number
+/// --dfa-size-limit
+/// 惰性 DFA 缓存的大小，缓存太小不会报错但搜索会变慢
 #[derive(Debug)]
-struct LineNumber;
+struct DfaSizeLimit;
 
-impl Flag for LineNumber {
+impl Flag for DfaSizeLimit {
     fn name_long(&self) -> &'static str {
-        "line-number"
-    }
-    fn name_short(&self) -> Option<u8> {
-        Some(b'n')
+        "dfa-size-limit"
     }
     fn doc_category(&self) -> Category {
-        Category::Output
+        Category::Search
     }
     fn doc_short(&self) -> &'static str {
-        r"Show line numbers."
+        r"The upper size limit of the regex DFA."
+    }
+    fn is_switch(&self) -> bool {
+        false
     }
     fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
-        assert!(v.unwrap_switch(), "--line-number has no automatic negation");
-        args.line_number = Some(true);
+        args.dfa_size_limit = Some(convert::human_readable_usize(&v.unwrap_value())?);
         Ok(())
     }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("NUM+SUFFIX?")
+    }
 }
 
-/// -N/--no-line-number
+/// --engine
+/// 指定使用的正则引擎
 #[derive(Debug)]
-struct LineNumberNo;
+struct Engine;
 
-impl Flag for LineNumberNo {
+impl Flag for Engine {
     fn name_long(&self) -> &'static str {
-        "no-line-number"
+        "engine"
     }
-    fn name_short(&self) -> Option<u8> {
-        Some(b'N')
+    fn doc_category(&self) -> Category {
+        Category::Search
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Specify which regex engine to use."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.engine = match convert::str(&v.unwrap_value())? {
+            "default" => EngineChoice::Default,
+            "pcre2" => EngineChoice::PCRE2,
+            "auto" => EngineChoice::Auto,
+            unk => anyhow::bail!("unrecognized regex engine '{unk}'"),
+        };
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("ENGINE")
+    }
+    fn doc_choices(&self) -> &'static [&'static str] {
+        &["default", "pcre2", "auto"]
+    }
+}
+
+/// --field-context-separator
+/// 上下文行中各个字段之间的分隔符，默认 `-`，支持转义（比如 `\t` `\x00`），可以是多个字节
+#[derive(Debug)]
+struct FieldContextSeparator;
+
+impl Flag for FieldContextSeparator {
+    fn name_long(&self) -> &'static str {
+        "field-context-separator"
     }
     fn doc_category(&self) -> Category {
         Category::Output
     }
     fn doc_short(&self) -> &'static str {
-        r"Suppress line numbers."
+        r"Set the field context separator."
+    }
+    fn is_switch(&self) -> bool {
+        false
     }
     fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
-        assert!(
-            v.unwrap_switch(),
-            "--no-line-number has no automatic negation"
-        );
-        args.line_number = Some(false);
+        let s = convert::string(v.unwrap_value())?;
+        args.field_context_separator = Some(Vec::unescape_bytes(&s));
         Ok(())
     }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("SEPARATOR")
+    }
 }
 
-/// --path-separator
+/// --field-match-separator
+/// 匹配行中路径、行号、列号、字节偏移和行内容之间的分隔符，默认 `:`，支持转义，可以是多个字节
 #[derive(Debug)]
-struct PathSeparator;
+struct FieldMatchSeparator;
 
-impl Flag for PathSeparator {
+impl Flag for FieldMatchSeparator {
     fn name_long(&self) -> &'static str {
-        "path-separator"
+        "field-match-separator"
     }
     fn doc_category(&self) -> Category {
         Category::Output
     }
     fn doc_short(&self) -> &'static str {
-        r"Set the path separator for printing paths."
+        r"Set the field match separator."
     }
     fn is_switch(&self) -> bool {
         false
     }
     fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
         let s = convert::string(v.unwrap_value())?;
-        let raw = Vec::unescape_bytes(&s);
-        args.path_separator = if raw.is_empty() {
-            None
-        } else if raw.len() == 1 {
-            Some(raw[0])
-        } else {
-            anyhow::bail!(
-                "A path separator must be exactly one byte, but \
-                 the given separator is {len} bytes: {sep}\n\
-                 In some shells on Windows '/' is automatically \
-                 expanded. Use '//' instead.",
-                len = raw.len(),
-                sep = s,
-            )
-        };
+        args.field_match_separator = Some(Vec::unescape_bytes(&s));
         Ok(())
     }
     fn doc_variable(&self) -> Option<&'static str> {
@@ -340,96 +463,1109 @@ impl Flag for PathSeparator {
     }
 }
 
-/// -e/--regexp
-/// 基于正则表达式进行匹配查找
+/// -f/--file
+/// 从文件读取模式，每行一个，可以和 -e 一起使用
+/// `#@ id=...; severity=...; message=...` 形式的行设置下一个模式的元数据，用于 --sarif 输出的规则
 #[derive(Debug)]
-struct Regexp;
+struct File;
 
-impl Flag for Regexp {
+impl Flag for File {
     fn name_long(&self) -> &'static str {
-        "regexp"
+        "file"
     }
     fn name_short(&self) -> Option<u8> {
-        Some(b'e')
+        Some(b'f')
     }
     fn doc_category(&self) -> Category {
         Category::Input
     }
     fn doc_short(&self) -> &'static str {
-        r"A pattern to search for."
+        r"Search for patterns from the given file."
+    }
+    fn is_switch(&self) -> bool {
+        false
     }
     fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
-        let regexp = convert::string(v.unwrap_value())?;
-        args.patterns.push(PatternSource::Regexp(regexp));
+        let path = PathBuf::from(v.unwrap_value());
+        args.patterns.push(PatternSource::File(path));
         Ok(())
     }
     fn doc_variable(&self) -> Option<&'static str> {
-        Some("PATTERN")
+        Some("PATTERNFILE")
     }
 }
 
-// -r/--replace
-// 匹配后替换
-// #[derive(Debug)]
-// struct Replace;
+/// --files
+/// 只打印会搜索的文件路径，不执行搜索，所有 positional args 都是路径
+#[derive(Debug)]
+struct Files;
 
-/// -j/--threads
+impl Flag for Files {
+    fn name_long(&self) -> &'static str {
+        "files"
+    }
+    fn doc_category(&self) -> Category {
+        Category::OtherBehaviors
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Print each file that would be searched."
+    }
+    fn update(&self, _: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.mode = Mode::Files;
+        Ok(())
+    }
+}
+
+/// -L/--follow
+/// 遍历目录时跟随符号链接，会检测符号链接导致的循环
 #[derive(Debug)]
-struct Threads;
+struct Follow;
 
-impl Flag for Threads {
+impl Flag for Follow {
     fn name_long(&self) -> &'static str {
-        "threads"
+        "follow"
     }
     fn name_short(&self) -> Option<u8> {
-        Some(b'j')
+        Some(b'L')
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-follow")
     }
     fn doc_category(&self) -> Category {
-        Category::Search
+        Category::Input
     }
     fn doc_short(&self) -> &'static str {
-        r"Set the approximate number of threads to use."
+        r"Follow symbolic links."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.follow = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// --follow-file
+/// 类似 `tail -F | gs PATTERN`，搜索完文件后继续搜索追加到文件的内容，文件被截断或轮转后从新文件开头继续
+#[derive(Debug)]
+struct FollowFile;
+
+impl Flag for FollowFile {
+    fn name_long(&self) -> &'static str {
+        "follow-file"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-follow-file")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Keep searching data appended to a file."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.follow_file = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// --format
+/// 按模板输出每个匹配，比如 `{path}:{line}:{col}: {match}`，模板在 HiArgs 中校验
+#[derive(Debug)]
+struct Format;
+
+impl Flag for Format {
+    fn name_long(&self) -> &'static str {
+        "format"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Print each match using a template like '{path}:{line}:{col}: {match}'."
     }
     fn is_switch(&self) -> bool {
         false
     }
     fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
-        let threads = convert::usize(&v.unwrap_value())?;
-        args.threads = if threads == 0 { None } else { Some(threads) };
+        args.format = Some(convert::string(v.unwrap_value())?);
         Ok(())
     }
     fn doc_variable(&self) -> Option<&'static str> {
-        Some("NUM")
+        Some("TEMPLATE")
     }
 }
 
-mod convert {
-    use std::ffi::{OsStr, OsString};
-    use anyhow::Context;
+/// -g/--glob
+/// 只搜索匹配 glob 的文件，可以重复指定，`!` 开头的 glob 排除匹配的文件，命令行中直接给出的文件不受影响
+#[derive(Debug)]
+struct Glob;
 
-    /// OsStr 转 &str
-    pub(super) fn str(v: &OsStr) -> anyhow::Result<&str> {
-        let Some(s) = v.to_str() else {
-            anyhow::bail!("value is not valid UTF-8")
-        };
-        Ok(s)
+impl Flag for Glob {
+    fn name_long(&self) -> &'static str {
+        "glob"
     }
-
-    /// OsString 转 String
-    pub(super) fn string(v: OsString) -> anyhow::Result<String> {
-        let Ok(s) = v.into_string() else {
-            anyhow::bail!("value is not valid UTF-8")
-        };
-        Ok(s)
+    fn name_short(&self) -> Option<u8> {
+        Some(b'g')
     }
-
-    /// str 转 usize
-    pub(super) fn usize(v: &OsStr) -> anyhow::Result<usize> {
-        str(v)?.parse().context("value is not a valid number")
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Include or exclude file paths matching GLOB."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.globs.push(convert::string(v.unwrap_value())?);
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("GLOB")
     }
+}
 
-    /// str 转 u64
-    pub(super) fn u64(v: &OsStr) -> anyhow::Result<u64> {
-        str(v)?.parse().context("value is not a valid number")
+/// --heading
+#[derive(Debug)]
+struct Heading;
+
+impl Flag for Heading {
+    fn name_long(&self) -> &'static str {
+        "heading"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-heading")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Print matches grouped by each file."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.heading = Some(v.unwrap_switch());
+        Ok(())
     }
 }
+
+/// --heap-limit
+/// 限制行缓冲最多使用的堆内存，避免没有换行的超长行（比如压缩过的 js 文件）耗尽内存
+#[derive(Debug)]
+struct HeapLimit;
+
+impl Flag for HeapLimit {
+    fn name_long(&self) -> &'static str {
+        "heap-limit"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Search
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Limit the heap memory used by the line buffer."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.heap_limit = Some(convert::human_readable_usize(&v.unwrap_value())?);
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("NUM+SUFFIX?")
+    }
+}
+
+/// --hyperlink-format
+/// 文件路径的超链接格式，可以是模板（比如 `vscode://file{path}:{line}:{column}`）或者内置的编辑器别名（比如 vscode）
+#[derive(Debug)]
+struct HyperlinkFormatFlag;
+
+impl Flag for HyperlinkFormatFlag {
+    fn name_long(&self) -> &'static str {
+        "hyperlink-format"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Set the format of hyperlinks."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        let v = v.unwrap_value();
+        args.hyperlink_format = convert::str(&v)?.parse::<HyperlinkFormat>()?;
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("FORMAT")
+    }
+}
+
+/// -n/--line-number
+#[derive(Debug)]
+struct LineNumber;
+
+impl Flag for LineNumber {
+    fn name_long(&self) -> &'static str {
+        "line-number"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'n')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Show line numbers."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        assert!(v.unwrap_switch(), "--line-number has no automatic negation");
+        args.line_number = Some(true);
+        Ok(())
+    }
+}
+
+/// -N/--no-line-number
+#[derive(Debug)]
+struct LineNumberNo;
+
+impl Flag for LineNumberNo {
+    fn name_long(&self) -> &'static str {
+        "no-line-number"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'N')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Suppress line numbers."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        assert!(
+            v.unwrap_switch(),
+            "--no-line-number has no automatic negation"
+        );
+        args.line_number = Some(false);
+        Ok(())
+    }
+}
+
+/// -M/--max-columns
+/// 超过这个字节数的行不完整输出（省略或者只输出预览），避免压缩过的 js 等超长行刷屏，0 表示不限制
+#[derive(Debug)]
+struct MaxColumns;
+
+impl Flag for MaxColumns {
+    fn name_long(&self) -> &'static str {
+        "max-columns"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'M')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Omit lines longer than this limit."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.max_columns = Some(convert::u64(&v.unwrap_value())?);
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("NUM")
+    }
+}
+
+/// --max-columns-preview
+/// 超过 --max-columns 的行输出前面一部分作为预览，而不是整行省略
+#[derive(Debug)]
+struct MaxColumnsPreview;
+
+impl Flag for MaxColumnsPreview {
+    fn name_long(&self) -> &'static str {
+        "max-columns-preview"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-max-columns-preview")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Show preview for lines exceeding the limit."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.max_columns_preview = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// -d/--max-depth
+/// 遍历目录的最大深度，0 表示只搜索命令行中直接给出的文件
+#[derive(Debug)]
+struct MaxDepth;
+
+impl Flag for MaxDepth {
+    fn name_long(&self) -> &'static str {
+        "max-depth"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'd')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Descend at most NUM directories."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.max_depth = Some(convert::usize(&v.unwrap_value())?);
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("NUM")
+    }
+}
+
+/// --max-filesize
+/// 跳过大于指定大小的文件，支持 K M G 后缀，比如 10M
+#[derive(Debug)]
+struct MaxFilesize;
+
+impl Flag for MaxFilesize {
+    fn name_long(&self) -> &'static str {
+        "max-filesize"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Ignore files larger than NUM in size."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.max_filesize = Some(convert::human_readable_u64(&v.unwrap_value())?);
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("NUM+SUFFIX?")
+    }
+}
+
+/// --no-unicode
+/// 关闭 Unicode 模式，`\w` `.` 等只匹配 ASCII 或单个字节，搜索二进制数据或者追求速度时使用
+#[derive(Debug)]
+struct NoUnicode;
+
+impl Flag for NoUnicode {
+    fn name_long(&self) -> &'static str {
+        "no-unicode"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("unicode")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Search
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Disable Unicode mode."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.no_unicode = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// --null-data
+/// 使用 NUL 作为行终止符，比如搜索 `find -print0` 输出的以 NUL 分隔的记录
+#[derive(Debug)]
+struct NullData;
+
+impl Flag for NullData {
+    fn name_long(&self) -> &'static str {
+        "null-data"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Search
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Use NUL as a line terminator."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        assert!(v.unwrap_switch(), "--null-data has no negation");
+        args.crlf = false;
+        args.null_data = true;
+        Ok(())
+    }
+}
+
+/// --path-separator
+#[derive(Debug)]
+struct PathSeparator;
+
+impl Flag for PathSeparator {
+    fn name_long(&self) -> &'static str {
+        "path-separator"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Set the path separator for printing paths."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        let s = convert::string(v.unwrap_value())?;
+        let raw = Vec::unescape_bytes(&s);
+        args.path_separator = if raw.is_empty() {
+            None
+        } else if raw.len() == 1 {
+            Some(raw[0])
+        } else {
+            anyhow::bail!(
+                "A path separator must be exactly one byte, but \
+                 the given separator is {len} bytes: {sep}\n\
+                 In some shells on Windows '/' is automatically \
+                 expanded. Use '//' instead.",
+                len = raw.len(),
+                sep = s,
+            )
+        };
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("SEPARATOR")
+    }
+}
+
+/// --one-file-system
+/// 遍历目录时不跨越文件系统，即不进入挂载在其他文件系统上的目录
+#[derive(Debug)]
+struct OneFileSystem;
+
+impl Flag for OneFileSystem {
+    fn name_long(&self) -> &'static str {
+        "one-file-system"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-one-file-system")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Skip directories on other file systems."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.one_file_system = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// --pattern-colors
+/// 有多个模式时（比如多个 -e），每个模式的匹配项使用不同的颜色高亮
+#[derive(Debug)]
+struct PatternColors;
+
+impl Flag for PatternColors {
+    fn name_long(&self) -> &'static str {
+        "pattern-colors"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-pattern-colors")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Highlight matches of each pattern with a different color."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.pattern_colors = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// -P/--pcre2
+/// 使用 PCRE2 正则引擎，等价于 --engine pcre2
+#[derive(Debug)]
+struct PCRE2;
+
+impl Flag for PCRE2 {
+    fn name_long(&self) -> &'static str {
+        "pcre2"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'P')
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-pcre2")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Search
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Enable PCRE2 matching."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.engine = if v.unwrap_switch() {
+            EngineChoice::PCRE2
+        } else {
+            EngineChoice::Default
+        };
+        Ok(())
+    }
+}
+
+/// --poll-interval
+/// --follow-file 读到文件末尾后检查新数据的间隔，单位毫秒
+#[derive(Debug)]
+struct PollInterval;
+
+impl Flag for PollInterval {
+    fn name_long(&self) -> &'static str {
+        "poll-interval"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"How often --follow-file checks for new data, in milliseconds."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        let millis = convert::u64(&v.unwrap_value())?;
+        args.poll_interval = Some(Duration::from_millis(millis));
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("MILLISECONDS")
+    }
+}
+
+/// --pre
+/// 每个文件先交给预处理命令，搜索命令的标准输出，比如用 pdftotext 搜索 PDF。
+/// 命令的参数是文件路径，标准输入是文件内容，命令失败时单独报告这个文件的错误并继续搜索其他文件
+#[derive(Debug)]
+struct Pre;
+
+impl Flag for Pre {
+    fn name_long(&self) -> &'static str {
+        "pre"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Search the output of COMMAND for each file."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        let path = PathBuf::from(v.unwrap_value());
+        // 和 ripgrep 一样，空的命令表示不使用预处理命令
+        args.pre = if path.as_os_str().is_empty() { None } else { Some(path) };
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("COMMAND")
+    }
+}
+
+/// --pre-glob
+/// 只有匹配 glob 的文件才交给 --pre 命令，其他文件直接搜索，可以重复指定，`!` 开头的 glob 排除匹配的文件
+#[derive(Debug)]
+struct PreGlob;
+
+impl Flag for PreGlob {
+    fn name_long(&self) -> &'static str {
+        "pre-glob"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Only use the --pre command for files matching GLOB."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.pre_glob.push(convert::string(v.unwrap_value())?);
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("GLOB")
+    }
+}
+
+/// -p/--pretty
+/// 适合在终端中查看的输出：按文件分组，标题带上文件中的匹配数，行号右对齐
+#[derive(Debug)]
+struct Pretty;
+
+impl Flag for Pretty {
+    fn name_long(&self) -> &'static str {
+        "pretty"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'p')
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-pretty")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Alias for --heading --line-number with match counts and aligned line numbers."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.pretty = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// --regex-size-limit
+/// 编译后正则的大小限制，很大的自动生成的选择（比如上万个关键字）可能超过默认的 100MB
+#[derive(Debug)]
+struct RegexSizeLimit;
+
+impl Flag for RegexSizeLimit {
+    fn name_long(&self) -> &'static str {
+        "regex-size-limit"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Search
+    }
+    fn doc_short(&self) -> &'static str {
+        r"The size limit of the compiled regex."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.regex_size_limit = Some(convert::human_readable_usize(&v.unwrap_value())?);
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("NUM+SUFFIX?")
+    }
+}
+
+/// -e/--regexp
+/// 基于正则表达式进行匹配查找
+#[derive(Debug)]
+struct Regexp;
+
+impl Flag for Regexp {
+    fn name_long(&self) -> &'static str {
+        "regexp"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'e')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"A pattern to search for."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        let regexp = convert::string(v.unwrap_value())?;
+        args.patterns.push(PatternSource::Regexp(regexp));
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("PATTERN")
+    }
+}
+
+/// --rules
+/// 按规则文件中的规则搜索，所有规则在一次搜索中匹配，结果按规则分组输出，会强制使用单线程搜索。
+/// 退出码取决于找到的结果中最高的严重程度：error 为 3，warning 为 1，没有结果或者只有 note/none 为 0
+#[derive(Debug)]
+struct Rules;
+
+impl Flag for Rules {
+    fn name_long(&self) -> &'static str {
+        "rules"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Search with the rules in the given TOML file. Exits with 3 if an error-level rule matched, 1 if a warning-level rule matched and 0 otherwise, even when nothing matched."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.rules = Some(PathBuf::from(v.unwrap_value()));
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("RULESFILE")
+    }
+}
+
+/// --sarif
+/// 以 SARIF 2.1.0 格式输出，每个模式是一条规则，每个匹配是一个结果，会强制使用单线程搜索
+#[derive(Debug)]
+struct Sarif;
+
+impl Flag for Sarif {
+    fn name_long(&self) -> &'static str {
+        "sarif"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-sarif")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Print results in the SARIF 2.1.0 format."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.sarif = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// --search-archives
+/// 把 tar、tar.gz 和 zip（包括 jar）中的文件展开成 `bundle.zip!/src/main.rs` 这样的虚拟路径，不解压到磁盘直接搜索
+#[derive(Debug)]
+struct SearchArchives;
+
+impl Flag for SearchArchives {
+    fn name_long(&self) -> &'static str {
+        "search-archives"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-search-archives")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Search files inside tar, tar.gz and zip archives."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.search_archives = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+// -r/--replace
+// 匹配后替换
+// #[derive(Debug)]
+// struct Replace;
+
+/// --sort
+/// 按指定方式升序排列搜索结果，多线程搜索时按排好的顺序输出
+#[derive(Debug)]
+struct Sort;
+
+impl Flag for Sort {
+    fn name_long(&self) -> &'static str {
+        "sort"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Sort results in ascending order."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.sort = sort_mode(convert::str(&v.unwrap_value())?, false)?;
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("SORTBY")
+    }
+    fn doc_choices(&self) -> &'static [&'static str] {
+        &["none", "path", "modified", "accessed", "created"]
+    }
+}
+
+/// --sortr
+/// 按指定方式降序排列搜索结果，多线程搜索时按排好的顺序输出
+#[derive(Debug)]
+struct Sortr;
+
+impl Flag for Sortr {
+    fn name_long(&self) -> &'static str {
+        "sortr"
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Sort results in descending order."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.sort = sort_mode(convert::str(&v.unwrap_value())?, true)?;
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("SORTBY")
+    }
+    fn doc_choices(&self) -> &'static [&'static str] {
+        &["none", "path", "modified", "accessed", "created"]
+    }
+}
+
+/// --sort 和 --sortr 共用的参数解析，"none" 表示不排序
+fn sort_mode(v: &str, reverse: bool) -> anyhow::Result<Option<SortMode>> {
+    let kind = match v {
+        "none" => return Ok(None),
+        "path" => SortModeKind::Path,
+        "modified" => SortModeKind::LastModified,
+        "accessed" => SortModeKind::LastAccessed,
+        "created" => SortModeKind::Created,
+        unk => anyhow::bail!("choice '{unk}' is unrecognized"),
+    };
+    Ok(Some(SortMode { reverse, kind }))
+}
+
+/// --stats
+/// 搜索结束后打印统计数据，比如匹配次数、搜索的文件数、耗时等
+#[derive(Debug)]
+struct Stats;
+
+impl Flag for Stats {
+    fn name_long(&self) -> &'static str {
+        "stats"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-stats")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Logging
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Print statistics about the search."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.stats = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// -j/--threads
+#[derive(Debug)]
+struct Threads;
+
+impl Flag for Threads {
+    fn name_long(&self) -> &'static str {
+        "threads"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'j')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Search
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Set the approximate number of threads to use."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        let threads = convert::usize(&v.unwrap_value())?;
+        args.threads = if threads == 0 { None } else { Some(threads) };
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("NUM")
+    }
+}
+
+/// -t/--type
+/// 只搜索指定类型的文件，可以重复指定，归档的类型是 tar 和 zip
+#[derive(Debug)]
+struct Type;
+
+impl Flag for Type {
+    fn name_long(&self) -> &'static str {
+        "type"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b't')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Input
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Only search files matching TYPE."
+    }
+    fn is_switch(&self) -> bool {
+        false
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.types.push(convert::string(v.unwrap_value())?);
+        Ok(())
+    }
+    fn doc_variable(&self) -> Option<&'static str> {
+        Some("TYPE")
+    }
+}
+
+/// --verbose-regex
+/// 忽略模式中的空白字符并允许 `#` 注释，相当于在每个模式前加上 `(?x)`
+#[derive(Debug)]
+struct VerboseRegex;
+
+impl Flag for VerboseRegex {
+    fn name_long(&self) -> &'static str {
+        "verbose-regex"
+    }
+    fn name_negated(&self) -> Option<&'static str> {
+        Some("no-verbose-regex")
+    }
+    fn doc_category(&self) -> Category {
+        Category::Search
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Ignore whitespace and allow comments in patterns."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        args.verbose_regex = v.unwrap_switch();
+        Ok(())
+    }
+}
+
+/// -H/--with-filename
+/// 打印匹配行时带上文件路径
+#[derive(Debug)]
+struct WithFilename;
+
+impl Flag for WithFilename {
+    fn name_long(&self) -> &'static str {
+        "with-filename"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'H')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Print the file path with each matching line."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        assert!(v.unwrap_switch(), "--with-filename has no defined negation");
+        args.with_filename = Some(true);
+        Ok(())
+    }
+}
+
+/// -I/--no-filename
+/// 打印匹配行时不带文件路径，以标题方式打印时也不打印标题
+#[derive(Debug)]
+struct WithFilenameNo;
+
+impl Flag for WithFilenameNo {
+    fn name_long(&self) -> &'static str {
+        "no-filename"
+    }
+    fn name_short(&self) -> Option<u8> {
+        Some(b'I')
+    }
+    fn doc_category(&self) -> Category {
+        Category::Output
+    }
+    fn doc_short(&self) -> &'static str {
+        r"Never print the path with each matching line."
+    }
+    fn update(&self, v: FlagValue, args: &mut LowArgs) -> anyhow::Result<()> {
+        assert!(v.unwrap_switch(), "--no-filename has no defined negation");
+        args.with_filename = Some(false);
+        Ok(())
+    }
+}
+
+mod convert {
+    use std::ffi::{OsStr, OsString};
+    use anyhow::Context;
+
+    /// OsStr 转 &str
+    pub(super) fn str(v: &OsStr) -> anyhow::Result<&str> {
+        let Some(s) = v.to_str() else {
+            anyhow::bail!("value is not valid UTF-8")
+        };
+        Ok(s)
+    }
+
+    /// OsString 转 String
+    pub(super) fn string(v: OsString) -> anyhow::Result<String> {
+        let Ok(s) = v.into_string() else {
+            anyhow::bail!("value is not valid UTF-8")
+        };
+        Ok(s)
+    }
+
+    /// str 转 usize
+    pub(super) fn usize(v: &OsStr) -> anyhow::Result<usize> {
+        str(v)?.parse().context("value is not a valid number")
+    }
+
+    /// str 转 u64
+    pub(super) fn u64(v: &OsStr) -> anyhow::Result<u64> {
+        str(v)?.parse().context("value is not a valid number")
+    }
+
+    /// 带单位后缀的大小转字节数，支持 K M G 后缀（1024 进制），没有后缀就是字节数，比如 10M
+    pub(super) fn human_readable_u64(v: &OsStr) -> anyhow::Result<u64> {
+        let s = str(v)?;
+        let (digits, shift) = match s.as_bytes().last() {
+            Some(b'K') => (&s[..s.len() - 1], 10),
+            Some(b'M') => (&s[..s.len() - 1], 20),
+            Some(b'G') => (&s[..s.len() - 1], 30),
+            _ => (s, 0),
+        };
+        let n: u64 = digits.parse().context("value is not a valid number")?;
+        let Some(size) = n.checked_mul(1 << shift) else {
+            anyhow::bail!("size '{s}' is too big")
+        };
+        Ok(size)
+    }
+
+    /// 和 human_readable_u64 一样，但是结果需要能放进 usize
+    pub(super) fn human_readable_usize(v: &OsStr) -> anyhow::Result<usize> {
+        let size = human_readable_u64(v)?;
+        let Ok(size) = usize::try_from(size) else {
+            anyhow::bail!("size '{size}' is too big")
+        };
+        Ok(size)
+    }
+}
+
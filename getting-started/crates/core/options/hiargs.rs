@@ -1,12 +1,23 @@
 use std::collections::HashSet;
 use std::env::current_dir;
 use std::path::{Path, PathBuf};
-use termcolor::{ColorChoice, StandardStream, WriteColor};
+use std::time::{Duration, SystemTime};
+use termcolor::{Color, ColorChoice, ColorSpec, StandardStream, WriteColor};
 use walkdir::WalkDir;
-use grep::printer::StandardBuilder;
-use grep::regex::RegexMatcherBuilder;
+use grep::matcher::LineTerminator;
+use grep::printer::{
+    BufferWriter, ColorSpecs, HyperlinkFormat, LintBuilder, SarifBuilder, SarifLevel, SarifRule, StandardBuilder,
+    Stats, TemplateBuilder, TemplateFormat,
+};
+use grep::regex::{ErrorKind, RegexMatcherBuilder};
 use grep::searcher::{Searcher, SearcherBuilder};
-use crate::options::lowargs::{CaseMode, LowArgs, Mode, PatternSource, SearchMode};
+use crate::options::lowargs::{
+    CaseMode, EngineChoice, LowArgs, Mode, PatternSource, SearchMode, SortMode, SortModeKind,
+};
+use crate::archive::{self, ArchiveKind};
+use crate::err_message;
+use crate::glob::PathFilter;
+use crate::rules::{read_rules, RuleFilter};
 use crate::search::{PatternMatcher, Printer, SearchWorker, SearchWorkerBuilder};
 
 /// HiArgs 是实际应用到各个组件的参数，LowArgs 中的参数类型一般都比较简单，在使用前基本需要进一步处理
@@ -21,16 +32,82 @@ pub(crate) struct HiArgs {
     case: CaseMode,
     // 颜色高亮输出颜色选择
     // color: ColorChoice,
+    /// 是否打印行首在文件中的字节偏移
+    byte_offset: bool,
     /// 是否打印匹配项在匹配行中的列数
     column: bool,
+    /// 是否使用 CRLF 作为行终止符
+    crlf: bool,
+    /// 当前工作目录，pretty 模式下用于把路径显示为相对路径
+    cwd: PathBuf,
+    /// 惰性 DFA 缓存的大小
+    dfa_size_limit: Option<usize>,
+    /// 使用的正则引擎
+    engine: EngineChoice,
+    /// 上下文行的字段分隔符
+    field_context_separator: Vec<u8>,
+    /// 匹配行的字段分隔符
+    field_match_separator: Vec<u8>,
+    /// 遍历目录时是否跟随符号链接
+    follow: bool,
+    /// 是否在搜索完文件后继续搜索追加的内容
+    follow_file: bool,
+    /// 按模板输出时使用的模板
+    format: Option<TemplateFormat>,
+    /// 按 -g/--glob -t/--type 过滤遍历到的文件
+    globs: PathFilter,
     /// 是否以标题的方式打印匹配文件路径
     heading: bool,
+    /// 行缓冲最多使用的堆内存
+    heap_limit: Option<usize>,
+    /// 文件路径的超链接格式，已经设置好了主机名
+    hyperlink_format: HyperlinkFormat,
     /// 是否打印匹配行在文件中的行号
     line_number: bool,
+    /// 超过这个字节数的行不完整输出
+    max_columns: Option<u64>,
+    /// 超过 max_columns 的行是否输出预览
+    max_columns_preview: bool,
+    /// 遍历目录的最大深度
+    max_depth: Option<usize>,
+    /// 跳过大于这个字节数的文件
+    max_filesize: Option<u64>,
+    /// 是否关闭正则的 Unicode 模式
+    no_unicode: bool,
+    /// 是否使用 NUL 作为行终止符
+    null_data: bool,
+    /// 遍历目录时是否不跨越文件系统
+    one_file_system: bool,
     /// 自定义的路径分隔符
     path_terminator: Option<u8>,
+    /// 是否按模式使用不同的颜色高亮匹配项
+    pattern_colors: bool,
+    /// --follow-file 检查新数据的间隔
+    poll_interval: Duration,
+    /// 预处理命令
+    pre: Option<PathBuf>,
+    /// 使用预处理命令的文件，为空时所有文件都使用预处理命令
+    pre_globs: PathFilter,
+    /// 是否以 pretty 模式输出
+    pretty: bool,
+    /// 编译后正则的大小限制
+    regex_size_limit: Option<usize>,
+    /// 是否按 --rules 指定的规则文件搜索
+    rules: bool,
+    /// 是否以 SARIF 格式输出
+    sarif: bool,
+    /// 是否把归档展开成其中的文件搜索
+    search_archives: bool,
+    /// 搜索结果的排序方式
+    sort: Option<SortMode>,
+    /// 是否统计并在搜索结束后打印统计数据
+    stats: bool,
     /// 搜索使用线程数量
     threads: usize,
+    /// 是否忽略模式中的空白和 `#` 注释
+    verbose_regex: bool,
+    /// 打印时是否带上文件路径
+    with_filename: bool,
 }
 
 impl HiArgs {
@@ -41,10 +118,22 @@ impl HiArgs {
         let patterns = Patterns::from_low_args(&mut state, &mut low)?;
         // 路径处理 (先从 positional 中找，没有就使用当期工作目录)
         let paths = Paths::from_low_args(&mut state, &patterns, &mut low)?;
+        // 规则的结果按规则分组输出，不能按模板输出
+        if low.rules.is_some() && low.format.is_some() {
+            anyhow::bail!("--rules cannot be used with --format");
+        }
+        // 输出模板有错误时在开始搜索前报错
+        let format = match low.format {
+            None => None,
+            Some(ref template) => match template.parse::<TemplateFormat>() {
+                Ok(format) => Some(format),
+                Err(err) => anyhow::bail!("invalid --format template '{template}': {err}"),
+            },
+        };
         // 是否打印匹配项列号
         let column = low.column.unwrap_or(false);
         // 是否按标题形式打印所属文件路径
-        let heading = low.heading.unwrap_or(false);
+        let heading = low.heading.unwrap_or(low.pretty);
         // 是否打印匹配行行号
         let line_number = low.line_number.unwrap_or_else(|| {   //即便没设置，如果设置的是标准搜索模式且打印列号就也打印行号
             let Mode::Search(ref search_mode) = low.mode else { return false };
@@ -52,32 +141,116 @@ impl HiArgs {
                 SearchMode::Standard => {
                     state.is_terminal_stdout
                         || column
+                        || low.pretty
+                        || low.sarif
+                        || low.rules.is_some()
+                        || format.as_ref().is_some_and(|f| f.needs_line_number())
                 }
             }
         });
-        // 搜索线程数，多线程搜索只是适用于多文件搜索（ripgrep 对于结果需要排序的情况也不使用多线程搜索）
-        let threads = if paths.is_one_file {
+        // 搜索线程数，多线程搜索只是适用于多文件搜索，需要排序时文件先排好序，多线程搜索后按文件的顺序输出
+        // SARIF 整个搜索只输出一个文档，--rules 所有搜索结束后才按规则分组输出，所有结果都要写到同一个 Printer，所以只能单线程搜索
+        let threads = if paths.is_one_file || low.sarif || low.rules.is_some() {
             1
         } else if let Some(threads) = low.threads {
-            1
-            // threads  //TODO
+            threads
         } else {
             // 没有设置使用多少线程数且是多文件搜索，就选择 min(CPU核心数,12)
-            // std::thread::available_parallelism().map_or(1, |n| n.get()).min(12)
-            1           //TODO
+            std::thread::available_parallelism().map_or(1, |n| n.get()).min(12)
+        };
+
+        // 没有设置时默认限制为 4096 字节，设置为 0 表示不限制
+        let max_columns = match low.max_columns {
+            None => Some(4096),
+            Some(0) => None,
+            Some(n) => Some(n),
         };
 
+        // 只有格式中用到 {host} 时主机名才有意义，不过获取主机名开销很小，直接设置
+        let hyperlink_format = if low.hyperlink_format.is_empty() {
+            low.hyperlink_format
+        } else {
+            low.hyperlink_format.with_host(hostname())
+        };
+
+        // --follow-file 会一直阻塞在一个文件上，所以只能搜索一个文件
+        if low.follow_file && !paths.is_one_file {
+            anyhow::bail!("--follow-file requires exactly one file path to search");
+        }
+        // 预处理命令的输出不会增长，不能继续搜索追加的内容
+        if low.follow_file && low.pre.is_some() {
+            anyhow::bail!("--follow-file cannot be used with --pre");
+        }
+        if low.follow_file && low.search_archives {
+            anyhow::bail!("--follow-file cannot be used with --search-archives");
+        }
+        // pretty 模式要在标题中输出匹配数并按最大行号对齐，文件搜索结束后才输出，而 --follow-file 的搜索一直不会结束
+        if low.follow_file && low.pretty {
+            anyhow::bail!("--follow-file cannot be used with --pretty");
+        }
+        let mut globs = PathFilter::default();
+        for glob in low.globs.iter() {
+            if let Err(err) = globs.add_glob(glob) {
+                anyhow::bail!("invalid --glob: {err}");
+            }
+        }
+        for name in low.types.iter() {
+            if let Err(err) = globs.add_type(name) {
+                anyhow::bail!("invalid --type: {err}");
+            }
+        }
+        let mut pre_globs = PathFilter::default();
+        for glob in low.pre_glob.iter() {
+            if let Err(err) = pre_globs.add_glob(glob) {
+                anyhow::bail!("invalid --pre-glob: {err}");
+            }
+        }
+
         Ok(HiArgs {
             mode: low.mode,
             patterns,
             paths,
             case: low.case,
             // color,
+            byte_offset: low.byte_offset,
             column,
+            crlf: low.crlf,
+            cwd: state.cwd,
+            dfa_size_limit: low.dfa_size_limit,
+            engine: low.engine,
+            field_context_separator: low.field_context_separator.unwrap_or_else(|| b"-".to_vec()),
+            field_match_separator: low.field_match_separator.unwrap_or_else(|| b":".to_vec()),
+            follow: low.follow,
+            follow_file: low.follow_file,
+            format,
+            globs,
             heading,
+            heap_limit: low.heap_limit,
+            hyperlink_format,
             line_number,
+            max_columns,
+            max_columns_preview: low.max_columns_preview,
+            max_depth: low.max_depth,
+            max_filesize: low.max_filesize,
+            no_unicode: low.no_unicode,
+            null_data: low.null_data,
+            one_file_system: low.one_file_system,
             path_terminator: low.path_separator,
+            pattern_colors: low.pattern_colors,
+            poll_interval: low.poll_interval.unwrap_or(Duration::from_millis(250)),
+            pre: low.pre,
+            pre_globs,
+            pretty: low.pretty,
+            regex_size_limit: low.regex_size_limit,
+            rules: low.rules.is_some(),
+            sarif: low.sarif,
+            search_archives: low.search_archives,
+            sort: low.sort,
+            stats: low.stats,
             threads,
+            verbose_regex: low.verbose_regex,
+            // 和之前的行为保持一致，默认总是带上文件路径
+            with_filename: low.with_filename.unwrap_or(true),
         })
     }
 
@@ -92,39 +265,177 @@ impl HiArgs {
         true
     }
 
+    /// 开启了 --stats 时返回一个空的统计数据，用于汇总每次搜索的统计结果
+    pub(crate) fn stats(&self) -> Option<Stats> {
+        if self.stats {
+            Some(Stats::new())
+        } else {
+            None
+        }
+    }
+
     pub(crate) fn threads(&self) -> usize {
         self.threads
     }
 
+    /// 是否按 --rules 指定的规则文件搜索，这时退出码取决于结果中最高的严重程度
+    pub(crate) fn rules(&self) -> bool {
+        self.rules
+    }
+
+    /// 每个模式对应一条规则，没有在模式文件或规则文件中设置 id 时使用 `pattern-N`（N 从 1 开始）
+    fn sarif_rules(&self) -> Vec<SarifRule> {
+        let patterns = &self.patterns;
+        patterns.patterns.iter().zip(patterns.meta.iter()).enumerate()
+            .map(|(i, (pattern, meta))| {
+                let id = meta.id.clone().unwrap_or_else(|| format!("pattern-{}", i + 1));
+                SarifRule::new(id, pattern.clone())
+                    .level(meta.severity.unwrap_or_default())
+                    .message(meta.message.clone())
+            })
+            .collect()
+    }
+
     pub(crate) fn search_worker<W: WriteColor>(
         &self,
         matcher: PatternMatcher,
         searcher: grep::searcher::Searcher,
         printer: Printer<W>,
     ) -> anyhow::Result<SearchWorker<W>> {
-        let builder = SearchWorkerBuilder::new();
+        let mut builder = SearchWorkerBuilder::new();
+        builder.follow_file(self.follow_file.then_some(self.poll_interval))
+            .preprocessor(self.pre.clone(), self.pre_globs.clone())
+            .search_archives(self.search_archives)
+            .rule_filter(self.patterns.rule_filter.clone())
+            .rule_matchers(self.rule_matchers(&matcher)?);
         Ok(builder.build(searcher, matcher, printer))
     }
 
+    /// SARIF 和 --rules 的每个模式是一条规则，每条规则单独构建一个和 matcher 使用相同引擎的匹配器，
+    /// 合并的匹配器只用来找出匹配行，同一位置只会报告序号最小的模式，所以匹配行中的每条规则要分别查找
+    fn rule_matchers(&self, matcher: &PatternMatcher) -> anyhow::Result<Vec<PatternMatcher>> {
+        let patterns = &self.patterns.patterns;
+        if !(self.sarif || self.rules) || patterns.len() < 2 {
+            return Ok(vec![]);
+        }
+        patterns.iter()
+            .map(|pattern| match *matcher {
+                PatternMatcher::RustRegex(_) => self.matcher_rust(std::slice::from_ref(pattern)),
+                #[cfg(feature = "pcre2")]
+                PatternMatcher::PCRE2(_) => self.matcher_pcre2(std::slice::from_ref(pattern)),
+            })
+            .collect()
+    }
+
     pub(crate) fn matcher(&self) -> anyhow::Result<PatternMatcher> {
+        match self.engine {
+            EngineChoice::Default => match self.matcher_rust(&self.patterns.patterns) {
+                Ok(m) => Ok(m),
+                Err(err) => {
+                    anyhow::bail!("error build matcher: {}", err);
+                }
+            },
+            EngineChoice::PCRE2 => Ok(self.matcher_pcre2(&self.patterns.patterns)?),
+            EngineChoice::Auto => {
+                // 先尝试 Rust Regex，失败（比如使用了 look-around）再尝试 PCRE2
+                let rust_err = match self.matcher_rust(&self.patterns.patterns) {
+                    Ok(m) => return Ok(m),
+                    Err(err) => err,
+                };
+                log::debug!(
+                    "error building Rust regex in hybrid mode:\n{}",
+                    rust_err,
+                );
+                let pcre_err = match self.matcher_pcre2(&self.patterns.patterns) {
+                    Ok(m) => return Ok(m),
+                    Err(err) => err,
+                };
+                let divider = "~".repeat(79);
+                anyhow::bail!(
+                    "regex could not be compiled with either the default \
+                     regex engine or with PCRE2.\n\n\
+                     default regex engine error:\n{divider}\n{rust_err}\n\
+                     {divider}\n\n\
+                     PCRE2 regex engine error:\n{divider}\n{pcre_err}",
+                );
+            }
+        }
+    }
+
+    /// 使用 Rust Regex 引擎构建匹配器
+    fn matcher_rust(&self, patterns: &[String]) -> anyhow::Result<PatternMatcher> {
         let mut builder = RegexMatcherBuilder::new();
         match self.case {
-            CaseMode::Sensitive => builder.case_insensitive(true),
-            CaseMode::Insensitive => builder.case_insensitive(false),
+            CaseMode::Sensitive => builder.case_insensitive(false),
+            CaseMode::Insensitive => builder.case_insensitive(true),
             CaseMode::Smart => builder.case_smart(true),
         };
-        let m = match builder.build_many(&self.patterns.patterns) { // String 实现了 AsRef<str>
+        // 按行搜索，`^` `$` 匹配每一行的开始和结束；同时告诉匹配器行终止符，保证匹配结果不会跨行
+        builder.multi_line(true).line_terminator(Some(b'\n'));
+        if self.crlf {
+            builder.crlf(true);
+        }
+        if self.null_data {
+            builder.line_terminator(Some(b'\x00'));
+        }
+        if let Some(limit) = self.regex_size_limit {
+            builder.size_limit(limit);
+        }
+        if let Some(limit) = self.dfa_size_limit {
+            builder.dfa_size_limit(limit);
+        }
+        builder.unicode(!self.no_unicode).ignore_whitespace(self.verbose_regex);
+        let m = match builder.build_many(patterns) { // String 实现了 AsRef<str>
             Ok(m) => m,
-            Err(err) => {
-                anyhow::bail!("error build matcher: {}", err.to_string());
+            // 超过大小限制时提示用户可以调大哪个选项
+            Err(err) if matches!(err.kind(), ErrorKind::SizeLimitExceeded(_)) => {
+                anyhow::bail!("{err} (use --regex-size-limit to increase it)")
             }
+            Err(err) => return Err(err.into()),
         };
         Ok(PatternMatcher::RustRegex(m))
     }
 
+    /// 使用 PCRE2 引擎构建匹配器
+    #[cfg(feature = "pcre2")]
+    fn matcher_pcre2(&self, patterns: &[String]) -> anyhow::Result<PatternMatcher> {
+        let mut builder = grep::pcre2::RegexMatcherBuilder::new();
+        match self.case {
+            CaseMode::Sensitive => builder.case_insensitive(false),
+            CaseMode::Insensitive => builder.case_insensitive(true),
+            CaseMode::Smart => builder.case_smart(true),
+        };
+        // PCRE2 的匹配器不报告行终止符，Searcher 会逐行调用它，所以这里的多行模式只影响 `^` `$` 的语义
+        builder.multi_line(true).utf(!self.no_unicode);
+        if self.crlf {
+            builder.crlf(true);
+        }
+        if self.null_data {
+            builder.line_terminator(b'\x00');
+        }
+        let m = builder.build_many(patterns)?;
+        Ok(PatternMatcher::PCRE2(m))
+    }
+
+    /// 没有开启 pcre2 feature 时总是返回错误
+    #[cfg(not(feature = "pcre2"))]
+    fn matcher_pcre2(&self, _patterns: &[String]) -> anyhow::Result<PatternMatcher> {
+        anyhow::bail!("PCRE2 is not available in this build of gs.")
+    }
+
     pub(crate) fn searcher(&self) -> anyhow::Result<Searcher> {
+        // 行终止符需要和 matcher() 中设置的保持一致，否则搜索时会报 ConfigError::MismatchedLineTerminators
+        let line_term = if self.crlf {
+            LineTerminator::crlf()
+        } else if self.null_data {
+            LineTerminator::byte(b'\x00')
+        } else {
+            LineTerminator::byte(b'\n')
+        };
         let mut builder = SearcherBuilder::new();
-        builder.line_number(self.line_number);
+        builder.line_number(self.line_number)
+            .line_terminator(line_term)
+            .heap_limit(self.heap_limit);
         Ok(builder.build())
     }
 
@@ -133,49 +444,180 @@ impl HiArgs {
         _: SearchMode,
         wtr: W,
     ) -> Printer<W> {
+        if self.sarif {
+            let sarif = SarifBuilder::new()
+                .tool("gs".to_string(), Some(env!("CARGO_PKG_VERSION").to_string()))
+                .rules(self.sarif_rules())
+                .stats(self.stats)
+                .build(wtr);
+            return Printer::Sarif(sarif);
+        }
+        if self.rules {
+            let lint = LintBuilder::new()
+                .rules(self.sarif_rules())
+                .path(self.with_filename)
+                .stats(self.stats)
+                .build(wtr);
+            return Printer::Lint(lint);
+        }
+        if let Some(ref format) = self.format {
+            let template = TemplateBuilder::new()
+                .format(format.clone())
+                .path(self.with_filename)
+                .stats(self.stats)
+                .build(wtr);
+            return Printer::Template(template);
+        }
+        let mut colors = ColorSpecs::default();
+        if self.pattern_colors {
+            colors = colors.with_pattern_colors(pattern_color_specs());
+        }
         let standard = StandardBuilder::new()
+            .color_specs(colors)
             .column(self.column)
+            .byte_offset(self.byte_offset)
+            .path(self.with_filename)
+            .separator_field_match(self.field_match_separator.clone())
+            .separator_field_context(self.field_context_separator.clone())
             .heading(self.heading)
+            // 多线程搜索时不同文件之间的空行由 BufferWriter 输出
+            .heading_separator(self.threads == 1)
             .path_terminator(self.path_terminator.clone())
-            .max_columns(Some(4096))
+            .max_columns(self.max_columns)
+            .max_columns_preview(self.max_columns_preview)
             .trim_ascii(true)
+            .stats(self.stats)
+            .hyperlink(self.hyperlink_format.clone())
+            .pretty(self.pretty)
             .build(wtr);
         Printer::Standard(standard)
     }
 
     pub(crate) fn paths(&mut self) -> Vec<PathBuf> {
+        let paths = self.search_paths();
+        if !self.pretty {
+            return paths;
+        }
+        // pretty 模式下输出相对路径，路径同时用于打开文件，相对于当前工作目录仍然指向同一个文件
+        paths.into_iter().map(|path| relative_path(&self.cwd, path)).collect()
+    }
+
+    /// 待搜索的所有文件路径，目录会被递归遍历，开启 --search-archives 时归档会展开成其中文件的虚拟路径
+    fn search_paths(&mut self) -> Vec<PathBuf> {
         let mut file_paths = Vec::new();
-        if self.paths.is_one_file { //如果只是一个文件
-            file_paths.push(self.paths.paths[0].clone());
+        if self.paths.is_one_file && self.archive_kind(&self.paths.paths[0]).is_none() { //如果只是一个文件
+            self.push_file(self.paths.paths[0].clone(), true, &mut file_paths);
             return file_paths;
         }
         // 如果是目录，需要递归遍历目录，获取所有文件的路径
-        for path in self.paths.paths.drain(..) {
+        let paths = std::mem::take(&mut self.paths.paths);
+        for path in paths {
             if path.is_file() {
-                file_paths.push(path);
+                // 和 ripgrep 一样，命令行中直接给出的文件不经过 -g/--glob -t/--type 过滤
+                self.push_file(path, true, &mut file_paths);
                 continue;
             }
-            // 使用 WalkDir 遍历目录
-            for entry in WalkDir::new(path) {
+            // 使用 WalkDir 遍历目录，跟随符号链接时 WalkDir 会检测循环并以错误的形式返回
+            for entry in self.walker(path) {
                 let entry = match entry {
                     Ok(e) => e,
-                    Err(e) => {
-                        eprintln!("Error accessing entry: {}", e);
+                    Err(err) => {
+                        err_message!("{}", err);
                         continue;
                     }
                 };
-                // 确保是文件
-                if entry.file_type().is_file() {
-                    file_paths.push(entry.path().to_path_buf());
+                // 确保是文件（跟随符号链接时 file_type() 是链接目标的类型）
+                if !entry.file_type().is_file() {
+                    continue;
                 }
+                self.push_file(entry.into_path(), false, &mut file_paths);
             }
         }
+        if let Some(ref sort) = self.sort {
+            sort_paths(sort, &mut file_paths);
+        }
         file_paths
     }
 
+    /// 开启 --search-archives 时文件的归档格式
+    fn archive_kind(&self, path: &Path) -> Option<ArchiveKind> {
+        if !self.search_archives {
+            return None;
+        }
+        ArchiveKind::from_path(path)
+    }
+
+    /// 添加一个待搜索的文件，归档展开成其中的文件，归档中的文件总是经过 -g/--glob -t/--type 过滤
+    /// --max-filesize 对命令行中直接给出的文件同样生效，归档按其中每个文件的大小判断是否跳过
+    fn push_file(&self, path: PathBuf, explicit: bool, file_paths: &mut Vec<PathBuf>) {
+        let Some(kind) = self.archive_kind(&path) else {
+            if (explicit || self.globs.is_match(&path)) && !self.skip_filesize(&path) {
+                file_paths.push(path);
+            }
+            return;
+        };
+        let entries = match archive::list_entries(&path, kind) {
+            Ok(entries) => entries,
+            Err(err) => {
+                err_message!("{}: {}", path.display(), err);
+                return;
+            }
+        };
+        for entry in entries {
+            let entry_path = archive::virtual_path(&path, &entry.name);
+            if self.max_filesize.is_some_and(|max_filesize| entry.size > max_filesize) {
+                log::debug!("ignoring {}: {} bytes exceeds --max-filesize", entry_path.display(), entry.size);
+                continue;
+            }
+            if self.globs.is_match(&entry_path) {
+                file_paths.push(entry_path);
+            }
+        }
+    }
+
+    /// 根据 --max-depth -L/--follow --one-file-system 创建目录遍历器
+    fn walker(&self, path: PathBuf) -> WalkDir {
+        let mut walker = WalkDir::new(path)
+            .follow_links(self.follow)
+            .same_file_system(self.one_file_system);
+        if let Some(max_depth) = self.max_depth {
+            walker = walker.max_depth(max_depth);
+        }
+        walker
+    }
+
+    /// 是否因为 --max-filesize 跳过文件，获取不到文件大小时不跳过，交给后面的搜索报错
+    fn skip_filesize(&self, path: &Path) -> bool {
+        let Some(max_filesize) = self.max_filesize else { return false };
+        match std::fs::metadata(path) {
+            Ok(md) if md.len() > max_filesize => {
+                log::debug!(
+                    "ignoring {}: {} bytes exceeds --max-filesize {}",
+                    path.display(),
+                    md.len(),
+                    max_filesize,
+                );
+                true
+            }
+            _ => false,
+        }
+    }
+
     pub(crate) fn stdout(&self) -> StandardStream {
         StandardStream::stdout(ColorChoice::Auto)
     }
+
+    /// 多线程搜索时的输出，每个搜索线程通过 buffer() 创建自己的缓冲
+    pub(crate) fn buffer_writer(&self) -> BufferWriter {
+        let mut wtr = BufferWriter::stdout(ColorChoice::Auto);
+        // 和单线程搜索一样，以标题的方式打印时不同文件的结果之间空一行，按模板输出时没有标题
+        let line_term = if self.null_data { b'\x00' } else { b'\n' };
+        let heading = self.heading && self.with_filename && self.format.is_none();
+        wtr.separator(heading.then(|| vec![line_term]));
+        // 待搜索的文件已经排好序，按文件序号输出就是排好序的结果
+        wtr.ordered(self.sort.is_some());
+        wtr
+    }
 }
 
 #[derive(Debug)]
@@ -203,15 +645,46 @@ impl State {
 struct Patterns {
     // 匹配用的 pattern
     patterns: Vec<String>,
+    /// 每个 pattern 的元数据，和 patterns 一一对应
+    meta: Vec<PatternMeta>,
+    /// 规则文件中限定了适用文件的规则，搜索每个文件前用于算出哪些规则适用
+    rule_filter: Option<RuleFilter>,
+}
+
+/// 模式文件中通过 `#@ id=...; severity=...; message=...` 设置的模式元数据，-e 指定的模式没有元数据
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+struct PatternMeta {
+    /// 规则 id
+    id: Option<String>,
+    /// 严重程度
+    severity: Option<SarifLevel>,
+    /// 匹配时的说明
+    message: Option<String>,
 }
 
 impl Patterns {
     // LowArgs patterns -> HiArgs patterns
     // 优先使用 -e/--regexp 指定的正则表达式，没有就使用 positional args 中的第一个参数
     fn from_low_args(state: &mut State, low: &mut LowArgs) -> anyhow::Result<Patterns> {
-        //除了 Search 模式其他不需要 Pattern
-        if !matches!(low.mode, Mode::Search(_)) {
-            return Ok(Patterns { patterns: vec![] });
+        //除了 Search 模式（以及诊断正则的 DebugRegex 模式）其他不需要 Pattern
+        if !matches!(low.mode, Mode::Search(_) | Mode::DebugRegex) {
+            return Ok(Patterns { patterns: vec![], meta: vec![], rule_filter: None });
+        }
+        // 使用规则文件时 positional args 都是路径
+        if let Some(ref path) = low.rules {
+            anyhow::ensure!(
+                low.patterns.is_empty(),
+                "--rules cannot be combined with -e/--regexp or -f/--file"
+            );
+            let rules = read_rules(path)?;
+            let rule_filter = RuleFilter::new(&rules);
+            let (patterns, meta) = rules.into_iter()
+                .map(|rule| {
+                    let meta = PatternMeta { id: Some(rule.id), severity: rule.severity, message: rule.message };
+                    (rule.pattern, meta)
+                })
+                .unzip();
+            return Ok(Patterns { patterns, meta, rule_filter });
         }
         if low.patterns.is_empty() {    //即没有通过 -e/--regexp 指定正则表达式, 选择 positional 中的第一个参数
             anyhow::ensure!(
@@ -222,27 +695,80 @@ impl Patterns {
             let Ok(pattern) = os_pattern.into_string() else {
                 anyhow::bail!("pattern given is not valid UTF-8");
             };
-            return Ok(Patterns { patterns: vec![pattern] });
+            return Ok(Patterns { patterns: vec![pattern], meta: vec![PatternMeta::default()], rule_filter: None });
         }
         //使用 -e/--regexp 指定的正则表达式
         //去重并转 PatternSource -> String
         let mut seen = HashSet::new();
         let mut patterns = Vec::with_capacity(low.patterns.len());
+        let mut meta = Vec::with_capacity(low.patterns.len());
+        let mut ids = HashSet::new();
         // for source in low.patterns {  //这种方式会导致low.pattens所有权转移
         for source in low.patterns.drain(..) {
-            match source {
-                PatternSource::Regexp(pat) => {
-                    if !seen.contains(&pat) {
-                        seen.insert(pat.clone());
-                        patterns.push(pat);
+            let entries = match source {
+                PatternSource::Regexp(pat) => vec![(pat, PatternMeta::default())],
+                PatternSource::File(path) => read_pattern_file(&path)?,
+            };
+            // 重复的模式只保留第一个（连同它的元数据）
+            for (pat, pat_meta) in entries {
+                if !seen.contains(&pat) {
+                    if let Some(ref id) = pat_meta.id {
+                        anyhow::ensure!(ids.insert(id.clone()), "duplicate pattern id '{id}'");
                     }
+                    seen.insert(pat.clone());
+                    patterns.push(pat);
+                    meta.push(pat_meta);
                 }
             }
         }
-        Ok(Patterns { patterns })
+        Ok(Patterns { patterns, meta, rule_filter: None })
     }
 }
 
+/// 读取模式文件，每行一个模式，`#@` 开头的行设置下一个模式的元数据，比如：
+/// ```text
+/// #@ id=no-todo; severity=warning; message=TODO left in code
+/// TODO
+/// ```
+fn read_pattern_file(path: &Path) -> anyhow::Result<Vec<(String, PatternMeta)>> {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) => anyhow::bail!("{}: {}", path.display(), err),
+    };
+    let Ok(contents) = String::from_utf8(contents) else {
+        anyhow::bail!("{}: pattern file is not valid UTF-8", path.display());
+    };
+    let mut entries = vec![];
+    let mut meta: Option<PatternMeta> = None;
+    for (i, line) in contents.lines().enumerate() {
+        let Some(directives) = line.strip_prefix("#@") else {
+            entries.push((line.to_string(), meta.take().unwrap_or_default()));
+            continue;
+        };
+        let meta = meta.get_or_insert_with(PatternMeta::default);
+        for directive in directives.split(';').map(str::trim).filter(|d| !d.is_empty()) {
+            let location = format!("{}:{}", path.display(), i + 1);
+            let Some((key, value)) = directive.split_once('=') else {
+                anyhow::bail!("{location}: pattern metadata '{directive}' must look like key=value");
+            };
+            let value = value.trim().to_string();
+            match key.trim() {
+                "id" => meta.id = Some(value),
+                "message" => meta.message = Some(value),
+                "severity" => match value.parse() {
+                    Ok(severity) => meta.severity = Some(severity),
+                    Err(err) => anyhow::bail!("{location}: {err}"),
+                },
+                key => anyhow::bail!(
+                    "{location}: unknown pattern metadata key '{key}', expected id, severity or message"
+                ),
+            }
+        }
+    }
+    anyhow::ensure!(meta.is_none(), "{}: pattern metadata is not followed by a pattern", path.display());
+    Ok(entries)
+}
+
 #[derive(Debug, Clone)]
 struct Paths {
     /// 待搜索的路径
@@ -271,4 +797,339 @@ impl Paths {
         log::debug!("heuristic chose to search ./");
         Ok(Paths { paths: vec![PathBuf::from("./")], has_implicit_path: true, is_one_file: false })
     }
-}
\ No newline at end of file
+}
+
+/// 去掉路径开头的 `./`，当前工作目录下的绝对路径转成相对路径，其他路径保持不变
+fn relative_path(cwd: &Path, path: PathBuf) -> PathBuf {
+    let relative = if path.is_absolute() {
+        path.strip_prefix(cwd).unwrap_or(&path)
+    } else {
+        path.strip_prefix(".").unwrap_or(&path)
+    };
+    if relative.as_os_str().is_empty() {
+        return path;
+    }
+    relative.to_path_buf()
+}
+
+/// 按 --sort/--sortr 指定的方式对待搜索的文件排序
+/// 获取不到时间戳的文件（比如文件系统不支持创建时间）不会报错，而是统一排在最前面（倒序时排在最后），
+/// 时间戳相同时按路径排序，保证输出顺序是确定的
+fn sort_paths(sort: &SortMode, paths: &mut Vec<PathBuf>) {
+    if sort.kind == SortModeKind::Path {
+        paths.sort();
+    } else {
+        let mut keyed: Vec<(Option<SystemTime>, PathBuf)> = paths
+            .drain(..)
+            .map(|path| (file_time(sort.kind, &path), path))
+            .collect();
+        keyed.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        paths.extend(keyed.into_iter().map(|(_, path)| path));
+    }
+    if sort.reverse {
+        paths.reverse();
+    }
+}
+
+/// 获取文件对应的时间戳，获取失败返回 None，归档中的文件使用归档的时间戳
+fn file_time(kind: SortModeKind, path: &Path) -> Option<SystemTime> {
+    let archive = archive::split_virtual_path(path).map(|(archive, _)| archive);
+    let path = archive.as_deref().unwrap_or(path);
+    let md = match path.metadata() {
+        Ok(md) => md,
+        Err(err) => {
+            log::debug!("{}: failed to read metadata for sorting: {}", path.display(), err);
+            return None;
+        }
+    };
+    let time = match kind {
+        SortModeKind::Path => return None,
+        SortModeKind::LastModified => md.modified(),
+        SortModeKind::LastAccessed => md.accessed(),
+        SortModeKind::Created => md.created(),
+    };
+    match time {
+        Ok(time) => Some(time),
+        Err(err) => {
+            log::debug!("{}: {:?} time unavailable for sorting: {}", path.display(), kind, err);
+            None
+        }
+    }
+}
+
+/// --pattern-colors 使用的颜色，模式比颜色多时循环使用
+fn pattern_color_specs() -> Vec<ColorSpec> {
+    [Color::Red, Color::Green, Color::Blue, Color::Yellow, Color::Cyan, Color::Magenta]
+        .into_iter()
+        .map(|color| {
+            let mut spec = ColorSpec::new();
+            spec.set_fg(Some(color)).set_bold(true);
+            spec
+        })
+        .collect()
+}
+
+/// 超链接格式中 `{host}` 使用的主机名，获取不到时为 None
+fn hostname() -> Option<String> {
+    ["/proc/sys/kernel/hostname", "/etc/hostname"]
+        .into_iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .map(|name| name.trim().to_string())
+        .find(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pattern_file_metadata() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let read = |contents: &str| {
+            std::fs::write(file.path(), contents).unwrap();
+            read_pattern_file(file.path())
+        };
+        let entries = read("#@ id=no-todo; severity=error\n#@ message=TODO left\nTODO\\b\r\nFIXME\n").unwrap();
+        let meta = PatternMeta {
+            id: Some("no-todo".to_string()),
+            severity: Some(SarifLevel::Error),
+            message: Some("TODO left".to_string()),
+        };
+        assert_eq!(
+            vec![("TODO\\b".to_string(), meta), ("FIXME".to_string(), PatternMeta::default())],
+            entries,
+        );
+        let err = |contents: &str| format!("{:#}", read(contents).unwrap_err());
+        assert!(err("#@ severity=fatal\nx\n").contains(":1: invalid severity 'fatal'"));
+        assert!(err("x\n#@ level=error\ny\n").contains(":2: unknown pattern metadata key 'level'"));
+        assert!(err("#@ id\nx\n").contains("must look like key=value"));
+        assert!(err("x\n#@ id=a\n").contains("not followed by a pattern"));
+    }
+
+    /// --follow-file 不能和需要整个文件搜索结束才能输出的选项一起使用
+    #[test]
+    fn follow_file_conflicts() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "foo\n").unwrap();
+        let error = |low: LowArgs| {
+            let low = LowArgs {
+                patterns: vec![PatternSource::Regexp("foo".to_string())],
+                positional: vec![file.path().as_os_str().to_owned()],
+                follow_file: true,
+                ..low
+            };
+            HiArgs::from_low_args(low).err().map(|err| err.to_string())
+        };
+        assert_eq!(None, error(LowArgs::default()));
+        assert_eq!(
+            Some("--follow-file cannot be used with --pretty".to_string()),
+            error(LowArgs { pretty: true, ..LowArgs::default() }),
+        );
+        assert_eq!(
+            Some("--follow-file cannot be used with --search-archives".to_string()),
+            error(LowArgs { search_archives: true, ..LowArgs::default() }),
+        );
+    }
+
+    /// 按时间排序时时间戳相同的按路径排序，获取不到时间戳的排在最前面（倒序时排在最后），归档中的文件使用归档的时间戳
+    #[test]
+    fn sort_by_time() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let touch = |name: &str, secs: u64| {
+            let path = dir.join(name);
+            let file = std::fs::File::create(&path).unwrap();
+            file.set_modified(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(secs)).unwrap();
+            path
+        };
+        let newest = touch("a.txt", 3000);
+        let tied_b = touch("b.txt", 1000);
+        let tied_c = touch("c.txt", 1000);
+        let archive = touch("bundle.tar", 2000);
+        let entry = archive::virtual_path(&archive, "src/lib.rs");
+        let missing = dir.join("missing.txt");
+        let sorted = |kind: SortModeKind, reverse: bool| {
+            let mut paths = vec![newest.clone(), tied_c.clone(), entry.clone(), missing.clone(), tied_b.clone()];
+            sort_paths(&SortMode { reverse, kind }, &mut paths);
+            paths
+        };
+        let expected = vec![missing.clone(), tied_b.clone(), tied_c.clone(), entry.clone(), newest.clone()];
+        assert_eq!(expected, sorted(SortModeKind::LastModified, false));
+        let reversed: Vec<PathBuf> = expected.into_iter().rev().collect();
+        assert_eq!(reversed, sorted(SortModeKind::LastModified, true));
+        let by_path = vec![newest.clone(), tied_b.clone(), entry.clone(), tied_c.clone(), missing.clone()];
+        assert_eq!(by_path, sorted(SortModeKind::Path, false));
+        let reversed: Vec<PathBuf> = by_path.into_iter().rev().collect();
+        assert_eq!(reversed, sorted(SortModeKind::Path, true));
+    }
+
+    /// 多文件搜索默认使用多线程，需要排序时同样使用多线程
+    #[test]
+    fn threads() {
+        let dir = tempfile::tempdir().unwrap();
+        let threads = |sort: Option<SortMode>, threads: Option<usize>| {
+            let low = LowArgs {
+                patterns: vec![PatternSource::Regexp("foo".to_string())],
+                positional: vec![dir.path().as_os_str().to_owned()],
+                sort,
+                threads,
+                ..LowArgs::default()
+            };
+            HiArgs::from_low_args(low).unwrap().threads()
+        };
+        let cpus = std::thread::available_parallelism().map_or(1, |n| n.get()).min(12);
+        assert_eq!(cpus, threads(None, None));
+        assert_eq!(3, threads(None, Some(3)));
+        let sort = SortMode { reverse: false, kind: SortModeKind::Path };
+        assert_eq!(cpus, threads(Some(sort), None));
+        assert_eq!(3, threads(Some(sort), Some(3)));
+    }
+
+    /// 规则之间互不遮挡：不适用于文件的规则和同一位置序号更小的规则都不会影响其他规则的结果
+    #[test]
+    fn overlapping_rules() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        for name in ["src", "tests"] {
+            std::fs::create_dir_all(dir.join(name)).unwrap();
+            std::fs::write(dir.join(name).join("a.rs"), "x.unwrap();\n").unwrap();
+        }
+        let rules = dir.join("rules.toml");
+        std::fs::write(&rules, "\
+[[rule]]
+id = 'src-unwrap'
+pattern = 'unwrap'
+glob = '**/src/**'
+severity = 'note'
+
+[[rule]]
+id = 'any-unwrap-call'
+pattern = 'unwrap\\(\\)'
+severity = 'error'
+").unwrap();
+        let low = LowArgs {
+            rules: Some(rules),
+            positional: vec![dir.join("src").into_os_string(), dir.join("tests").into_os_string()],
+            with_filename: Some(false),
+            ..LowArgs::default()
+        };
+        let mut args = HiArgs::from_low_args(low).unwrap();
+        let printer = args.printer(SearchMode::Standard, termcolor::NoColor::new(vec![]));
+        let mut worker = args.search_worker(args.matcher().unwrap(), args.searcher().unwrap(), printer).unwrap();
+        for path in args.search_paths() {
+            worker.search(&path).unwrap();
+        }
+        assert_eq!(Some(SarifLevel::Error), worker.printer().max_level());
+        worker.printer().end().unwrap();
+        let got = String::from_utf8(worker.printer().get_mut().get_ref().clone()).unwrap();
+        assert!(got.contains("[src-unwrap]: Match for pattern 'unwrap' (1 match)"), "{got}");
+        assert!(got.contains("[any-unwrap-call]: Match for pattern 'unwrap\\(\\)' (2 matches)"), "{got}");
+    }
+
+    /// -L/--follow 跟随符号链接时遇到循环会通过 err_message! 报错并跳过，遍历可以正常结束
+    #[test]
+    fn follow_symlink_loop() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("a");
+        std::fs::create_dir(&dir).unwrap();
+        std::fs::write(dir.join("file.txt"), "foo\n").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("loop")).unwrap();
+        let args = |follow: bool| {
+            let low = LowArgs {
+                patterns: vec![PatternSource::Regexp("foo".to_string())],
+                positional: vec![temp.path().as_os_str().to_owned()],
+                follow,
+                ..LowArgs::default()
+            };
+            HiArgs::from_low_args(low).unwrap()
+        };
+        let loops = |args: &HiArgs| {
+            args.walker(temp.path().to_path_buf())
+                .into_iter()
+                .filter(|entry| entry.as_ref().is_err_and(|err| err.loop_ancestor().is_some()))
+                .count()
+        };
+        assert_eq!(0, loops(&args(false)));
+        assert_eq!(1, loops(&args(true)));
+        assert_eq!(vec![dir.join("file.txt")], args(true).search_paths());
+        assert!(crate::logger::messages::errored());
+    }
+
+    /// --max-depth 1 只搜索目录下直接包含的文件
+    #[test]
+    fn max_depth() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        std::fs::create_dir(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("top.txt"), "foo\n").unwrap();
+        std::fs::write(dir.join("sub").join("nested.txt"), "foo\n").unwrap();
+        let search_paths = |max_depth: Option<usize>| {
+            let low = LowArgs {
+                patterns: vec![PatternSource::Regexp("foo".to_string())],
+                positional: vec![dir.as_os_str().to_owned()],
+                max_depth,
+                sort: Some(SortMode { reverse: false, kind: SortModeKind::Path }),
+                ..LowArgs::default()
+            };
+            HiArgs::from_low_args(low).unwrap().search_paths()
+        };
+        assert_eq!(vec![dir.join("top.txt")], search_paths(Some(1)));
+        assert_eq!(vec![dir.join("sub").join("nested.txt"), dir.join("top.txt")], search_paths(None));
+    }
+
+    /// --max-filesize 对命令行中直接给出的文件和目录中的文件都生效
+    #[test]
+    fn max_filesize_explicit_file() {
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path();
+        let (small, large) = (dir.join("small.txt"), dir.join("large.txt"));
+        std::fs::write(&small, "foo\n").unwrap();
+        std::fs::write(&large, "foo\n".repeat(100)).unwrap();
+        let search_paths = |positional: Vec<&Path>| {
+            let low = LowArgs {
+                patterns: vec![PatternSource::Regexp("foo".to_string())],
+                positional: positional.into_iter().map(|p| p.as_os_str().to_owned()).collect(),
+                max_filesize: Some(100),
+                ..LowArgs::default()
+            };
+            HiArgs::from_low_args(low).unwrap().search_paths()
+        };
+        assert!(search_paths(vec![&large]).is_empty());
+        assert_eq!(vec![small.clone()], search_paths(vec![&small, &large]));
+        assert_eq!(vec![small.clone()], search_paths(vec![dir]));
+    }
+
+    /// 两个正则引擎对 -s/-i/-S 的处理要一致
+    #[test]
+    fn case_modes() {
+        use grep::matcher::Matcher;
+
+        fn is_match(matcher: PatternMatcher, haystack: &[u8]) -> bool {
+            match matcher {
+                PatternMatcher::RustRegex(m) => m.is_match(haystack).unwrap(),
+                #[cfg(feature = "pcre2")]
+                PatternMatcher::PCRE2(m) => m.is_match(haystack).unwrap(),
+            }
+        }
+
+        let cases = [
+            (CaseMode::Sensitive, "foo", false),
+            (CaseMode::Sensitive, "FOO", true),
+            (CaseMode::Insensitive, "foo", true),
+            (CaseMode::Smart, "foo", true),
+            (CaseMode::Smart, "Foo", false),
+        ];
+        for (case, pattern, expected) in cases {
+            let name = format!("{case:?} {pattern}");
+            let low = LowArgs {
+                case,
+                patterns: vec![PatternSource::Regexp(pattern.to_string())],
+                ..LowArgs::default()
+            };
+            let args = HiArgs::from_low_args(low).unwrap();
+            assert_eq!(expected, is_match(args.matcher_rust(&args.patterns.patterns).unwrap(), b"FOO"), "{name}");
+            #[cfg(feature = "pcre2")]
+            assert_eq!(expected, is_match(args.matcher_pcre2(&args.patterns.patterns).unwrap(), b"FOO"), "{name}");
+        }
+    }
+}
@@ -1,4 +1,7 @@
 use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+use grep::printer::HyperlinkFormat;
 
 /// 低级参数，可以理解为是原生态的参数
 #[derive(Debug, Default)]   //Default为结构体自动派生构造函数
@@ -18,16 +21,82 @@ pub(crate) struct LowArgs {
     pub(crate) case: CaseMode,
     // 颜色高亮输出颜色选择
     // pub(crate) color: ColorChoice,
+    /// 是否打印行首在文件中的字节偏移
+    pub(crate) byte_offset: bool,
     /// 是否打印匹配项在匹配行中的列数
     pub(crate) column: Option<bool>,
+    /// 是否使用 CRLF 作为行终止符
+    pub(crate) crlf: bool,
+    /// 惰性 DFA 缓存的大小
+    pub(crate) dfa_size_limit: Option<usize>,
+    /// 使用的正则引擎
+    pub(crate) engine: EngineChoice,
+    /// 上下文行的字段分隔符
+    pub(crate) field_context_separator: Option<Vec<u8>>,
+    /// 匹配行的字段分隔符
+    pub(crate) field_match_separator: Option<Vec<u8>>,
+    /// 遍历目录时是否跟随符号链接
+    pub(crate) follow: bool,
+    /// 是否在搜索完文件后继续搜索追加的内容
+    pub(crate) follow_file: bool,
+    /// 输出模板，在 HiArgs 中解析校验
+    pub(crate) format: Option<String>,
+    /// 过滤搜索文件的 glob，在 HiArgs 中校验
+    pub(crate) globs: Vec<String>,
     /// 是否以标题的方式打印匹配文件路径
     pub(crate) heading: Option<bool>,
+    /// 行缓冲最多使用的堆内存
+    pub(crate) heap_limit: Option<usize>,
+    /// 文件路径的超链接格式
+    pub(crate) hyperlink_format: HyperlinkFormat,
     /// 是否打印匹配行在文件中的行号
     pub(crate) line_number: Option<bool>,
+    /// 超过这个字节数的行不完整输出，0 表示不限制
+    pub(crate) max_columns: Option<u64>,
+    /// 超过 max_columns 的行是否输出预览
+    pub(crate) max_columns_preview: bool,
+    /// 遍历目录的最大深度
+    pub(crate) max_depth: Option<usize>,
+    /// 跳过大于这个字节数的文件
+    pub(crate) max_filesize: Option<u64>,
+    /// 是否关闭正则的 Unicode 模式
+    pub(crate) no_unicode: bool,
+    /// 是否使用 NUL 作为行终止符
+    pub(crate) null_data: bool,
+    /// 遍历目录时是否不跨越文件系统（挂载点）
+    pub(crate) one_file_system: bool,
     /// 自定义的路径分隔符
     pub(crate) path_separator: Option<u8>,
+    /// 是否按模式使用不同的颜色高亮匹配项
+    pub(crate) pattern_colors: bool,
+    /// --follow-file 检查新数据的间隔
+    pub(crate) poll_interval: Option<Duration>,
+    /// 预处理命令
+    pub(crate) pre: Option<PathBuf>,
+    /// 使用预处理命令的文件的 glob，在 HiArgs 中校验
+    pub(crate) pre_glob: Vec<String>,
+    /// 是否以 pretty 模式输出
+    pub(crate) pretty: bool,
+    /// 编译后正则的大小限制
+    pub(crate) regex_size_limit: Option<usize>,
+    /// 规则文件，在 HiArgs 中读取解析
+    pub(crate) rules: Option<PathBuf>,
+    /// 是否以 SARIF 格式输出
+    pub(crate) sarif: bool,
+    /// 是否搜索归档中的文件
+    pub(crate) search_archives: bool,
+    /// 搜索结果的排序方式，多线程搜索时按排好的顺序输出
+    pub(crate) sort: Option<SortMode>,
+    /// 搜索结束后是否打印统计数据
+    pub(crate) stats: bool,
     /// 搜索使用线程数量
     pub(crate) threads: Option<usize>,
+    /// 只搜索这些类型的文件，在 HiArgs 中校验
+    pub(crate) types: Vec<String>,
+    /// 是否忽略模式中的空白和 `#` 注释，相当于 `(?x)`
+    pub(crate) verbose_regex: bool,
+    /// 打印时是否带上文件路径
+    pub(crate) with_filename: Option<bool>,
 }
 
 //处理特殊命令行参数（查看帮助和查看版本号）
@@ -51,6 +120,8 @@ pub(crate) enum Mode {
     Search(SearchMode),
     /// 列举会搜索到的文件列表，但并不真正执行搜索
     Files,
+    /// 打印正则匹配器的诊断信息，并不真正执行搜索
+    DebugRegex,
     // Types,
     // Generate(GenerateMode),
 }
@@ -88,9 +159,44 @@ pub(crate) enum CaseMode {
     Smart
 }
 
+/// 排序方式，对应 --sort/--sortr
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct SortMode {
+    /// 是否倒序（--sortr）
+    pub(crate) reverse: bool,
+    /// 排序依据
+    pub(crate) kind: SortModeKind,
+}
+
+/// 排序依据
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum SortModeKind {
+    /// 按文件路径排序
+    Path,
+    /// 按文件最后修改时间排序
+    LastModified,
+    /// 按文件最后访问时间排序
+    LastAccessed,
+    /// 按文件创建时间排序
+    Created,
+}
+
+/// 正则引擎选择
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum EngineChoice {
+    /// 默认使用 Rust Regex
+    #[default]
+    Default,
+    /// 优先使用 Rust Regex，如果 pattern 使用了 Rust Regex 不支持的特性（比如 look-around、反向引用），编译失败后自动切换到 PCRE2
+    Auto,
+    /// 使用 PCRE2，需要开启 pcre2 feature 编译
+    PCRE2,
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) enum PatternSource {
     /// 正则表达式 Pattern
     Regexp(String),
-    // File(PathBuf),
+    /// 模式文件，每行一个模式，`#@` 开头的行是下一个模式的元数据
+    File(PathBuf),
 }
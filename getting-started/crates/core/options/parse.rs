@@ -302,7 +302,11 @@ enum FlagInfoKind {
 #[cfg(test)]
 mod tests {
     use crate::options::{HiArgs, parse, ParseResult};
-    use crate::options::lowargs::{CaseMode, LowArgs, Mode, SpecialMode};
+    use std::ffi::OsString;
+    use std::path::PathBuf;
+    use crate::options::lowargs::{
+        CaseMode, EngineChoice, LowArgs, Mode, PatternSource, SortMode, SortModeKind, SpecialMode,
+    };
     use crate::options::parse::parse_low;
 
     /// 测试特殊选项
@@ -325,6 +329,207 @@ mod tests {
         }
     }
 
+    /// 测试 -P/--pcre2 和 --engine，后出现的选项覆盖前面的选项
+    #[test]
+    fn parse_low_engine() {
+        let ParseResult::Ok(low_args) = parse_low(["-P"]) else { panic!() };
+        assert_eq!(EngineChoice::PCRE2, low_args.engine);
+
+        let ParseResult::Ok(low_args) = parse_low(["-P", "--engine", "auto"]) else { panic!() };
+        assert_eq!(EngineChoice::Auto, low_args.engine);
+
+        let ParseResult::Ok(low_args) = parse_low(["--pcre2", "--no-pcre2"]) else { panic!() };
+        assert_eq!(EngineChoice::Default, low_args.engine);
+
+        let ParseResult::Err(err) = parse_low(["--engine", "rust"]) else { panic!() };
+        assert!(format!("{err:#}").contains("unrecognized regex engine"), "{err:#}");
+    }
+
+    /// 测试 --sort/--sortr，后出现的选项覆盖前面的选项，none 表示不排序
+    #[test]
+    fn parse_low_sort() {
+        let ParseResult::Ok(low_args) = parse_low(["--sort", "path"]) else { panic!() };
+        assert_eq!(Some(SortMode { reverse: false, kind: SortModeKind::Path }), low_args.sort);
+
+        let ParseResult::Ok(low_args) = parse_low(["--sort", "path", "--sortr", "modified"]) else { panic!() };
+        assert_eq!(Some(SortMode { reverse: true, kind: SortModeKind::LastModified }), low_args.sort);
+
+        let ParseResult::Ok(low_args) = parse_low(["--sortr", "created", "--sort", "none"]) else { panic!() };
+        assert_eq!(None, low_args.sort);
+
+        let ParseResult::Err(err) = parse_low(["--sort", "size"]) else { panic!() };
+        assert!(format!("{err:#}").contains("unrecognized"), "{err:#}");
+    }
+
+    /// 测试目录遍历相关的选项，--max-filesize 支持 K M G 后缀
+    #[test]
+    fn parse_low_walk() {
+        let argv = ["-L", "--one-file-system", "--max-depth", "2", "--max-filesize", "10M"];
+        let ParseResult::Ok(low_args) = parse_low(argv) else { panic!() };
+        assert!(low_args.follow);
+        assert!(low_args.one_file_system);
+        assert_eq!(Some(2), low_args.max_depth);
+        assert_eq!(Some(10 * (1 << 20)), low_args.max_filesize);
+
+        let ParseResult::Ok(low_args) = parse_low(["--max-filesize", "512", "-L", "--no-follow"]) else { panic!() };
+        assert_eq!(Some(512), low_args.max_filesize);
+        assert!(!low_args.follow);
+
+        let ParseResult::Err(err) = parse_low(["--max-filesize", "10T"]) else { panic!() };
+        assert!(format!("{err:#}").contains("not a valid number"), "{err:#}");
+        let ParseResult::Err(err) = parse_low(["--max-filesize", "99999999999G"]) else { panic!() };
+        assert!(format!("{err:#}").contains("too big"), "{err:#}");
+    }
+
+    #[test]
+    fn parse_low_follow_file() {
+        let ParseResult::Ok(low_args) = parse_low(["--follow-file", "--poll-interval", "100"]) else { panic!() };
+        assert!(low_args.follow_file);
+        assert_eq!(Some(std::time::Duration::from_millis(100)), low_args.poll_interval);
+        let ParseResult::Ok(low_args) = parse_low(["--follow-file", "--no-follow-file"]) else { panic!() };
+        assert!(!low_args.follow_file);
+        assert_eq!(None, low_args.poll_interval);
+    }
+
+    #[test]
+    fn parse_low_debug_regex() {
+        let ParseResult::Ok(low_args) = parse_low(["--debug-regex", "foo"]) else { panic!() };
+        assert!(matches!(low_args.mode, Mode::DebugRegex));
+        assert_eq!(1, low_args.positional.len());
+    }
+
+    #[test]
+    fn parse_low_pattern_colors() {
+        let ParseResult::Ok(low_args) = parse_low(["--pattern-colors", "-e", "foo", "-e", "bar"]) else { panic!() };
+        assert!(low_args.pattern_colors);
+        assert_eq!(2, low_args.patterns.len());
+        let ParseResult::Ok(low_args) = parse_low(["--pattern-colors", "--no-pattern-colors"]) else { panic!() };
+        assert!(!low_args.pattern_colors);
+    }
+
+    #[test]
+    fn parse_low_regex_limits() {
+        let argv = ["--regex-size-limit", "200M", "--dfa-size-limit", "1G", "--no-unicode", "--verbose-regex"];
+        let ParseResult::Ok(low_args) = parse_low(argv) else { panic!() };
+        assert_eq!(Some(200 * (1 << 20)), low_args.regex_size_limit);
+        assert_eq!(Some(1 << 30), low_args.dfa_size_limit);
+        assert!(low_args.no_unicode);
+        assert!(low_args.verbose_regex);
+        let ParseResult::Ok(low_args) = parse_low(["--no-unicode", "--unicode"]) else { panic!() };
+        assert!(!low_args.no_unicode);
+        let ParseResult::Err(err) = parse_low(["--regex-size-limit", "big"]) else { panic!() };
+        assert!(format!("{err:#}").contains("not a valid number"), "{err:#}");
+    }
+
+    #[test]
+    fn parse_low_hyperlink_format() {
+        let ParseResult::Ok(low_args) = parse_low(["--hyperlink-format", "vscode"]) else { panic!() };
+        assert!(low_args.hyperlink_format.is_line_dependent());
+        let ParseResult::Ok(low_args) = parse_low(["--hyperlink-format=file://{host}{path}"]) else { panic!() };
+        assert!(!low_args.hyperlink_format.is_empty());
+        let ParseResult::Ok(low_args) = parse_low(Vec::<&str>::new()) else { panic!() };
+        assert!(low_args.hyperlink_format.is_empty());
+        let ParseResult::Err(err) = parse_low(["--hyperlink-format", "vscode://{file}"]) else { panic!() };
+        assert!(format!("{err:#}").contains("invalid hyperlink format variable"), "{err:#}");
+    }
+
+    #[test]
+    fn parse_low_max_columns() {
+        let ParseResult::Ok(low_args) = parse_low(["-M", "80", "--max-columns-preview"]) else { panic!() };
+        assert_eq!(Some(80), low_args.max_columns);
+        assert!(low_args.max_columns_preview);
+        let ParseResult::Ok(low_args) = parse_low(["--max-columns=0", "--max-columns-preview", "--no-max-columns-preview"]) else { panic!() };
+        assert_eq!(Some(0), low_args.max_columns);
+        assert!(!low_args.max_columns_preview);
+        let ParseResult::Err(err) = parse_low(["--max-columns", "wide"]) else { panic!() };
+        assert!(format!("{err:#}").contains("not a valid number"), "{err:#}");
+    }
+
+    #[test]
+    fn parse_low_fields() {
+        let argv = ["-b", "-I", "--field-match-separator", "\\t|", "--field-context-separator=→"];
+        let ParseResult::Ok(low_args) = parse_low(argv) else { panic!() };
+        assert!(low_args.byte_offset);
+        assert_eq!(Some(false), low_args.with_filename);
+        assert_eq!(Some(b"\t|".to_vec()), low_args.field_match_separator);
+        assert_eq!(Some("→".as_bytes().to_vec()), low_args.field_context_separator);
+        let ParseResult::Ok(low_args) = parse_low(["-I", "-H"]) else { panic!() };
+        assert_eq!(Some(true), low_args.with_filename);
+        let ParseResult::Ok(low_args) = parse_low(Vec::<&str>::new()) else { panic!() };
+        assert_eq!(None, low_args.with_filename);
+        assert!(!low_args.byte_offset);
+    }
+
+    #[test]
+    fn parse_low_pretty() {
+        let ParseResult::Ok(low_args) = parse_low(["-p"]) else { panic!() };
+        assert!(low_args.pretty);
+        let ParseResult::Ok(low_args) = parse_low(["--pretty", "--no-pretty"]) else { panic!() };
+        assert!(!low_args.pretty);
+    }
+
+    #[test]
+    fn parse_low_format() {
+        let ParseResult::Ok(low_args) = parse_low(["--format", "{path}:{line}: {match}"]) else { panic!() };
+        assert_eq!(Some("{path}:{line}: {match}".to_string()), low_args.format);
+        // 模板在 HiArgs 中才校验，这里原样保存
+        let ParseResult::Ok(low_args) = parse_low(["--format={nope}"]) else { panic!() };
+        assert_eq!(Some("{nope}".to_string()), low_args.format);
+    }
+
+    #[test]
+    fn parse_low_sarif() {
+        let ParseResult::Ok(low_args) = parse_low(["--sarif", "-f", "rules.txt", "-e", "TODO"]) else { panic!() };
+        assert!(low_args.sarif);
+        assert_eq!(
+            vec![PatternSource::File(PathBuf::from("rules.txt")), PatternSource::Regexp("TODO".to_string())],
+            low_args.patterns,
+        );
+    }
+
+    #[test]
+    fn parse_low_rules() {
+        let ParseResult::Ok(low_args) = parse_low(["--rules", "rules.toml", "src"]) else { panic!() };
+        assert_eq!(Some(PathBuf::from("rules.toml")), low_args.rules);
+        assert_eq!(vec![std::ffi::OsString::from("src")], low_args.positional);
+        let ParseResult::Ok(low_args) = parse_low(Vec::<&str>::new()) else { panic!() };
+        assert_eq!(None, low_args.rules);
+    }
+
+    #[test]
+    fn parse_low_pre() {
+        let argv = ["--pre", "pdftotext.sh", "--pre-glob", "*.pdf", "--pre-glob=!secret/**"];
+        let ParseResult::Ok(low_args) = parse_low(argv) else { panic!() };
+        assert_eq!(Some(PathBuf::from("pdftotext.sh")), low_args.pre);
+        assert_eq!(vec!["*.pdf".to_string(), "!secret/**".to_string()], low_args.pre_glob);
+        // 空的命令表示不使用预处理命令
+        let ParseResult::Ok(low_args) = parse_low(["--pre", "pdftotext.sh", "--pre="]) else { panic!() };
+        assert_eq!(None, low_args.pre);
+    }
+
+    #[test]
+    fn parse_low_search_archives() {
+        let argv = ["--search-archives", "--files", "-g", "*.java", "--glob=!test/**", "-t", "zip", "dist"];
+        let ParseResult::Ok(low_args) = parse_low(argv) else { panic!() };
+        assert!(low_args.search_archives);
+        assert!(matches!(low_args.mode, Mode::Files));
+        assert_eq!(vec!["*.java".to_string(), "!test/**".to_string()], low_args.globs);
+        assert_eq!(vec!["zip".to_string()], low_args.types);
+        assert_eq!(vec![OsString::from("dist")], low_args.positional);
+        let ParseResult::Ok(low_args) = parse_low(["--search-archives", "--no-search-archives"]) else { panic!() };
+        assert!(!low_args.search_archives);
+    }
+
+    #[test]
+    fn parse_low_heap_limit() {
+        let ParseResult::Ok(low_args) = parse_low(["--heap-limit", "64K"]) else { panic!() };
+        assert_eq!(Some(64 * (1 << 10)), low_args.heap_limit);
+        let ParseResult::Ok(low_args) = parse_low(Vec::<&str>::new()) else { panic!() };
+        assert_eq!(None, low_args.heap_limit);
+        let ParseResult::Err(err) = parse_low(["--heap-limit", "lots"]) else { panic!() };
+        assert!(format!("{err:#}").contains("not a valid number"), "{err:#}");
+    }
+
     /// 测试常用的搜索选项，比如： -i -n --column --heading --path-separator=:
     #[test]
     fn parse_low_normal() {
@@ -349,6 +554,22 @@ mod tests {
         }
     }
 
+    /// 测试 --crlf 和 --null-data 互斥，后出现的选项覆盖前面的选项
+    #[test]
+    fn parse_low_line_terminator() {
+        let ParseResult::Ok(low_args) = parse_low(["--crlf", "--null-data"]) else { panic!() };
+        assert!(!low_args.crlf);
+        assert!(low_args.null_data);
+
+        let ParseResult::Ok(low_args) = parse_low(["--null-data", "--crlf"]) else { panic!() };
+        assert!(low_args.crlf);
+        assert!(!low_args.null_data);
+
+        let ParseResult::Ok(low_args) = parse_low(["--crlf", "--no-crlf"]) else { panic!() };
+        assert!(!low_args.crlf);
+        assert!(!low_args.null_data);
+    }
+
     /// 测试常用的搜索选项，比如： -i -n --column --heading --path-separator=:
     #[test]
     fn parse_low_then_hi() {
@@ -0,0 +1,233 @@
+//! --rules 指定的规则文件，每条规则是一个模式加上规则的元数据，比如：
+//! ```toml
+//! [[rule]]
+//! id = "no-unwrap"
+//! pattern = '\.unwrap\(\)'
+//! type = "rust"
+//! glob = ["!tests/**", "src/**"]
+//! message = "avoid unwrap outside of tests"
+//! severity = "error"
+//! ```
+//! 所有规则的模式一起构建一个匹配器找出匹配行，每个文件只搜索一遍，再用每条规则单独的匹配器在匹配行中分别查找，
+//! 所以同一位置可以报告多条规则，规则的序号就是模式序号（PatternID）。
+//! glob 和 type 限定规则适用的文件，搜索每个文件前算出哪些规则适用，Printer 只在匹配行中查找适用的规则。
+//! 规则文件由 toml 解析，glob 和 type 既可以是字符串也可以是字符串数组
+
+use std::ops::Range;
+use std::path::Path;
+use grep::printer::SarifLevel;
+use serde::Deserialize;
+use toml::Spanned;
+use crate::glob::PathFilter;
+
+/// 一条规则
+#[derive(Clone, Debug)]
+pub(crate) struct Rule {
+    pub(crate) id: String,
+    pub(crate) pattern: String,
+    pub(crate) message: Option<String>,
+    pub(crate) severity: Option<SarifLevel>,
+    /// 规则适用的文件
+    pub(crate) filter: PathFilter,
+}
+
+/// 所有规则适用的文件，搜索每个文件前用于算出哪些规则适用于这个文件
+#[derive(Clone, Debug)]
+pub(crate) struct RuleFilter {
+    /// 和规则一一对应
+    filters: Vec<PathFilter>,
+}
+
+impl RuleFilter {
+    /// 所有规则都适用于所有文件时返回 None，搜索时不需要过滤
+    pub(crate) fn new(rules: &[Rule]) -> Option<RuleFilter> {
+        if rules.iter().all(|rule| rule.filter.is_empty()) {
+            return None;
+        }
+        Some(RuleFilter { filters: rules.iter().map(|rule| rule.filter.clone()).collect() })
+    }
+
+    /// 第 i 个元素表示第 i 条规则是否适用于 path
+    pub(crate) fn enabled(&self, path: &Path) -> Vec<bool> {
+        self.filters.iter().map(|filter| filter.is_match(path)).collect()
+    }
+}
+
+/// 读取规则文件，错误信息带上文件路径和行号
+pub(crate) fn read_rules(path: &Path) -> anyhow::Result<Vec<Rule>> {
+    let contents = match std::fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) => anyhow::bail!("{}: {}", path.display(), err),
+    };
+    let Ok(contents) = String::from_utf8(contents) else {
+        anyhow::bail!("{}: rules file is not valid UTF-8", path.display());
+    };
+    match parse_rules(&contents) {
+        Ok(rules) => Ok(rules),
+        Err((line, msg)) => anyhow::bail!("{}:{}: {}", path.display(), line, msg),
+    }
+}
+
+/// 规则文件，只能包含 `[[rule]]` 表
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default)]
+    rule: Vec<RuleDef>,
+}
+
+/// 规则文件中的一个 `[[rule]]` 表，带上位置用于报告错误所在的行
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleDef {
+    id: Spanned<String>,
+    pattern: Spanned<String>,
+    glob: Option<Spanned<Strings>>,
+    #[serde(rename = "type")]
+    types: Option<Spanned<Strings>>,
+    message: Option<String>,
+    severity: Option<Spanned<String>>,
+}
+
+/// 字符串或者字符串数组
+#[derive(Debug, Deserialize)]
+#[serde(untagged, expecting = "invalid type, expected a string or an array of strings")]
+enum Strings {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Strings {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            Strings::One(s) => vec![s],
+            Strings::Many(values) => values,
+        }
+    }
+}
+
+/// 解析规则文件的内容，出错时返回出错的行号和错误信息
+fn parse_rules(contents: &str) -> Result<Vec<Rule>, (usize, String)> {
+    let line = |span: Range<usize>| contents[..span.start].matches('\n').count() + 1;
+    let file: RulesFile = toml::from_str(contents).map_err(|err| {
+        let msg = err.message().trim_end().to_string();
+        (err.span().map_or(1, line), msg)
+    })?;
+    let mut rules: Vec<Rule> = Vec::with_capacity(file.rule.len());
+    for def in file.rule {
+        let id_line = line(def.id.span());
+        let rule = rule_from_def(def).map_err(|(span, msg)| (line(span), msg))?;
+        if rules.iter().any(|other| other.id == rule.id) {
+            return Err((id_line, format!("duplicate rule id '{}'", rule.id)));
+        }
+        rules.push(rule);
+    }
+    if rules.is_empty() {
+        return Err((1, "no rules found, each rule starts with a [[rule]] table".to_string()));
+    }
+    Ok(rules)
+}
+
+/// `[[rule]]` 表转成规则，toml 已经检查过键和值的类型，这里检查值的内容，出错时返回出错的值的位置
+fn rule_from_def(def: RuleDef) -> Result<Rule, (Range<usize>, String)> {
+    let (id_span, id) = (def.id.span(), def.id.into_inner());
+    if id.is_empty() {
+        return Err((id_span, "rule is missing a non-empty 'id'".to_string()));
+    }
+    if def.pattern.get_ref().is_empty() {
+        return Err((def.pattern.span(), format!("rule '{id}' is missing a non-empty 'pattern'")));
+    }
+    let severity = match def.severity {
+        None => None,
+        Some(severity) => match severity.get_ref().parse::<SarifLevel>() {
+            Ok(level) => Some(level),
+            Err(err) => return Err((severity.span(), err.to_string())),
+        },
+    };
+    let mut filter = PathFilter::default();
+    if let Some(globs) = def.glob {
+        let span = globs.span();
+        for glob in globs.into_inner().into_vec() {
+            filter.add_glob(&glob).map_err(|err| (span.clone(), err))?;
+        }
+    }
+    if let Some(types) = def.types {
+        let span = types.span();
+        for name in types.into_inner().into_vec() {
+            filter.add_type(&name).map_err(|err| (span.clone(), err))?;
+        }
+    }
+    Ok(Rule { id, pattern: def.pattern.into_inner(), message: def.message, severity, filter })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let contents = r#"
+# 规则文件
+[[rule]]
+id = "no-unwrap"   # 行尾注释
+pattern = '\.unwrap\(\)'
+type = "rust"
+glob = [
+    "!tests/**",   # 排除测试
+    'src/**',
+]
+message = "avoid \"unwrap\"\tin src"
+severity = "error"
+
+[[ rule ]]
+"id" = 'todo'
+pattern = '''
+TODO|FIXME'''
+"#;
+        let rules = parse_rules(contents).unwrap();
+        assert_eq!(2, rules.len());
+        assert_eq!(("no-unwrap", r"\.unwrap\(\)"), (rules[0].id.as_str(), rules[0].pattern.as_str()));
+        assert_eq!(Some("avoid \"unwrap\"\tin src".to_string()), rules[0].message);
+        assert_eq!(Some(SarifLevel::Error), rules[0].severity);
+        assert_eq!(("todo", "TODO|FIXME", None), (rules[1].id.as_str(), rules[1].pattern.as_str(), rules[1].severity));
+
+        let filter = RuleFilter::new(&rules).unwrap();
+        assert_eq!(vec![true, true], filter.enabled(Path::new("./src/main.rs")));
+        assert_eq!(vec![false, true], filter.enabled(Path::new("tests/main.rs")));
+        assert_eq!(vec![false, true], filter.enabled(Path::new("src/README.md")));
+        assert!(RuleFilter::new(&rules[1..]).is_none());
+
+        // 内联表写法
+        let rules = parse_rules("rule = [{ id = 'a', pattern = 'x', glob = '*.rs' }, { id = 'b', pattern = 'y' }]\n").unwrap();
+        assert_eq!(vec!["a", "b"], rules.iter().map(|rule| rule.id.as_str()).collect::<Vec<_>>());
+        assert_eq!(vec![true, true], RuleFilter::new(&rules).unwrap().enabled(Path::new("main.rs")));
+        assert_eq!(vec![false, true], RuleFilter::new(&rules).unwrap().enabled(Path::new("main.c")));
+    }
+
+    #[test]
+    fn parse_errors() {
+        let err = |contents: &str| {
+            let (line, msg) = parse_rules(contents).unwrap_err();
+            format!("{line}: {msg}")
+        };
+
+        assert_eq!("1: unknown field `id`, expected `rule`", err("id = 'a'\n"));
+        assert_eq!("1: unknown field `rules`, expected `rule`", err("[[rules]]\n"));
+        assert_eq!("1: invalid type: map, expected a sequence", err("[rule]\n"));
+        assert!(err("[[rule]]\nid = 'a'\nseverity = 'fatal'\npattern = 'x'\n").starts_with("3: invalid severity 'fatal'"));
+        assert!(err("[[rule]]\nid = 'a'\npattern = 'x'\ncolor = 'red'\n").starts_with("4: unknown field `color`"));
+        assert!(err("[[rule]]\nid = 'a'\npattern = 'x'\ntype = 'cobol'\n").starts_with("4: unknown file type 'cobol'"));
+        assert!(err("[[rule]]\nglob = '{a'\nid = 'a'\npattern = 'x'\n").starts_with("2: invalid glob '{a': unclosed alternate group"));
+        assert_eq!("3: duplicate key `id` in table `rule`", err("[[rule]]\nid = 'a'\nid = 'b'\n"));
+        assert_eq!("1: missing field `pattern`", err("[[rule]]\nid = 'a'\n"));
+        assert_eq!("3: rule 'a' is missing a non-empty 'pattern'", err("[[rule]]\nid = 'a'\npattern = ''\n"));
+        assert_eq!("2: rule is missing a non-empty 'id'", err("[[rule]]\nid = ''\npattern = 'x'\n"));
+        assert_eq!("5: duplicate rule id 'a'", err("[[rule]]\nid='a'\npattern='x'\n[[rule]]\nid='a'\npattern='y'\n"));
+        assert_eq!("2: invalid literal string", err("[[rule]]\nid = 'a\n"));
+        assert!(err("[[rule]]\nid = \"\\d\"\n").starts_with("2: invalid escape sequence"));
+        assert_eq!("2: invalid type: sequence, expected a string", err("[[rule]]\nid = ['a']\npattern = 'x'\n"));
+        assert_eq!("4: invalid type, expected a string or an array of strings", err("[[rule]]\nid = 'a'\npattern = 'x'\nglob = 1\n"));
+        assert!(err("[[rule]]\nid = 'a' x\n").starts_with("2: expected newline"));
+        assert_eq!("1: no rules found, each rule starts with a [[rule]] table", err("# nothing\n"));
+    }
+}
@@ -1,10 +1,30 @@
-use std::io;
-use std::path::Path;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
 use {grep::matcher::Matcher, termcolor::WriteColor};
-use grep::searcher::Searcher;
+use grep::printer::{SarifLevel, Stats};
+use grep::searcher::{Searcher, Sink};
+use crate::archive::{self, ArchiveReader};
+use crate::glob::PathFilter;
+use crate::rules::RuleFilter;
 
 #[derive(Clone, Debug, Default)]
-struct Config {}
+struct Config {
+    /// 开启 --follow-file 时检查新数据的间隔
+    follow_file: Option<Duration>,
+    /// 预处理命令，搜索的是命令的标准输出
+    preprocessor: Option<PathBuf>,
+    /// 使用预处理命令的文件，为空时所有文件都使用预处理命令
+    preprocessor_globs: PathFilter,
+    /// --rules 中限定了适用文件的规则
+    rule_filter: Option<RuleFilter>,
+    /// 每条规则单独的匹配器，为空时匹配行中的规则由合并的匹配器查找
+    rule_matchers: Vec<PatternMatcher>,
+    /// 是否把 `bundle.zip!/src/main.rs` 这样的虚拟路径当作归档中的文件搜索
+    search_archives: bool,
+}
 
 #[derive(Clone, Debug)]
 pub(crate) struct SearchWorkerBuilder {
@@ -24,6 +44,37 @@ impl SearchWorkerBuilder {
         }
     }
 
+    /// 设置后搜索完文件不会结束，而是按给定的间隔继续搜索追加的内容
+    pub(crate) fn follow_file(&mut self, poll_interval: Option<Duration>) -> &mut SearchWorkerBuilder {
+        self.config.follow_file = poll_interval;
+        self
+    }
+
+    /// 设置后匹配 globs 的文件先交给预处理命令，搜索命令的标准输出
+    pub(crate) fn preprocessor(&mut self, cmd: Option<PathBuf>, globs: PathFilter) -> &mut SearchWorkerBuilder {
+        self.config.preprocessor = cmd;
+        self.config.preprocessor_globs = globs;
+        self
+    }
+
+    /// 设置后搜索每个文件前先算出哪些规则适用于这个文件，不适用的规则的匹配项不输出，没有适用的规则时跳过这个文件
+    pub(crate) fn rule_filter(&mut self, rule_filter: Option<RuleFilter>) -> &mut SearchWorkerBuilder {
+        self.config.rule_filter = rule_filter;
+        self
+    }
+
+    /// 设置每条规则（模式）单独的匹配器，SARIF 和 Lint 在匹配行中分别查找每条规则，规则之间不会互相遮挡
+    pub(crate) fn rule_matchers(&mut self, matchers: Vec<PatternMatcher>) -> &mut SearchWorkerBuilder {
+        self.config.rule_matchers = matchers;
+        self
+    }
+
+    /// 设置后归档中文件的虚拟路径会从归档中流式解压后搜索
+    pub(crate) fn search_archives(&mut self, yes: bool) -> &mut SearchWorkerBuilder {
+        self.config.search_archives = yes;
+        self
+    }
+
     pub(crate) fn build<W: WriteColor>(
         &self,
         searcher: Searcher,
@@ -36,6 +87,7 @@ impl SearchWorkerBuilder {
             searcher,
             matcher,
             printer,
+            archives: ArchiveReader::default(),
         }
     }
 }
@@ -60,6 +112,8 @@ pub(crate) struct SearchWorker<W> {
     matcher: PatternMatcher,
     /// 实现将匹配结果输出， 是对 Standard 的代理封装
     printer: Printer<W>,
+    /// 打开的归档，按顺序搜索同一个 tar 中的文件时不需要从头读取
+    archives: ArchiveReader,
 }
 
 impl<W: WriteColor> SearchWorker<W> {
@@ -71,81 +125,301 @@ impl<W: WriteColor> SearchWorker<W> {
         log::debug!("search path: {}", path.display());
 
         // ripgrep 这里支持多种处理，比如从标准输入搜索、执行搜索前预处理、从压缩路径搜索（会先解压）
-        // 这里只展示从普通的文件路径搜索
+        // 这里支持搜索归档中的文件、执行搜索前预处理和从普通的文件路径搜索，归档中的文件不经过预处理
+        if self.config.search_archives {
+            if let Some((archive, entry)) = archive::split_virtual_path(path) {
+                return self.search_archive_entry(path, &archive, &entry);
+            }
+        }
+        if self.should_preprocess(path) {
+            return self.search_preprocessor(path);
+        }
         self.search_path(path)
     }
 
+    /// Printer 的可变引用，并行搜索时用来取出每个文件的输出缓冲
+    pub(crate) fn printer(&mut self) -> &mut Printer<W> {
+        &mut self.printer
+    }
+
+    /// 设置了预处理命令并且文件匹配 --pre-glob（没有设置时匹配所有文件）
+    fn should_preprocess(&self, path: &Path) -> bool {
+        let globs = &self.config.preprocessor_globs;
+        self.config.preprocessor.is_some() && (globs.is_empty() || globs.is_match(path))
+    }
+
+    /// 执行预处理命令并搜索它的标准输出，命令的参数是文件路径，标准输入是文件内容，
+    /// 命令无法启动或者退出码不为 0 时返回错误，错误信息带上命令的标准错误输出
+    fn search_preprocessor(&mut self, path: &Path) -> io::Result<SearchResult> {
+        let cmd = self.config.preprocessor.clone().unwrap();
+        let file = File::open(path)?;
+        let mut child = Command::new(&cmd)
+            .arg(path)
+            .stdin(Stdio::from(file))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("preprocessor command could not start: '{}': {}", cmd.display(), err),
+                )
+            })?;
+        // 另起线程读取标准错误，避免命令写满标准错误的管道后阻塞，而这边还在等它的标准输出
+        let mut stderr = child.stderr.take().unwrap();
+        let stderr_reader = std::thread::spawn(move || {
+            let mut buf = vec![];
+            let _ = stderr.read_to_end(&mut buf);
+            buf
+        });
+        let mut stdout = child.stdout.take().unwrap();
+        let result = self.search_input(path, Input::Reader(&mut stdout));
+        // 先关闭标准输出，搜索提前结束时命令再写标准输出会收到 SIGPIPE 退出，不会一直阻塞
+        drop(stdout);
+        let status = child.wait()?;
+        let stderr = stderr_reader.join().unwrap_or_default();
+        let result = result?;
+        if !status.success() {
+            let stderr = String::from_utf8_lossy(&stderr);
+            let stderr = stderr.trim_end();
+            let mut msg = format!("preprocessor command failed: '{} {}': {}", cmd.display(), path.display(), status);
+            if !stderr.is_empty() {
+                msg.push_str(":\n");
+                msg.push_str(stderr);
+            }
+            return Err(io::Error::other(msg));
+        }
+        Ok(result)
+    }
+
+    /// 从归档中流式解压出文件并搜索
+    fn search_archive_entry(&mut self, path: &Path, archive: &Path, entry: &str) -> io::Result<SearchResult> {
+        // 先把 archives 取出来，reader 借用它的同时还要可变借用 self 搜索
+        let mut archives = std::mem::take(&mut self.archives);
+        let result = archives.read_entry(archive, entry, |rdr| self.search_input(path, Input::Reader(rdr)));
+        self.archives = archives;
+        result
+    }
+
     /// 从文件路径指定的文件搜索
     fn search_path(&mut self, path: &Path) -> io::Result<SearchResult> {
+        self.search_input(path, Input::File(self.config.follow_file))
+    }
+
+    /// 搜索文件或者 reader 中的内容，path 用于输出和计算适用的规则
+    fn search_input(&mut self, path: &Path, input: Input<'_>) -> io::Result<SearchResult> {
         // 获取 searcher printer 可变引用
         let (searcher, printer) = (&mut self.searcher, &mut self.printer);
+        let enabled = self.config.rule_filter.as_ref().map(|filter| filter.enabled(path));
+        if let Some(ref enabled) = enabled {
+            if !enabled.contains(&true) {
+                log::debug!("{}: skipping, no rule applies to this file", path.display());
+                return Ok(SearchResult::default());
+            }
+        }
+        let enabled = enabled.as_deref();
         match self.matcher {
             // ref m: 指匹配并获取matcher的引用，来避免所有权转移
             PatternMatcher::RustRegex(ref m) => {
-                search_path(m, searcher, printer, path)
+                let rules: Vec<_> = self.config.rule_matchers.iter().filter_map(PatternMatcher::as_rust).collect();
+                search_path(m, &rules, searcher, printer, path, input, enabled)
+            }
+            #[cfg(feature = "pcre2")]
+            PatternMatcher::PCRE2(ref m) => {
+                let rules: Vec<_> = self.config.rule_matchers.iter().filter_map(PatternMatcher::as_pcre2).collect();
+                search_path(m, &rules, searcher, printer, path, input, enabled)
             }
         }
     }
 }
 
+/// 搜索的内容
+enum Input<'r> {
+    /// 直接搜索文件，设置了 --follow-file 的检查间隔时搜索完继续搜索追加的内容
+    File(Option<Duration>),
+    /// 搜索 reader 中的内容，比如预处理命令的标准输出、归档中的文件
+    Reader(&'r mut dyn io::Read),
+}
+
 #[derive(Clone, Debug, Default)]
 pub(crate) struct SearchResult {
     //是否有匹配的行
     has_match: bool,
+    /// 本次搜索的统计数据，只有开启了 --stats 才有
+    stats: Option<Stats>,
 }
 
 impl SearchResult {
     pub(crate) fn has_match(&self) -> bool {
         self.has_match
     }
+
+    pub(crate) fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
 }
 
-/// 支持的正则引擎匹配器，这里只展示 Rust Regex
+/// 支持的正则引擎匹配器，Rust Regex 以及可选的 PCRE2（需要开启 pcre2 feature）
 /// Pattern -> RegexMatcher
 #[derive(Clone, Debug)]
 pub(crate) enum PatternMatcher {
     RustRegex(grep::regex::RegexMatcher),
-    // #[cfg(feature = "pcre2")]
-    // PCRE2(grep::pcre2::RegexMatcher),
+    #[cfg(feature = "pcre2")]
+    PCRE2(grep::pcre2::RegexMatcher),
 }
 
 /// 匹配结果的输出类型
-/// ripgrep 支持三种实现 Standard Summary JSON, 这里只分析 Standard
+/// ripgrep 支持三种实现 Standard Summary JSON, 这里只分析 Standard，另外加了按用户模板输出的 Template、
+/// SARIF 以及按规则分组输出的 Lint
+/// 每个搜索线程只创建一个 Printer，所以不需要为了缩小枚举的大小把 Standard 装箱
 #[derive(Clone, Debug)]
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum Printer<W> {
     /// Use the standard printer, which supports the classic grep-like format.
     Standard(grep::printer::Standard<W>),
+    /// 按 --format 指定的模板输出，每个匹配一行
+    Template(grep::printer::Template<W>),
+    /// 以 SARIF 格式输出，所有搜索结束后需要调用 end()
+    Sarif(grep::printer::Sarif<W>),
+    /// 按规则分组输出 --rules 的结果，所有搜索结束后需要调用 end()
+    Lint(grep::printer::Lint<W>),
     // Use the summary printer, which supports aggregate displays of search results.
     // Summary(grep::printer::Summary<W>),
     // A JSON printer, which emits results in the JSON Lines format.
     // JSON(grep::printer::JSON<W>),
 }
 
-fn search_path<M: Matcher, W: WriteColor>(
+impl PatternMatcher {
+    fn as_rust(&self) -> Option<&grep::regex::RegexMatcher> {
+        match *self {
+            PatternMatcher::RustRegex(ref m) => Some(m),
+            #[cfg(feature = "pcre2")]
+            PatternMatcher::PCRE2(_) => None,
+        }
+    }
+
+    #[cfg(feature = "pcre2")]
+    fn as_pcre2(&self) -> Option<&grep::pcre2::RegexMatcher> {
+        match *self {
+            PatternMatcher::PCRE2(ref m) => Some(m),
+            PatternMatcher::RustRegex(_) => None,
+        }
+    }
+}
+
+impl<W: WriteColor> Printer<W> {
+    /// 输出目的地的可变引用
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        match *self {
+            Printer::Standard(ref mut p) => p.get_mut(),
+            Printer::Template(ref mut p) => p.get_mut(),
+            Printer::Sarif(ref mut p) => p.get_mut(),
+            Printer::Lint(ref mut p) => p.get_mut(),
+        }
+    }
+
+    /// 所有搜索结束后输出收尾的内容，SARIF 需要结束文档，Lint 在这时才输出所有结果
+    pub(crate) fn end(&mut self) -> io::Result<()> {
+        match *self {
+            Printer::Sarif(ref mut p) => p.end(),
+            Printer::Lint(ref mut p) => p.end(),
+            Printer::Standard(_) | Printer::Template(_) => Ok(()),
+        }
+    }
+
+    /// 输出的结果中最高的严重程度，只有 SARIF 和 Lint 的结果有严重程度
+    pub(crate) fn max_level(&self) -> Option<SarifLevel> {
+        match *self {
+            Printer::Sarif(ref p) => p.max_level(),
+            Printer::Lint(ref p) => p.max_level(),
+            Printer::Standard(_) | Printer::Template(_) => None,
+        }
+    }
+}
+
+/// rules 为空时 SARIF 和 Lint 用 matcher 查找匹配行中所有规则的匹配项
+fn search_path<M: Matcher + Copy, W: WriteColor>(
     matcher: M,
+    rules: &[M],
     searcher: &mut grep::searcher::Searcher,
     printer: &mut Printer<W>,
     path: &Path,
+    mut input: Input<'_>,
+    enabled: Option<&[bool]>,
 ) -> io::Result<SearchResult> {
     match *printer {
         Printer::Standard(ref mut standard) => {
-            let mut sink = standard.sink_with_path(&matcher, path);
-            searcher.search_path(&matcher, path, &mut sink)?;     //TODO 为何这里 &sink 不可变引用会报编译错误： the trait `grep::grep_searcher::Sink` is not implemented for `&printer::standard::StandardSink<'_, '_, &M, W>`
+            let mut sink = standard.sink_with_path(matcher, path);
+            search_sink(matcher, searcher, path, &mut input, &mut sink)?;
+            //TODO 为何这里 &sink 不可变引用会报编译错误： the trait `grep::grep_searcher::Sink` is not implemented for `&printer::standard::StandardSink<'_, '_, &M, W>`
             //官方推荐要么传值、要么使用可变引用；
             Ok(SearchResult {
                 has_match: sink.has_match(),    //是否有搜索到匹配行
+                stats: sink.stats().cloned(),
+            })
+        }
+        Printer::Template(ref mut template) => {
+            let mut sink = template.sink_with_path(matcher, path);
+            search_sink(matcher, searcher, path, &mut input, &mut sink)?;
+            Ok(SearchResult {
+                has_match: sink.has_match(),
+                stats: sink.stats().cloned(),
+            })
+        }
+        Printer::Sarif(ref mut sarif) => {
+            let mut sink = sarif.sink_with_path(matcher, path);
+            if let Some(enabled) = enabled {
+                sink = sink.pattern_filter(enabled);
+            }
+            if !rules.is_empty() {
+                sink = sink.pattern_matchers(rules);
+            }
+            search_sink(matcher, searcher, path, &mut input, &mut sink)?;
+            Ok(SearchResult {
+                has_match: sink.has_match(),
+                stats: sink.stats().cloned(),
+            })
+        }
+        Printer::Lint(ref mut lint) => {
+            let mut sink = lint.sink_with_path(matcher, path);
+            if let Some(enabled) = enabled {
+                sink = sink.pattern_filter(enabled);
+            }
+            if !rules.is_empty() {
+                sink = sink.pattern_matchers(rules);
+            }
+            search_sink(matcher, searcher, path, &mut input, &mut sink)?;
+            Ok(SearchResult {
+                has_match: sink.has_match(),
+                stats: sink.stats().cloned(),
             })
         }
     }
 }
 
+/// 用给定的 Sink 搜索文件或者 reader，设置了 --follow-file 时搜索完继续搜索追加的内容
+fn search_sink<M: Matcher, S: Sink<Error = io::Error>>(
+    matcher: M,
+    searcher: &mut grep::searcher::Searcher,
+    path: &Path,
+    input: &mut Input<'_>,
+    sink: &mut S,
+) -> io::Result<()> {
+    match *input {
+        Input::File(Some(poll_interval)) => searcher.search_path_follow(matcher, path, poll_interval, sink),
+        Input::File(None) => searcher.search_path(matcher, path, sink),
+        Input::Reader(ref mut rdr) => searcher.search_reader(matcher, rdr, sink),
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::path::Path;
-    use termcolor::ColorChoice;
+    use std::path::{Path, PathBuf};
+    use termcolor::{ColorChoice, NoColor};
     use grep::printer::StandardBuilder;
     use grep::regex::RegexMatcherBuilder;
     use grep::searcher::SearcherBuilder;
+    use crate::glob::PathFilter;
     use crate::search::{PatternMatcher, Printer, SearchWorkerBuilder};
 
     #[test]
@@ -172,4 +446,48 @@ mod tests {
         let path = Path::new("./Cargo.toml");
         search_worker.search(path).unwrap();
     }
-}
\ No newline at end of file
+
+    /// 用 shell 脚本代替 pdftotext 这类转换命令
+    #[cfg(unix)]
+    #[test]
+    fn preprocessor() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+        let script = |name: &str, body: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+            path
+        };
+        let upper = script("upper.sh", r#"test "$(cat)" = "$(cat "$1")" && tr a-z A-Z < "$1""#);
+        let fail = script("fail.sh", r#"echo "cannot convert $1" >&2; exit 3"#);
+        let (doc, notes) = (dir.join("doc.pdf"), dir.join("notes.txt"));
+        std::fs::write(&doc, "hello pdf\n").unwrap();
+        std::fs::write(&notes, "hello txt\n").unwrap();
+
+        let search = |cmd: PathBuf, glob: &str, path: &Path| {
+            let matcher = PatternMatcher::RustRegex(RegexMatcherBuilder::new().build("HELLO").unwrap());
+            let printer = Printer::Standard(StandardBuilder::new().path(false).build(NoColor::new(vec![])));
+            let mut globs = PathFilter::default();
+            globs.add_glob(glob).unwrap();
+            let mut worker = SearchWorkerBuilder::new()
+                .preprocessor(Some(cmd), globs)
+                .build(SearcherBuilder::new().line_number(false).build(), matcher, printer);
+            let result = worker.search(path).map(|result| result.has_match()).map_err(|err| err.to_string());
+            (result, String::from_utf8(worker.printer().get_mut().get_ref().clone()).unwrap())
+        };
+        // 匹配 --pre-glob 的文件搜索命令的输出，其他文件直接搜索
+        assert_eq!((Ok(true), "HELLO PDF\n".to_string()), search(upper.clone(), "*.pdf", &doc));
+        assert_eq!((Ok(false), String::new()), search(upper, "*.pdf", &notes));
+
+        let (result, _) = search(fail, "*", &doc);
+        let msg = result.unwrap_err();
+        assert!(msg.starts_with("preprocessor command failed: '"), "{msg}");
+        assert!(msg.ends_with(&format!("exit status: 3:\ncannot convert {}", doc.display())), "{msg}");
+
+        let (result, _) = search(dir.join("missing.sh"), "*", &doc);
+        assert!(result.unwrap_err().starts_with("preprocessor command could not start"));
+    }
+}
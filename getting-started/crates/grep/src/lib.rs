@@ -1,4 +1,6 @@
 pub extern crate grep_searcher as searcher;
 pub extern crate grep_regex as regex;
 pub extern crate grep_printer as printer;
-pub extern crate grep_matcher as matcher;
\ No newline at end of file
+pub extern crate grep_matcher as matcher;
+#[cfg(feature = "pcre2")]
+pub extern crate grep_pcre2 as pcre2;
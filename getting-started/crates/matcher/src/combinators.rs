@@ -0,0 +1,369 @@
+//! 组合多个 Matcher 的匹配器，用于表达 "行同时匹配 A 和 B 但不匹配 C" 这类规则
+//! AndMatcher、NotMatcher 都是按行判断的，匹配结果不会跨行；SetMatcher 则是多个 Matcher 的并集，会报告匹配项所属的模式序号
+
+use std::cmp;
+use memchr::{memchr, memrchr};
+use crate::{LineTerminator, Match, Matcher, NoCaptures, PatternID};
+
+/// haystack 中的一行，end 包含行终止符，content_end 不包含行终止符（CRLF 时也不包含 `\r`）
+#[derive(Clone, Copy, Debug)]
+struct Line {
+    start: usize,
+    end: usize,
+    content_end: usize,
+}
+
+impl Line {
+    /// pos 所在的行
+    fn containing(haystack: &[u8], line_term: LineTerminator, pos: usize) -> Line {
+        let term = line_term.as_byte();
+        let start = memrchr(term, &haystack[..pos]).map_or(0, |i| i + 1);
+        let end = memchr(term, &haystack[start..]).map_or(haystack.len(), |i| start + i + 1);
+        let mut content_end = end;
+        if content_end > start && haystack[content_end - 1] == term {
+            content_end -= 1;
+            if line_term.is_crlf() && content_end > start && haystack[content_end - 1] == b'\r' {
+                content_end -= 1;
+            }
+        }
+        Line { start, end, content_end }
+    }
+
+    /// 是否是 haystack 的最后一行，空的 haystack 也看作有一个空行
+    fn is_last(&self, haystack: &[u8]) -> bool {
+        self.end >= haystack.len()
+    }
+}
+
+/// 匹配同时被 first 和 second 匹配的行，报告这些行中 first 和 second 的所有匹配项
+/// second 的模式序号排在 first 之后，即 second 的 PatternID::ZERO 报告为 first.pattern_count()
+#[derive(Clone, Debug)]
+pub struct AndMatcher<A, B> {
+    first: A,
+    second: B,
+    line_term: LineTerminator,
+}
+
+impl<A: Matcher, B: Matcher<Error = A::Error>> AndMatcher<A, B> {
+    /// 行终止符默认使用 first 或 second 报告的行终止符，都没有时使用 `\n`
+    pub fn new(first: A, second: B) -> AndMatcher<A, B> {
+        let line_term = first.line_terminator()
+            .or_else(|| second.line_terminator())
+            .unwrap_or_default();
+        AndMatcher { first, second, line_term }
+    }
+
+    /// 设置划分行使用的行终止符，需要和 Searcher 的行终止符一致
+    pub fn with_line_terminator(mut self, line_term: LineTerminator) -> AndMatcher<A, B> {
+        self.line_term = line_term;
+        self
+    }
+
+    /// 查找 line[at..] 中 first 和 second 最靠前的匹配项，位置相同时优先 first
+    fn find_in_line(
+        &self,
+        line: &[u8],
+        at: usize,
+    ) -> Result<Option<(Match, PatternID)>, A::Error> {
+        if at > line.len() {
+            return Ok(None);
+        }
+        let first = self.first.find_pattern_at(line, at)?;
+        let offset = self.first.pattern_count();
+        let second = self.second.find_pattern_at(line, at)?
+            .map(|(m, pid)| (m, PatternID::new(offset + pid.as_usize())));
+        Ok(match (first, second) {
+            (Some(a), Some(b)) => Some(if b.0.start() < a.0.start() { b } else { a }),
+            (a, b) => a.or(b),
+        })
+    }
+}
+
+impl<A: Matcher, B: Matcher<Error = A::Error>> Matcher for AndMatcher<A, B> {
+    type Captures = NoCaptures;
+    type Error = A::Error;
+
+    #[inline]
+    fn new_captures(&self) -> Result<NoCaptures, A::Error> {
+        Ok(NoCaptures::new())
+    }
+
+    #[inline]
+    fn pattern_count(&self) -> usize {
+        self.first.pattern_count() + self.second.pattern_count()
+    }
+
+    #[inline]
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, A::Error> {
+        Ok(self.find_pattern_at(haystack, at)?.map(|(m, _)| m))
+    }
+
+    /// 先用 first 跳到可能满足条件的行，再判断这一行 second 是否也能匹配
+    /// 行是否满足条件看的是整行，即使 at 之前的部分才有 first 或 second 的匹配项
+    fn find_pattern_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+    ) -> Result<Option<(Match, PatternID)>, A::Error> {
+        let mut search = Line::containing(haystack, self.line_term, at).start;
+        loop {
+            let m = match self.first.find_at(haystack, search)? {
+                None => return Ok(None),
+                Some(m) => m,
+            };
+            let line = Line::containing(haystack, self.line_term, m.start());
+            let content = &haystack[..line.content_end];
+            if m.start() <= line.content_end && self.second.is_match_at(content, line.start)? {
+                let found = self.find_in_line(content, cmp::max(at, line.start))?;
+                if found.is_some() {
+                    return Ok(found);
+                }
+            }
+            if line.is_last(haystack) {
+                return Ok(None);
+            }
+            search = line.end;
+        }
+    }
+
+    /// 匹配项总是限制在一行内
+    #[inline]
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        Some(self.line_term)
+    }
+}
+
+/// 匹配不被 matcher 匹配的行
+/// 匹配项是行首的空匹配，所以输出时不会高亮任何内容，和 AndMatcher 组合时只起到过滤行的作用
+#[derive(Clone, Debug)]
+pub struct NotMatcher<M> {
+    matcher: M,
+    line_term: LineTerminator,
+}
+
+impl<M: Matcher> NotMatcher<M> {
+    /// 行终止符默认使用 matcher 报告的行终止符，没有时使用 `\n`
+    pub fn new(matcher: M) -> NotMatcher<M> {
+        let line_term = matcher.line_terminator().unwrap_or_default();
+        NotMatcher { matcher, line_term }
+    }
+
+    /// 设置划分行使用的行终止符，需要和 Searcher 的行终止符一致
+    pub fn with_line_terminator(mut self, line_term: LineTerminator) -> NotMatcher<M> {
+        self.line_term = line_term;
+        self
+    }
+}
+
+impl<M: Matcher> Matcher for NotMatcher<M> {
+    type Captures = NoCaptures;
+    type Error = M::Error;
+
+    #[inline]
+    fn new_captures(&self) -> Result<NoCaptures, M::Error> {
+        Ok(NoCaptures::new())
+    }
+
+    /// 逐行判断 matcher 是否能匹配，at 所在行的行首在 at 之前时从下一行开始
+    /// at 等于 haystack 长度时看作一个空行，AndMatcher 判断空行时传入的 haystack 就以上一行的行终止符结尾
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, M::Error> {
+        let mut line = Line::containing(haystack, self.line_term, at);
+        if line.start < at {
+            if line.is_last(haystack) {
+                return Ok(None);
+            }
+            line = Line::containing(haystack, self.line_term, line.end);
+        }
+        loop {
+            let content = &haystack[..line.content_end];
+            if !self.matcher.is_match_at(content, line.start)? {
+                return Ok(Some(Match::zero(line.start)));
+            }
+            if line.is_last(haystack) {
+                return Ok(None);
+            }
+            line = Line::containing(haystack, self.line_term, line.end);
+        }
+    }
+
+    /// 匹配项总是限制在一行内
+    #[inline]
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        Some(self.line_term)
+    }
+}
+
+/// 多个 Matcher 的并集，匹配任意一个 Matcher 匹配的内容，并报告匹配项是哪个模式匹配到的
+/// 每个 Matcher 的模式序号依次排列，比如第一个 Matcher 有两个模式时，第二个 Matcher 的 PatternID::ZERO 报告为 2
+#[derive(Clone, Debug)]
+pub struct SetMatcher<M> {
+    matchers: Vec<M>,
+    /// 每个 Matcher 第一个模式的序号
+    offsets: Vec<usize>,
+    pattern_count: usize,
+}
+
+impl<M: Matcher> SetMatcher<M> {
+    pub fn new(matchers: Vec<M>) -> SetMatcher<M> {
+        let mut offsets = Vec::with_capacity(matchers.len());
+        let mut pattern_count = 0;
+        for matcher in matchers.iter() {
+            offsets.push(pattern_count);
+            pattern_count += matcher.pattern_count();
+        }
+        SetMatcher { matchers, offsets, pattern_count }
+    }
+}
+
+impl<M: Matcher> Matcher for SetMatcher<M> {
+    type Captures = NoCaptures;
+    type Error = M::Error;
+
+    #[inline]
+    fn new_captures(&self) -> Result<NoCaptures, M::Error> {
+        Ok(NoCaptures::new())
+    }
+
+    #[inline]
+    fn pattern_count(&self) -> usize {
+        self.pattern_count
+    }
+
+    #[inline]
+    fn is_match_at(&self, haystack: &[u8], at: usize) -> Result<bool, M::Error> {
+        for matcher in self.matchers.iter() {
+            if matcher.is_match_at(haystack, at)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    #[inline]
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, M::Error> {
+        Ok(self.find_pattern_at(haystack, at)?.map(|(m, _)| m))
+    }
+
+    /// 返回所有 Matcher 中起始位置最靠前的匹配项，位置相同时优先排在前面的 Matcher
+    fn find_pattern_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+    ) -> Result<Option<(Match, PatternID)>, M::Error> {
+        let mut best: Option<(Match, PatternID)> = None;
+        for (matcher, &offset) in self.matchers.iter().zip(self.offsets.iter()) {
+            let Some((m, pid)) = matcher.find_pattern_at(haystack, at)? else {
+                continue;
+            };
+            if best.is_none_or(|(b, _)| m.start() < b.start()) {
+                best = Some((m, PatternID::new(offset + pid.as_usize())));
+            }
+        }
+        Ok(best)
+    }
+
+    /// 所有 Matcher 报告相同的行终止符时才能保证匹配项不跨行
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        let mut line_terms = self.matchers.iter().map(|m| m.line_terminator());
+        let first = line_terms.next()??;
+        line_terms.all(|lt| lt == Some(first)).then_some(first)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LineTerminator, Match, Matcher, NoCaptures, NoError, PatternID};
+    use super::{AndMatcher, NotMatcher, SetMatcher};
+
+    /// 按字面量查找的 Matcher，保证不匹配 `\n`
+    #[derive(Clone, Debug)]
+    struct Literal(&'static str);
+
+    impl Matcher for Literal {
+        type Captures = NoCaptures;
+        type Error = NoError;
+
+        fn new_captures(&self) -> Result<NoCaptures, NoError> {
+            Ok(NoCaptures::new())
+        }
+
+        fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+            let needle = self.0.as_bytes();
+            Ok(haystack[at..]
+                .windows(needle.len())
+                .position(|w| w == needle)
+                .map(|i| Match::new(at + i, at + i + needle.len())))
+        }
+
+        fn line_terminator(&self) -> Option<LineTerminator> {
+            Some(LineTerminator::byte(b'\n'))
+        }
+    }
+
+    fn find_all<M: Matcher<Error = NoError>>(matcher: &M, haystack: &str) -> Vec<(usize, usize, usize)> {
+        let mut found = vec![];
+        matcher.find_pattern_iter_at(haystack.as_bytes(), 0, |m, pid| {
+            found.push((m.start(), m.end(), pid.as_usize()));
+            true
+        }).unwrap();
+        found
+    }
+
+    #[test]
+    fn set_matcher_pattern_ids() {
+        let matcher = SetMatcher::new(vec![Literal("foo"), Literal("bar"), Literal("fo")]);
+        assert_eq!(3, matcher.pattern_count());
+        assert_eq!(
+            vec![(0, 3, 0), (4, 7, 1), (8, 11, 0)],
+            find_all(&matcher, "foo bar foo"),
+        );
+        assert_eq!(Some(LineTerminator::byte(b'\n')), matcher.line_terminator());
+        let empty: SetMatcher<Literal> = SetMatcher::new(vec![]);
+        assert_eq!(None, empty.find_at(b"foo", 0).unwrap());
+        assert_eq!(None, empty.line_terminator());
+    }
+
+    #[test]
+    fn and_matcher_requires_both_on_same_line() {
+        let matcher = AndMatcher::new(Literal("foo"), Literal("bar"));
+        let haystack = "foo\nbar\nbar foo\nfoo\n";
+        // 第 3 行同时包含 foo 和 bar，两者都会报告，bar 的序号排在 foo 后面
+        assert_eq!(vec![(8, 11, 1), (12, 15, 0)], find_all(&matcher, haystack));
+        // 从行中间开始查找时，行是否满足条件仍然看整行
+        assert_eq!(
+            Some((Match::new(12, 15), PatternID::ZERO)),
+            matcher.find_pattern_at(haystack.as_bytes(), 11).unwrap(),
+        );
+        assert_eq!(None, matcher.find_at(haystack.as_bytes(), 15).unwrap());
+    }
+
+    #[test]
+    fn not_matcher_matches_other_lines() {
+        let matcher = NotMatcher::new(Literal("foo"));
+        let haystack = "foo\nbar\n\nfoo bar";
+        assert_eq!(vec![(4, 4, 0), (8, 8, 0)], find_all(&matcher, haystack));
+        assert_eq!(Some(Match::zero(0)), matcher.find_at(b"", 0).unwrap());
+        assert_eq!(None, matcher.find_at(b"foo\n", 0).unwrap());
+    }
+
+    #[test]
+    fn and_not_matcher() {
+        // 包含 foo 但不包含 bar 的行
+        let matcher = AndMatcher::new(Literal("foo"), NotMatcher::new(Literal("bar")));
+        let haystack = "foo bar\r\nfoo baz\r\nbaz\r\n";
+        let matcher = matcher.with_line_terminator(LineTerminator::crlf());
+        assert_eq!(vec![(9, 12, 0)], find_all(&matcher, haystack));
+        assert_eq!(2, matcher.pattern_count());
+    }
+
+    #[test]
+    fn and_not_matcher_empty_lines() {
+        // 既不包含 a 也不包含 b 的行，第一行之后的空行也要能匹配
+        let matcher = AndMatcher::new(NotMatcher::new(Literal("a")), NotMatcher::new(Literal("b")));
+        let haystack = "a\n\nb\n\nc\n";
+        assert_eq!(vec![(2, 2, 0), (5, 5, 0), (6, 6, 0)], find_all(&matcher, haystack));
+        // 行终止符之后的空位置单独作为 haystack 末尾传入时看作空行
+        let matcher = NotMatcher::new(Literal("b"));
+        assert_eq!(Some(Match::zero(2)), matcher.find_at(b"a\n", 2).unwrap());
+    }
+}
@@ -0,0 +1,129 @@
+use memchr::memchr;
+
+/// 将 replacement 中的捕获组引用替换成对应捕获组匹配的内容，结果追加到 dst
+/// 支持的写法和 regex 包一致：`$1`、`$name`、`${1}`、`${name}`，`$$` 表示字面量 `$`
+/// 引用的捕获组不存在或者没有参与匹配时替换成空字符串
+pub(crate) fn interpolate<A, N>(
+    mut replacement: &[u8],
+    mut append: A,
+    mut name_to_index: N,
+    dst: &mut Vec<u8>,
+) where
+    A: FnMut(usize, &mut Vec<u8>),
+    N: FnMut(&str) -> Option<usize>,
+{
+    while !replacement.is_empty() {
+        match memchr(b'$', replacement) {
+            None => break,
+            Some(i) => {
+                dst.extend(&replacement[..i]);
+                replacement = &replacement[i..];
+            }
+        }
+        if replacement.get(1) == Some(&b'$') {
+            dst.push(b'$');
+            replacement = &replacement[2..];
+            continue;
+        }
+        let cap_ref = match find_cap_ref(replacement) {
+            Some(cap_ref) => cap_ref,
+            None => {
+                dst.push(b'$');
+                replacement = &replacement[1..];
+                continue;
+            }
+        };
+        replacement = &replacement[cap_ref.end..];
+        match cap_ref.cap {
+            Ref::Number(i) => append(i, dst),
+            Ref::Named(name) => {
+                if let Some(i) = name_to_index(name) {
+                    append(i, dst);
+                }
+            }
+        }
+    }
+    dst.extend(replacement);
+}
+
+/// 捕获组引用，按序号或者按名称
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Ref<'a> {
+    Named(&'a str),
+    Number(usize),
+}
+
+/// 一个捕获组引用以及它在 replacement 中结束的位置
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct CapRef<'a> {
+    cap: Ref<'a>,
+    end: usize,
+}
+
+/// 解析 replacement 开头（以 `$` 开头）的捕获组引用，不是合法的引用时返回 None
+fn find_cap_ref(replacement: &[u8]) -> Option<CapRef<'_>> {
+    if replacement.len() <= 1 || replacement[0] != b'$' {
+        return None;
+    }
+    let braced = replacement[1] == b'{';
+    let start = if braced { 2 } else { 1 };
+    let mut end = start;
+    while replacement.get(end).is_some_and(|&b| is_valid_cap_letter(b)) {
+        end += 1;
+    }
+    if end == start {
+        return None;
+    }
+    // 只包含合法字符，一定是 UTF-8
+    let name = std::str::from_utf8(&replacement[start..end]).unwrap();
+    if braced {
+        if replacement.get(end) != Some(&b'}') {
+            return None;
+        }
+        end += 1;
+    }
+    let cap = match name.parse::<usize>() {
+        Ok(i) => Ref::Number(i),
+        Err(_) => Ref::Named(name),
+    };
+    Some(CapRef { cap, end })
+}
+
+fn is_valid_cap_letter(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b == b'_'
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpolate::interpolate;
+
+    /// 用 groups 模拟捕获组，名称 "first" 对应第 1 组
+    fn interp(groups: &[&str], replacement: &str) -> String {
+        let mut dst = vec![];
+        interpolate(
+            replacement.as_bytes(),
+            |i, dst| {
+                if let Some(group) = groups.get(i) {
+                    dst.extend(group.as_bytes());
+                }
+            },
+            |name| if name == "first" { Some(1) } else { None },
+            &mut dst,
+        );
+        String::from_utf8(dst).unwrap()
+    }
+
+    #[test]
+    fn interpolate_refs() {
+        let groups = ["ab", "a", "b"];
+        assert_eq!("b-a", interp(&groups, "$2-$1"));
+        assert_eq!("a", interp(&groups, "$first"));
+        assert_eq!("ax", interp(&groups, "${1}x"));
+        // `$1x` 会被当作名称为 "1x" 的捕获组，不存在所以替换成空
+        assert_eq!("", interp(&groups, "$1x"));
+        assert_eq!("$1", interp(&groups, "$$1"));
+        assert_eq!("cost: $", interp(&groups, "cost: $"));
+        assert_eq!("${1", interp(&groups, "${1"));
+        assert_eq!("", interp(&groups, "$9$missing"));
+    }
+}
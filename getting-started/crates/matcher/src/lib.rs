@@ -1,5 +1,10 @@
 use std::io;
 
+pub use crate::combinators::{AndMatcher, NotMatcher, SetMatcher};
+
+mod combinators;
+mod interpolate;
+
 /// 指向可寻址内存的连续块的可能为空的范围。
 /// 其实就是用于表示匹配字符串范围的，这里的匹配字符串可能是行、可能是行里匹配的字符串
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -60,6 +65,32 @@ impl Match {
     }
 }
 
+/// 匹配项所属模式的序号，Matcher 由多个模式构建时（比如多个 -e 参数）用于区分匹配项是哪个模式匹配到的
+/// 序号按构建时模式的顺序从 0 开始，只有一个模式的 Matcher 所有匹配项都属于 PatternID::ZERO
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct PatternID(u32);
+
+impl PatternID {
+    /// 第一个模式的序号
+    pub const ZERO: PatternID = PatternID(0);
+
+    /// 序号超过 u32::MAX 时 panic
+    #[inline]
+    pub fn new(id: usize) -> PatternID {
+        PatternID(u32::try_from(id).expect("pattern id exceeds u32::MAX"))
+    }
+
+    #[inline]
+    pub fn as_usize(&self) -> usize {
+        self.0 as usize
+    }
+
+    #[inline]
+    pub fn as_u32(&self) -> u32 {
+        self.0
+    }
+}
+
 /// 为了使用 container[index] 这个容器的语法糖， container 需要实现 std::ops::Index 特征
 impl std::ops::Index<Match> for [u8] {
     type Output = [u8];
@@ -213,9 +244,93 @@ pub enum LineMatchKind {
     Candidate(usize),
 }
 
+/// 一次匹配中各个捕获组的匹配范围，第 0 组是整个匹配
+pub trait Captures {
+    /// 捕获组的数量，包括第 0 组
+    fn len(&self) -> usize;
+
+    /// 第 i 个捕获组的匹配范围，捕获组不存在或者没有参与匹配时返回 None
+    fn get(&self, i: usize) -> Option<Match>;
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 将 replacement 中的 `$1`、`${name}` 这类引用替换成对应捕获组在 haystack 中匹配的内容，结果追加到 dst
+    /// name_to_index 用于将捕获组名称转换成序号，通常是 Matcher::capture_index
+    #[inline]
+    fn interpolate<F>(
+        &self,
+        name_to_index: F,
+        haystack: &[u8],
+        replacement: &[u8],
+        dst: &mut Vec<u8>,
+    ) where
+        F: Fn(&str) -> Option<usize>,
+    {
+        interpolate::interpolate(
+            replacement,
+            |i, dst| {
+                if let Some(range) = self.get(i) {
+                    dst.extend(&haystack[range]);
+                }
+            },
+            name_to_index,
+            dst,
+        )
+    }
+}
+
+/// 不支持捕获组的 Matcher 使用的 Captures 实现，没有任何捕获组
+#[derive(Clone, Debug)]
+pub struct NoCaptures(());
+
+impl NoCaptures {
+    pub fn new() -> NoCaptures {
+        NoCaptures(())
+    }
+}
+
+impl Default for NoCaptures {
+    fn default() -> NoCaptures {
+        NoCaptures::new()
+    }
+}
+
+impl Captures for NoCaptures {
+    #[inline]
+    fn len(&self) -> usize {
+        0
+    }
+
+    #[inline]
+    fn get(&self, _: usize) -> Option<Match> {
+        None
+    }
+}
+
 pub trait Matcher {
+    /// 匹配结果中捕获组的类型，不支持捕获组时使用 NoCaptures
+    type Captures: Captures;
+
     type Error: std::fmt::Display;
 
+    /// 创建一个空的 Captures，用于 captures_at 等方法保存匹配结果，可以复用以避免每次匹配都分配内存
+    fn new_captures(&self) -> Result<Self::Captures, Self::Error>;
+
+    /// 捕获组的数量，包括代表整个匹配的第 0 组，不支持捕获组时返回 0
+    #[inline]
+    fn capture_count(&self) -> usize {
+        0
+    }
+
+    /// 命名捕获组的序号，不存在这个名称或者不支持捕获组时返回 None
+    #[inline]
+    fn capture_index(&self, _name: &str) -> Option<usize> {
+        None
+    }
+
     /// 查找字节数组 haystack 中是否有匹配的字符串，只要匹配到一项就立即返回
     #[inline]
     fn is_match(&self, haystack: &[u8]) -> Result<bool, Self::Error> {
@@ -263,33 +378,214 @@ pub trait Matcher {
         at: usize,
     ) -> Result<Option<Match>, Self::Error>;
 
+    /// 构建 Matcher 的模式数量，find_pattern_at 返回的 PatternID 总是小于这个值
+    #[inline]
+    fn pattern_count(&self) -> usize {
+        1
+    }
+
+    /// 和 find_at 一样，同时返回匹配项所属模式的序号
+    /// 默认实现认为只有一个模式，所有匹配项都属于 PatternID::ZERO，由多个模式构建的 Matcher 需要重写这个方法
+    #[inline]
+    fn find_pattern_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+    ) -> Result<Option<(Match, PatternID)>, Self::Error> {
+        Ok(self.find_at(haystack, at)?.map(|m| (m, PatternID::ZERO)))
+    }
+
+    /// 返回匹配器保证永远不会匹配到的行终止符，没有这种保证时返回 None
+    /// Searcher 搜索前会检查这个行终止符和自身配置的行终止符是否一致，不一致直接报错，避免匹配结果跨行
+    #[inline]
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        None
+    }
+
     /// 迭代查询 bytes[at..] 中匹配的字符串交给 matched 闭包处理
+    #[inline]
     fn find_iter_at<F>(&self, bytes: &[u8], at: usize, mut matched: F) -> Result<(), Self::Error>
     where
         F: FnMut(Match) -> bool,
+    {
+        self.find_pattern_iter_at(bytes, at, |m, _| matched(m))
+    }
+
+    /// 和 find_iter_at 一样，同时将匹配项所属模式的序号交给 matched 闭包
+    fn find_pattern_iter_at<F>(&self, bytes: &[u8], at: usize, mut matched: F) -> Result<(), Self::Error>
+    where
+        F: FnMut(Match, PatternID) -> bool,
     {
         let mut last_end = at;
+        let mut last_match = None;
         loop {
             if last_end > bytes.len() {
                 return Ok(())
             }
-            let m = match self.find_at(bytes, last_end)? {
+            let (m, pid) = match self.find_pattern_at(bytes, last_end)? {
                 None => return Ok(()),
-                Some(m) => m,
+                Some(found) => found,
             };
-            last_end = m.end;
-            match matched(m) {
+            if m.start == m.end {
+                // 空匹配（比如 `a*` 或 look-around）需要向前推进一个字节，否则会在同一位置死循环
+                last_end = m.end + 1;
+                // 紧跟在上一个匹配后面的空匹配不报告
+                if Some(m.end) == last_match {
+                    continue;
+                }
+            } else {
+                last_end = m.end;
+            }
+            last_match = Some(m.end);
+            match matched(m, pid) {
                 true => continue,
                 false => return Ok(())
             }
         }
     }
+
+    /// 查找 haystack 中第一个匹配，将各个捕获组的范围保存到 caps，有匹配时返回 true
+    #[inline]
+    fn captures(
+        &self,
+        haystack: &[u8],
+        caps: &mut Self::Captures,
+    ) -> Result<bool, Self::Error> {
+        self.captures_at(haystack, 0, caps)
+    }
+
+    /// 和 captures 一样，只是从 haystack[at..] 开始查找，at 之前的内容仍然可以被 look-around 和 `\b` 这类断言看到
+    /// 默认实现表示不支持捕获组，总是返回 false，支持捕获组的 Matcher 需要重写这个方法
+    #[inline]
+    fn captures_at(
+        &self,
+        _haystack: &[u8],
+        _at: usize,
+        _caps: &mut Self::Captures,
+    ) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    /// 迭代 haystack 中所有匹配的捕获组交给 matched 闭包处理，闭包返回 false 时停止
+    #[inline]
+    fn captures_iter<F>(
+        &self,
+        haystack: &[u8],
+        caps: &mut Self::Captures,
+        matched: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&Self::Captures) -> bool,
+    {
+        self.captures_iter_at(haystack, 0, caps, matched)
+    }
+
+    /// 和 captures_iter 一样，只是从 haystack[at..] 开始查找，空匹配的处理和 find_iter_at 一致
+    fn captures_iter_at<F>(
+        &self,
+        haystack: &[u8],
+        at: usize,
+        caps: &mut Self::Captures,
+        mut matched: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&Self::Captures) -> bool,
+    {
+        let mut last_end = at;
+        let mut last_match = None;
+        loop {
+            if last_end > haystack.len() {
+                return Ok(());
+            }
+            if !self.captures_at(haystack, last_end, caps)? {
+                return Ok(());
+            }
+            let m = caps.get(0).expect("group 0 always participates in a match");
+            if m.start == m.end {
+                last_end = m.end + 1;
+                if Some(m.end) == last_match {
+                    continue;
+                }
+            } else {
+                last_end = m.end;
+            }
+            last_match = Some(m.end);
+            if !matched(caps) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// 将 haystack 中的每个匹配替换成 append 闭包写入 dst 的内容，匹配之间的内容原样写入 dst
+    /// append 闭包可以通过 Captures::interpolate 实现 `$1` 这类引用的替换，返回 false 时停止替换，剩余内容原样写入
+    #[inline]
+    fn replace_with_captures<F>(
+        &self,
+        haystack: &[u8],
+        caps: &mut Self::Captures,
+        dst: &mut Vec<u8>,
+        append: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&Self::Captures, &mut Vec<u8>) -> bool,
+    {
+        self.replace_with_captures_at(haystack, 0, caps, dst, append)
+    }
+
+    /// 和 replace_with_captures 一样，只是只替换 haystack[at..] 中的匹配，haystack[..at] 不会写入 dst
+    fn replace_with_captures_at<F>(
+        &self,
+        haystack: &[u8],
+        at: usize,
+        caps: &mut Self::Captures,
+        dst: &mut Vec<u8>,
+        mut append: F,
+    ) -> Result<(), Self::Error>
+    where
+        F: FnMut(&Self::Captures, &mut Vec<u8>) -> bool,
+    {
+        let mut last_match = at;
+        self.captures_iter_at(haystack, at, caps, |caps| {
+            let m = caps.get(0).expect("group 0 always participates in a match");
+            dst.extend(&haystack[last_match..m.start]);
+            last_match = m.end;
+            append(caps, dst)
+        })?;
+        dst.extend(&haystack[last_match..]);
+        Ok(())
+    }
 }
 
 /// 为所有实现了 Matcher 的类型重写 Matcher 下面的方法
 impl<'a, M: Matcher> Matcher for &'a M {
+    type Captures = M::Captures;
     type Error = M::Error;
 
+    #[inline]
+    fn new_captures(&self) -> Result<Self::Captures, Self::Error> {
+        (*self).new_captures()
+    }
+
+    #[inline]
+    fn capture_count(&self) -> usize {
+        (*self).capture_count()
+    }
+
+    #[inline]
+    fn capture_index(&self, name: &str) -> Option<usize> {
+        (*self).capture_index(name)
+    }
+
+    #[inline]
+    fn captures_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+        caps: &mut Self::Captures,
+    ) -> Result<bool, Self::Error> {
+        (*self).captures_at(haystack, at, caps)
+    }
+
     #[inline]
     fn is_match(&self, haystack: &[u8]) -> Result<bool, Self::Error> {
         (*self).is_match(haystack)
@@ -320,6 +616,25 @@ impl<'a, M: Matcher> Matcher for &'a M {
     ) -> Result<Option<Match>, Self::Error> {
         (*self).find_at(haystack, at)
     }
+
+    #[inline]
+    fn pattern_count(&self) -> usize {
+        (*self).pattern_count()
+    }
+
+    #[inline]
+    fn find_pattern_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+    ) -> Result<Option<(Match, PatternID)>, Self::Error> {
+        (*self).find_pattern_at(haystack, at)
+    }
+
+    #[inline]
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        (*self).line_terminator()
+    }
 }
 
 #[derive(Debug, Eq, PartialEq)]
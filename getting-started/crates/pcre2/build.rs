@@ -0,0 +1,41 @@
+use std::env;
+use std::path::PathBuf;
+
+/// 链接系统中安装的 libpcre2-8
+/// ripgrep 使用的 pcre2-sys 在找不到系统库时会使用 cc 编译内置的 PCRE2 源码，这里只支持链接系统库
+/// 可以通过环境变量 PCRE2_LIB_DIR 指定库所在目录
+fn main() {
+    println!("cargo:rerun-if-env-changed=PCRE2_LIB_DIR");
+
+    let mut dirs = vec![];
+    if let Some(dir) = env::var_os("PCRE2_LIB_DIR") {
+        dirs.push(PathBuf::from(dir));
+    }
+    for dir in [
+        "/usr/local/lib",
+        "/usr/lib",
+        "/usr/lib64",
+        "/usr/lib/x86_64-linux-gnu",
+        "/usr/lib/aarch64-linux-gnu",
+        "/opt/homebrew/lib",
+    ] {
+        dirs.push(PathBuf::from(dir));
+    }
+
+    for dir in dirs.iter() {
+        let names = ["libpcre2-8.so", "libpcre2-8.dylib", "libpcre2-8.a"];
+        if names.iter().any(|name| dir.join(name).exists()) {
+            println!("cargo:rustc-link-search=native={}", dir.display());
+            println!("cargo:rustc-link-lib=pcre2-8");
+            return;
+        }
+        // 只安装了运行时库（没有安装 libpcre2-dev 这类开发包）时，没有 libpcre2-8.so 这个软链接，直接链接带版本号的动态库
+        if dir.join("libpcre2-8.so.0").exists() {
+            println!("cargo:rustc-link-search=native={}", dir.display());
+            println!("cargo:rustc-link-lib=dylib:+verbatim=libpcre2-8.so.0");
+            return;
+        }
+    }
+    // 都找不到就交给链接器在默认搜索路径中查找
+    println!("cargo:rustc-link-lib=pcre2-8");
+}
@@ -0,0 +1,67 @@
+/// PCRE2 正则编译或匹配过程中的错误
+#[derive(Clone, Debug)]
+pub struct Error {
+    kind: ErrorKind,
+}
+
+impl Error {
+    /// 正则表达式编译失败，offset 是出错位置在 pattern 中的偏移
+    pub(crate) fn compile(code: i32, offset: usize) -> Error {
+        Error { kind: ErrorKind::Compile { message: error_message(code), offset } }
+    }
+
+    /// JIT 编译失败
+    pub(crate) fn jit(code: i32) -> Error {
+        Error { kind: ErrorKind::Jit(error_message(code)) }
+    }
+
+    /// 匹配过程中发生错误，比如超过了回溯限制
+    pub(crate) fn matching(code: i32) -> Error {
+        Error { kind: ErrorKind::Match(error_message(code)) }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// 编译错误，message 是 PCRE2 给出的错误信息
+    Compile { message: String, offset: usize },
+    /// JIT 编译错误
+    Jit(String),
+    /// 匹配错误
+    Match(String),
+}
+
+impl std::error::Error for Error {}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.kind {
+            ErrorKind::Compile { ref message, offset } => {
+                write!(f, "PCRE2: error compiling pattern at offset {}: {}", offset, message)
+            }
+            ErrorKind::Jit(ref message) => {
+                write!(f, "PCRE2: error JIT compiling pattern: {}", message)
+            }
+            ErrorKind::Match(ref message) => {
+                write!(f, "PCRE2: error matching: {}", message)
+            }
+        }
+    }
+}
+
+/// 通过 pcre2_get_error_message 获取错误码对应的错误信息
+fn error_message(code: i32) -> String {
+    let mut buf = [0u8; 256];
+    let rc = unsafe {
+        crate::ffi::pcre2_get_error_message_8(code, buf.as_mut_ptr(), buf.len())
+    };
+    if rc < 0 {
+        return format!("unknown PCRE2 error code {}", code);
+    }
+    String::from_utf8_lossy(&buf[..rc as usize]).into_owned()
+}
@@ -0,0 +1,106 @@
+// libpcre2-8 的 FFI 声明，只声明了这里用到的函数和常量
+// 常量值来自 pcre2.h，函数签名参考 https://www.pcre.org/current/doc/html/pcre2api.html
+
+#![allow(non_camel_case_types)]
+
+use std::os::raw::{c_int, c_void};
+
+/// PCRE2 的 8 位代码单元，即按字节匹配
+pub(crate) type PCRE2_UCHAR8 = u8;
+pub(crate) type PCRE2_SPTR8 = *const PCRE2_UCHAR8;
+pub(crate) type PCRE2_SIZE = usize;
+
+/// 编译后的正则表达式，不透明类型
+#[repr(C)]
+pub(crate) struct pcre2_code_8 {
+    _private: [u8; 0],
+}
+
+/// 匹配结果（ovector 等）的存储，不透明类型
+#[repr(C)]
+pub(crate) struct pcre2_match_data_8 {
+    _private: [u8; 0],
+}
+
+/// 编译上下文，用于设置换行符约定等，不透明类型
+#[repr(C)]
+pub(crate) struct pcre2_compile_context_8 {
+    _private: [u8; 0],
+}
+
+// 编译选项
+pub(crate) const PCRE2_CASELESS: u32 = 0x00000008;
+pub(crate) const PCRE2_DOTALL: u32 = 0x00000020;
+pub(crate) const PCRE2_MULTILINE: u32 = 0x00000400;
+pub(crate) const PCRE2_UCP: u32 = 0x00020000;
+pub(crate) const PCRE2_UTF: u32 = 0x00080000;
+pub(crate) const PCRE2_MATCH_INVALID_UTF: u32 = 0x04000000;
+
+// pcre2_jit_compile 选项
+pub(crate) const PCRE2_JIT_COMPLETE: u32 = 0x00000001;
+
+// 换行符约定，影响多行模式下 `^` `$` 以及 `.` 的行为
+pub(crate) const PCRE2_NEWLINE_LF: u32 = 2;
+pub(crate) const PCRE2_NEWLINE_ANYCRLF: u32 = 5;
+pub(crate) const PCRE2_NEWLINE_NUL: u32 = 6;
+
+// pcre2_pattern_info 查询项
+pub(crate) const PCRE2_INFO_CAPTURECOUNT: u32 = 4;
+pub(crate) const PCRE2_INFO_NAMECOUNT: u32 = 17;
+pub(crate) const PCRE2_INFO_NAMEENTRYSIZE: u32 = 18;
+pub(crate) const PCRE2_INFO_NAMETABLE: u32 = 19;
+
+// 错误码
+pub(crate) const PCRE2_ERROR_NOMATCH: c_int = -1;
+
+extern "C" {
+    pub(crate) fn pcre2_compile_8(
+        pattern: PCRE2_SPTR8,
+        length: PCRE2_SIZE,
+        options: u32,
+        errorcode: *mut c_int,
+        erroroffset: *mut PCRE2_SIZE,
+        ccontext: *mut pcre2_compile_context_8,
+    ) -> *mut pcre2_code_8;
+    pub(crate) fn pcre2_code_free_8(code: *mut pcre2_code_8);
+    pub(crate) fn pcre2_jit_compile_8(code: *mut pcre2_code_8, options: u32) -> c_int;
+    pub(crate) fn pcre2_pattern_info_8(
+        code: *const pcre2_code_8,
+        what: u32,
+        where_: *mut c_void,
+    ) -> c_int;
+
+    pub(crate) fn pcre2_compile_context_create_8(
+        gcontext: *mut c_void,
+    ) -> *mut pcre2_compile_context_8;
+    pub(crate) fn pcre2_compile_context_free_8(ccontext: *mut pcre2_compile_context_8);
+    pub(crate) fn pcre2_set_newline_8(
+        ccontext: *mut pcre2_compile_context_8,
+        value: u32,
+    ) -> c_int;
+
+    pub(crate) fn pcre2_match_data_create_from_pattern_8(
+        code: *const pcre2_code_8,
+        gcontext: *mut c_void,
+    ) -> *mut pcre2_match_data_8;
+    pub(crate) fn pcre2_match_data_free_8(match_data: *mut pcre2_match_data_8);
+    pub(crate) fn pcre2_get_ovector_pointer_8(
+        match_data: *mut pcre2_match_data_8,
+    ) -> *mut PCRE2_SIZE;
+
+    pub(crate) fn pcre2_match_8(
+        code: *const pcre2_code_8,
+        subject: PCRE2_SPTR8,
+        length: PCRE2_SIZE,
+        startoffset: PCRE2_SIZE,
+        options: u32,
+        match_data: *mut pcre2_match_data_8,
+        mcontext: *mut c_void,
+    ) -> c_int;
+
+    pub(crate) fn pcre2_get_error_message_8(
+        errorcode: c_int,
+        buffer: *mut PCRE2_UCHAR8,
+        bufflen: PCRE2_SIZE,
+    ) -> c_int;
+}
@@ -0,0 +1,11 @@
+/// 基于 PCRE2 的 Matcher 实现，支持 look-around、反向引用这些 Rust Regex 不支持的语法
+/// 依赖系统中安装的 libpcre2-8，参考 build.rs
+pub use crate::{
+    error::{Error, ErrorKind},
+    matcher::{RegexCaptures, RegexMatcher, RegexMatcherBuilder},
+};
+
+mod error;
+mod ffi;
+mod matcher;
+mod regex;
@@ -0,0 +1,385 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+use grep_matcher::{Captures, Match, Matcher};
+use crate::error::Error;
+use crate::ffi;
+use crate::regex::{Code, MatchData};
+
+/// PCRE2 正则表达式配置
+#[derive(Clone, Debug)]
+struct Config {
+    /// 是否忽略大小写
+    case_insensitive: bool,
+    /// 智能模式，只有 pattern 中不包含大写字符时才忽略大小写
+    case_smart: bool,
+    /// 多行模式，`^` `$` 分别匹配每一行的开始和结束
+    multi_line: bool,
+    /// `.` 是否匹配换行符
+    dot_matches_new_line: bool,
+    /// 是否将 `\r\n` 也当作换行符，开启后多行模式下 `$` 可以匹配在 `\r\n` 之前
+    crlf: bool,
+    /// 多行模式下 `^` `$` 识别的行终止符，只支持 `\n` 和 NUL
+    line_terminator: u8,
+    /// 是否开启 UTF-8 模式（同时让 \w \d 这些类匹配 Unicode 字符）
+    utf: bool,
+    /// 是否开启 JIT 编译
+    jit: bool,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            case_insensitive: false,
+            case_smart: false,
+            multi_line: false,
+            dot_matches_new_line: false,
+            crlf: false,
+            line_terminator: b'\n',
+            utf: false,
+            jit: true,
+        }
+    }
+}
+
+impl Config {
+    /// 转换成 PCRE2 编译选项
+    fn options(&self, pattern: &str) -> u32 {
+        let mut options = 0;
+        if self.case_insensitive || (self.case_smart && !has_uppercase_literal(pattern)) {
+            options |= ffi::PCRE2_CASELESS;
+        }
+        if self.multi_line {
+            options |= ffi::PCRE2_MULTILINE;
+        }
+        if self.dot_matches_new_line {
+            options |= ffi::PCRE2_DOTALL;
+        }
+        if self.utf {
+            // PCRE2_MATCH_INVALID_UTF 让无效的 UTF-8 序列永远不会被匹配，而不是报错，因为被搜索的文件不一定是合法的 UTF-8
+            options |= ffi::PCRE2_UTF | ffi::PCRE2_UCP | ffi::PCRE2_MATCH_INVALID_UTF;
+        }
+        options
+    }
+
+    /// 转换成 PCRE2 换行符约定
+    fn newline(&self) -> u32 {
+        if self.crlf {
+            ffi::PCRE2_NEWLINE_ANYCRLF
+        } else if self.line_terminator == b'\x00' {
+            ffi::PCRE2_NEWLINE_NUL
+        } else {
+            ffi::PCRE2_NEWLINE_LF
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RegexMatcherBuilder {
+    config: Config,
+}
+
+impl Default for RegexMatcherBuilder {
+    fn default() -> RegexMatcherBuilder {
+        RegexMatcherBuilder::new()
+    }
+}
+
+impl RegexMatcherBuilder {
+    pub fn new() -> RegexMatcherBuilder {
+        RegexMatcherBuilder { config: Config::default() }
+    }
+
+    pub fn build(&self, pattern: &str) -> Result<RegexMatcher, Error> {
+        self.build_many(&[pattern])
+    }
+
+    /// 和 grep_regex 一样，多个 pattern 使用 "|" 拼接成一个 pattern
+    /// 只有一个 pattern 时不做包装，这样编译错误中的偏移量和用户输入的 pattern 是对应的
+    pub fn build_many<P: AsRef<str>>(&self, patterns: &[P]) -> Result<RegexMatcher, Error> {
+        let pattern = if patterns.len() == 1 {
+            patterns[0].as_ref().to_string()
+        } else {
+            let mut alts = vec![];
+            for p in patterns.iter() {
+                alts.push(format!("(?:{})", p.as_ref()));
+            }
+            alts.join("|")
+        };
+        log::debug!("final PCRE2 regex: {:?}", pattern);
+        let code = Code::new(
+            &pattern,
+            self.config.options(&pattern),
+            self.config.newline(),
+            self.config.jit,
+        )?;
+        let match_data = RefCell::new(MatchData::new(&code));
+        Ok(RegexMatcher { code: Arc::new(code), match_data })
+    }
+
+    pub fn case_insensitive(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.case_insensitive = yes;
+        self
+    }
+
+    pub fn case_smart(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.case_smart = yes;
+        self
+    }
+
+    pub fn multi_line(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.multi_line = yes;
+        self
+    }
+
+    pub fn dot_matches_new_line(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.dot_matches_new_line = yes;
+        self
+    }
+
+    pub fn crlf(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.crlf = yes;
+        self
+    }
+
+    /// 设置多行模式下 `^` `$` 识别的行终止符，只支持 `\n` 和 NUL（对应 --null-data）
+    pub fn line_terminator(&mut self, line_term: u8) -> &mut RegexMatcherBuilder {
+        assert!(
+            line_term == b'\n' || line_term == b'\x00',
+            "PCRE2 only supports \\n or NUL line terminators"
+        );
+        self.config.line_terminator = line_term;
+        self
+    }
+
+    pub fn utf(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.utf = yes;
+        self
+    }
+
+    pub fn jit(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.jit = yes;
+        self
+    }
+}
+
+/// 基于 PCRE2 的 Matcher 实现
+/// 注意它不会报告行终止符（PCRE2 无法像 Rust Regex 那样从 Hir 中剔除行终止符），所以 Searcher 会逐行匹配
+/// 编译后的正则表达式在 clone 出来的匹配器之间共享，MatchData 则是每个匹配器一份，所以每个搜索线程需要 clone 一个匹配器
+pub struct RegexMatcher {
+    code: Arc<Code>,
+    /// find_at 复用的 MatchData，避免每次匹配都分配一次
+    match_data: RefCell<MatchData>,
+}
+
+impl Clone for RegexMatcher {
+    fn clone(&self) -> RegexMatcher {
+        let match_data = RefCell::new(MatchData::new(&self.code));
+        RegexMatcher { code: Arc::clone(&self.code), match_data }
+    }
+}
+
+impl std::fmt::Debug for RegexMatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RegexMatcher").field("code", &self.code).finish()
+    }
+}
+
+impl Matcher for RegexMatcher {
+    type Captures = RegexCaptures;
+    type Error = Error;
+
+    #[inline]
+    fn new_captures(&self) -> Result<RegexCaptures, Error> {
+        Ok(RegexCaptures { data: MatchData::new(&self.code), matched: false })
+    }
+
+    #[inline]
+    fn capture_count(&self) -> usize {
+        self.code.capture_count()
+    }
+
+    #[inline]
+    fn capture_index(&self, name: &str) -> Option<usize> {
+        self.code.capture_index(name)
+    }
+
+    #[inline]
+    fn captures_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+        caps: &mut RegexCaptures,
+    ) -> Result<bool, Error> {
+        caps.matched = caps.data.search(&self.code, haystack, at)?;
+        Ok(caps.matched)
+    }
+
+    #[inline]
+    fn find_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+    ) -> Result<Option<Match>, Error> {
+        let match_data = self.match_data.borrow_mut();
+        if !match_data.search(&self.code, haystack, at)? {
+            return Ok(None);
+        }
+        Ok(match_data.group(0).map(|(s, e)| Match::new(s, e)))
+    }
+}
+
+/// PCRE2 RegexMatcher 的捕获组，保存最近一次 captures_at 的结果
+pub struct RegexCaptures {
+    data: MatchData,
+    /// 最近一次 captures_at 是否匹配，不匹配时 MatchData 中的内容是无效的
+    matched: bool,
+}
+
+impl std::fmt::Debug for RegexCaptures {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let groups: Vec<Option<Match>> = (0..self.len()).map(|i| self.get(i)).collect();
+        f.debug_struct("RegexCaptures").field("groups", &groups).finish()
+    }
+}
+
+impl Captures for RegexCaptures {
+    #[inline]
+    fn len(&self) -> usize {
+        self.data.groups()
+    }
+
+    #[inline]
+    fn get(&self, i: usize) -> Option<Match> {
+        if !self.matched {
+            return None;
+        }
+        self.data.group(i).map(|(s, e)| Match::new(s, e))
+    }
+}
+
+/// pattern 中是否包含大写字符，跳过转义序列（比如 `\S` `\W`）
+fn has_uppercase_literal(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            chars.next();
+        } else if c.is_uppercase() {
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_matcher::{Captures, Match, Matcher};
+    use crate::matcher::RegexMatcherBuilder;
+
+    #[test]
+    fn look_around() {
+        let matcher = RegexMatcherBuilder::new().build(r"(?<=\$)\d+(?!\.)").unwrap();
+        assert_eq!(Some(Match::new(8, 10)), matcher.find_at(b"price: $42 each", 0).unwrap());
+        assert_eq!(None, matcher.find_at(b"price: 42 each", 0).unwrap());
+        assert_eq!(None, matcher.find_at(b"price: $4.2", 0).unwrap().filter(|m| m.start() == 8 && m.end() == 9));
+        // look-behind 可以看到 at 之前的内容
+        assert_eq!(Some(Match::new(1, 3)), matcher.find_at(b"$42", 1).unwrap());
+    }
+
+    #[test]
+    fn backreference() {
+        let matcher = RegexMatcherBuilder::new().build(r"\b(\w+)\s+\1\b").unwrap();
+        assert_eq!(Some(Match::new(0, 7)), matcher.find_at(b"the the cat", 0).unwrap());
+        assert!(!matcher.is_match(b"the cat").unwrap());
+    }
+
+    /// look-around 会产生空匹配，find_iter 需要能正常推进
+    #[test]
+    fn find_iter_empty_matches() {
+        let matcher = RegexMatcherBuilder::new().build("x*").unwrap();
+        let mut matches = vec![];
+        matcher.find_iter_at(b"xab", 0, |m| {
+            matches.push(m);
+            true
+        }).unwrap();
+        assert_eq!(vec![Match::new(0, 1), Match::new(2, 2), Match::new(3, 3)], matches);
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let mut builder = RegexMatcherBuilder::new();
+        assert!(!builder.build("foo").unwrap().is_match(b"FOO").unwrap());
+        builder.case_insensitive(true);
+        assert!(builder.build("foo").unwrap().is_match(b"FOO").unwrap());
+        assert!(builder.build("Foo").unwrap().is_match(b"fOO").unwrap());
+    }
+
+    /// clone 出来的匹配器有自己的 MatchData，交替使用不会互相影响
+    #[test]
+    fn clone_has_own_match_data() {
+        let matcher = RegexMatcherBuilder::new().build(r"\d+").unwrap();
+        let cloned = matcher.clone();
+        assert_eq!(Some(Match::new(2, 4)), matcher.find_at(b"a 42", 0).unwrap());
+        assert_eq!(None, cloned.find_at(b"none", 0).unwrap());
+        assert_eq!(Some(Match::new(0, 1)), matcher.find_at(b"7", 0).unwrap());
+        assert_eq!(Some(Match::new(1, 3)), cloned.find_at(b"x10", 0).unwrap());
+    }
+
+    #[test]
+    fn case_smart() {
+        let mut builder = RegexMatcherBuilder::new();
+        builder.case_smart(true);
+        assert!(builder.build("foo").unwrap().is_match(b"FOO").unwrap());
+        assert!(!builder.build("Foo").unwrap().is_match(b"FOO").unwrap());
+        // 转义序列中的大写字符不算
+        assert!(builder.build(r"\Sfoo").unwrap().is_match(b"XFOO").unwrap());
+    }
+
+    #[test]
+    fn crlf_and_multi_line() {
+        let mut builder = RegexMatcherBuilder::new();
+        builder.multi_line(true);
+        assert!(!builder.build("foo$").unwrap().is_match(b"foo\r\n").unwrap());
+        builder.crlf(true);
+        assert_eq!(Some(Match::new(0, 3)), builder.build("foo$").unwrap().find_at(b"foo\r\n", 0).unwrap());
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        let err = RegexMatcherBuilder::new().build("foo(").unwrap_err();
+        assert!(err.to_string().contains("offset 4"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_utf8_never_matches() {
+        let mut builder = RegexMatcherBuilder::new();
+        builder.utf(true);
+        let matcher = builder.build(r"\w+").unwrap();
+        assert_eq!(Some(Match::new(1, 4)), matcher.find_at(b"\xFFabc", 0).unwrap());
+    }
+
+    #[test]
+    fn captures_named() {
+        let matcher = RegexMatcherBuilder::new().build(r"(?<key>\w+)=(?<value>\d+)?").unwrap();
+        assert_eq!(3, matcher.capture_count());
+        assert_eq!(Some(1), matcher.capture_index("key"));
+        assert_eq!(Some(2), matcher.capture_index("value"));
+        assert_eq!(None, matcher.capture_index("missing"));
+
+        let mut caps = matcher.new_captures().unwrap();
+        assert!(matcher.captures(b"port=80", &mut caps).unwrap());
+        assert_eq!(Some(Match::new(0, 4)), caps.get(1));
+        assert_eq!(Some(Match::new(5, 7)), caps.get(2));
+        assert!(matcher.captures(b"host=", &mut caps).unwrap());
+        assert_eq!(None, caps.get(2));
+        assert!(!matcher.captures(b"none", &mut caps).unwrap());
+        assert_eq!(None, caps.get(0));
+
+        let haystack = b"a=1 b=2";
+        let mut dst = vec![];
+        matcher.replace_with_captures(haystack, &mut caps, &mut dst, |caps, dst| {
+            caps.interpolate(|name| matcher.capture_index(name), haystack, b"$value=${key}", dst);
+            true
+        }).unwrap();
+        assert_eq!("1=a 2=b", String::from_utf8(dst).unwrap());
+    }
+}
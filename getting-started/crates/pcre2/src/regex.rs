@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::os::raw::c_void;
+use std::ptr;
+use crate::error::Error;
+use crate::ffi;
+
+/// 对 pcre2_code 的安全封装，编译后的正则表达式是只读的，可以在多线程间共享
+pub(crate) struct Code {
+    code: *mut ffi::pcre2_code_8,
+    /// 捕获组的数量，不包括代表整个匹配的第 0 组
+    capture_count: usize,
+    /// 命名捕获组的名称到序号的映射
+    capture_names: HashMap<String, usize>,
+}
+
+unsafe impl Send for Code {}
+
+unsafe impl Sync for Code {}
+
+impl Drop for Code {
+    fn drop(&mut self) {
+        unsafe { ffi::pcre2_code_free_8(self.code) }
+    }
+}
+
+impl std::fmt::Debug for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Code")
+            .field("capture_count", &self.capture_count)
+            .field("capture_names", &self.capture_names)
+            .finish()
+    }
+}
+
+impl Code {
+    /// 编译正则表达式
+    /// options 是 PCRE2_CASELESS 这类编译选项，newline 是 PCRE2_NEWLINE_LF 这类换行符约定
+    /// jit 为 true 时尝试 JIT 编译，当前平台不支持 JIT 时退回到解释执行
+    pub(crate) fn new(pattern: &str, options: u32, newline: u32, jit: bool) -> Result<Code, Error> {
+        let (mut error_code, mut error_offset) = (0, 0);
+        let code = unsafe {
+            let ctx = ffi::pcre2_compile_context_create_8(ptr::null_mut());
+            assert!(!ctx.is_null(), "out of memory");
+            ffi::pcre2_set_newline_8(ctx, newline);
+            let code = ffi::pcre2_compile_8(
+                pattern.as_ptr(),
+                pattern.len(),
+                options,
+                &mut error_code,
+                &mut error_offset,
+                ctx,
+            );
+            ffi::pcre2_compile_context_free_8(ctx);
+            code
+        };
+        if code.is_null() {
+            return Err(Error::compile(error_code, error_offset));
+        }
+        let mut code = Code { code, capture_count: 0, capture_names: HashMap::new() };
+        code.capture_count = code.info_u32(ffi::PCRE2_INFO_CAPTURECOUNT) as usize;
+        code.capture_names = code.read_name_table();
+        if jit {
+            let rc = unsafe { ffi::pcre2_jit_compile_8(code.code, ffi::PCRE2_JIT_COMPLETE) };
+            if rc < 0 {
+                log::debug!("PCRE2 JIT unavailable, using interpreter: {}", Error::jit(rc));
+            }
+        }
+        Ok(code)
+    }
+
+    /// 查询 pcre2_pattern_info 中值类型为 uint32_t 的信息
+    fn info_u32(&self, what: u32) -> u32 {
+        let mut value: u32 = 0;
+        let rc = unsafe {
+            ffi::pcre2_pattern_info_8(self.code, what, &mut value as *mut u32 as *mut c_void)
+        };
+        assert_eq!(0, rc, "failed to query pattern info {}", what);
+        value
+    }
+
+    /// 读取命名捕获组的名称表
+    /// 名称表中每一项长度都是 entry_size，前两个字节是大端序的捕获组序号，后面是以 NUL 结尾的名称
+    fn read_name_table(&self) -> HashMap<String, usize> {
+        let mut names = HashMap::new();
+        let count = self.info_u32(ffi::PCRE2_INFO_NAMECOUNT) as usize;
+        if count == 0 {
+            return names;
+        }
+        let entry_size = self.info_u32(ffi::PCRE2_INFO_NAMEENTRYSIZE) as usize;
+        let mut table: *const u8 = ptr::null();
+        let rc = unsafe {
+            ffi::pcre2_pattern_info_8(
+                self.code,
+                ffi::PCRE2_INFO_NAMETABLE,
+                &mut table as *mut *const u8 as *mut c_void,
+            )
+        };
+        assert_eq!(0, rc, "failed to query name table");
+        let table = unsafe { std::slice::from_raw_parts(table, count * entry_size) };
+        for entry in table.chunks_exact(entry_size) {
+            let index = usize::from(u16::from_be_bytes([entry[0], entry[1]]));
+            let name = &entry[2..];
+            let len = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+            names.insert(String::from_utf8_lossy(&name[..len]).into_owned(), index);
+        }
+        names
+    }
+
+    /// 捕获组的数量，包括代表整个匹配的第 0 组
+    pub(crate) fn capture_count(&self) -> usize {
+        self.capture_count + 1
+    }
+
+    /// 命名捕获组的序号
+    pub(crate) fn capture_index(&self, name: &str) -> Option<usize> {
+        self.capture_names.get(name).copied()
+    }
+}
+
+/// 对 pcre2_match_data 的安全封装，保存一次匹配的结果（各个捕获组的范围）
+pub(crate) struct MatchData {
+    match_data: *mut ffi::pcre2_match_data_8,
+    /// 捕获组数量 + 1
+    groups: usize,
+}
+
+// pcre2_match_data 只在匹配时被读写，没有和线程绑定的状态，可以移动到其他线程使用，但不能同时在多个线程中使用
+unsafe impl Send for MatchData {}
+
+impl Drop for MatchData {
+    fn drop(&mut self) {
+        unsafe { ffi::pcre2_match_data_free_8(self.match_data) }
+    }
+}
+
+impl MatchData {
+    pub(crate) fn new(code: &Code) -> MatchData {
+        let match_data = unsafe {
+            ffi::pcre2_match_data_create_from_pattern_8(code.code, ptr::null_mut())
+        };
+        assert!(!match_data.is_null(), "out of memory");
+        MatchData { match_data, groups: code.capture_count + 1 }
+    }
+
+    /// 执行一次匹配，匹配成功返回 true，结果保存在 MatchData 中
+    /// 注意 subject 是完整传给 PCRE2 的，所以 look-behind 可以看到 start 之前的内容
+    pub(crate) fn search(&self, code: &Code, subject: &[u8], start: usize) -> Result<bool, Error> {
+        let rc = unsafe {
+            ffi::pcre2_match_8(
+                code.code,
+                subject.as_ptr(),
+                subject.len(),
+                start,
+                0,
+                self.match_data,
+                ptr::null_mut(),
+            )
+        };
+        if rc == ffi::PCRE2_ERROR_NOMATCH {
+            Ok(false)
+        } else if rc < 0 {
+            Err(Error::matching(rc))
+        } else {
+            Ok(true)
+        }
+    }
+
+    /// 捕获组数量 + 1
+    pub(crate) fn groups(&self) -> usize {
+        self.groups
+    }
+
+    /// 返回第 i 个捕获组的范围，没有参与匹配的捕获组返回 None
+    pub(crate) fn group(&self, i: usize) -> Option<(usize, usize)> {
+        if i >= self.groups {
+            return None;
+        }
+        let ovector = unsafe {
+            std::slice::from_raw_parts(
+                ffi::pcre2_get_ovector_pointer_8(self.match_data),
+                self.groups * 2,
+            )
+        };
+        let (start, end) = (ovector[i * 2], ovector[i * 2 + 1]);
+        // 没有参与匹配的捕获组会被设置为 PCRE2_UNSET (即 usize::MAX)
+        if start == usize::MAX || end == usize::MAX {
+            return None;
+        }
+        Some((start, end))
+    }
+}
@@ -0,0 +1,171 @@
+//! 并行搜索时的输出
+//! 多个搜索线程直接写标准输出会让不同文件的结果交错在一起，所以每个线程把一个文件的结果先写到自己的 Buffer，
+//! 搜索完这个文件后再通过 BufferWriter 整体输出到标准输出
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use termcolor::ColorChoice;
+
+/// 搜索线程的输出缓冲，颜色以 ANSI 转义序列的形式保存在缓冲中（不支持颜色时不保存），输出时原样写出
+pub use termcolor::Buffer;
+
+/// 把各个搜索线程的 Buffer 整体输出到标准输出，可以在多个线程间共享
+/// 每个 Buffer 的内容输出时都持有标准输出的锁，所以同一个文件的结果不会被其他文件的结果打断
+#[derive(Debug)]
+pub struct BufferWriter {
+    wtr: termcolor::BufferWriter,
+    /// 两个非空 Buffer 之间输出的分隔符
+    separator: Option<Vec<u8>>,
+    /// 是否按文件序号的顺序输出
+    ordered: bool,
+    /// 输出状态，输出分隔符和 Buffer 时都持有这个锁，保证两者之间不会插入其他 Buffer
+    order: Mutex<Order>,
+}
+
+impl BufferWriter {
+    /// 输出到标准输出，color_choice 决定 buffer() 创建的缓冲是否保存颜色
+    pub fn stdout(color_choice: ColorChoice) -> BufferWriter {
+        BufferWriter {
+            wtr: termcolor::BufferWriter::stdout(color_choice),
+            separator: None,
+            ordered: false,
+            order: Mutex::new(Order::default()),
+        }
+    }
+
+    /// 设置两个非空 Buffer 之间输出的分隔符，比如以标题方式打印时不同文件的结果之间空一行，默认没有分隔符
+    /// 和 termcolor 不同，分隔符原样输出，不会额外加上换行
+    pub fn separator(&mut self, sep: Option<Vec<u8>>) -> &mut BufferWriter {
+        self.separator = sep;
+        self
+    }
+
+    /// 设置是否按文件序号的顺序输出，默认哪个文件先搜索完就先输出哪个
+    /// 按顺序输出时搜索仍然是并行的，只是先搜索完但还没轮到的文件的结果会暂存起来
+    pub fn ordered(&mut self, yes: bool) -> &mut BufferWriter {
+        self.ordered = yes;
+        self
+    }
+
+    /// 创建一个新的输出缓冲，每个搜索线程使用一个，搜索每个文件前清空
+    pub fn buffer(&self) -> Buffer {
+        self.wtr.buffer()
+    }
+
+    /// 输出第 index 个文件（从 0 开始）的结果
+    /// 按顺序输出时每个序号都必须调用一次（没有结果的文件传空缓冲），否则后面的文件的结果一直不会输出
+    pub fn print(&self, index: usize, buf: &Buffer) -> io::Result<()> {
+        self.print_with(index, buf, |buf| self.wtr.print(buf))
+    }
+
+    /// print 的实现，write 把一个 Buffer 写到标准输出，测试时用来收集输出
+    fn print_with<F>(&self, index: usize, buf: &Buffer, mut write: F) -> io::Result<()>
+    where
+        F: FnMut(&Buffer) -> io::Result<()>,
+    {
+        let mut order = self.order.lock().unwrap();
+        if !self.ordered {
+            return self.print_one(&mut order.printed, buf, &mut write);
+        }
+        let Order { ref mut next, ref mut pending, ref mut printed } = *order;
+        push_ordered(next, pending, index, buf, |buf| self.print_one(printed, buf, &mut write))
+    }
+
+    /// 输出一个 Buffer，之前输出过非空 Buffer 时先输出分隔符
+    fn print_one<F>(&self, printed: &mut bool, buf: &Buffer, write: &mut F) -> io::Result<()>
+    where
+        F: FnMut(&Buffer) -> io::Result<()>,
+    {
+        if buf.is_empty() {
+            return Ok(());
+        }
+        if let Some(ref sep) = self.separator {
+            if *printed {
+                let mut sep_buf = Buffer::no_color();
+                sep_buf.write_all(sep)?;
+                write(&sep_buf)?;
+            }
+        }
+        *printed = true;
+        write(buf)
+    }
+}
+
+/// 输出状态
+#[derive(Debug, Default)]
+struct Order {
+    /// 下一个应该输出的文件序号
+    next: usize,
+    /// 已经搜索完但还没轮到输出的文件的结果
+    pending: BTreeMap<usize, Buffer>,
+    /// 是否输出过非空的 Buffer
+    printed: bool,
+}
+
+/// 收到第 index 个文件的结果，轮到它时连同后面已经暂存的结果一起按顺序输出，否则先暂存
+fn push_ordered<F>(
+    next: &mut usize,
+    pending: &mut BTreeMap<usize, Buffer>,
+    index: usize,
+    buf: &Buffer,
+    mut print: F,
+) -> io::Result<()>
+where
+    F: FnMut(&Buffer) -> io::Result<()>,
+{
+    if index != *next {
+        pending.insert(index, buf.clone());
+        return Ok(());
+    }
+    print(buf)?;
+    *next += 1;
+    while let Some(buf) = pending.remove(next) {
+        print(&buf)?;
+        *next += 1;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer(s: &str) -> Buffer {
+        let mut buf = Buffer::no_color();
+        buf.write_all(s.as_bytes()).unwrap();
+        buf
+    }
+
+    #[test]
+    fn order() {
+        let (mut next, mut pending) = (0, BTreeMap::new());
+        let mut printed = vec![];
+        let mut print = |buf: &Buffer| {
+            printed.push(String::from_utf8(buf.as_slice().to_vec()).unwrap());
+            Ok(())
+        };
+        for (index, s) in [(2, "c"), (1, "b"), (4, "e"), (0, "a"), (3, "")] {
+            push_ordered(&mut next, &mut pending, index, &buffer(s), &mut print).unwrap();
+        }
+        assert_eq!(vec!["a", "b", "c", "", "e"], printed);
+        assert!(pending.is_empty());
+        assert_eq!(5, next);
+    }
+
+    /// 按顺序输出时分隔符只出现在相邻的两个非空 Buffer 之间
+    #[test]
+    fn print_ordered_with_separator() {
+        let mut wtr = BufferWriter::stdout(ColorChoice::Never);
+        wtr.separator(Some(b"--\n".to_vec())).ordered(true);
+        let mut out = vec![];
+        for (index, s) in [(2, "c\n"), (1, ""), (4, "e\n"), (0, "a\n"), (3, "")] {
+            wtr.print_with(index, &buffer(s), |buf| {
+                out.extend_from_slice(buf.as_slice());
+                Ok(())
+            })
+            .unwrap();
+        }
+        assert_eq!("a\n--\nc\n--\ne\n", String::from_utf8(out).unwrap());
+    }
+}
@@ -1,3 +1,4 @@
+use grep_matcher::PatternID;
 use termcolor::{Color, ColorSpec};
 
 /// 颜色定制类型，可以为各种不同数据设置不同的输出颜色
@@ -11,6 +12,8 @@ pub struct ColorSpecs {
     column: ColorSpec,
     /// 匹配行中匹配切片的颜色，默认是红色
     matched: ColorSpec,
+    /// 按模式序号区分的匹配切片颜色，为空时所有模式都使用 matched
+    patterns: Vec<ColorSpec>,
 }
 
 impl Default for ColorSpecs {
@@ -20,6 +23,7 @@ impl Default for ColorSpecs {
             line: config_color(Color::Green, false),
             column: ColorSpec::default(),
             matched: config_color(Color::Red, true),
+            patterns: vec![],
         }
     }
 }
@@ -60,5 +64,19 @@ impl ColorSpecs {
     pub fn matched(&self) -> &ColorSpec {
         &self.matched
     }
+
+    /// 为每个模式的匹配切片设置不同的颜色，模式比颜色多时循环使用
+    pub fn with_pattern_colors(mut self, specs: Vec<ColorSpec>) -> ColorSpecs {
+        self.patterns = specs;
+        self
+    }
+
+    /// 指定模式的匹配切片颜色，没有设置按模式区分的颜色时就是 matched()
+    pub fn matched_pattern(&self, pid: PatternID) -> &ColorSpec {
+        if self.patterns.is_empty() {
+            return &self.matched;
+        }
+        &self.patterns[pid.as_usize() % self.patterns.len()]
+    }
 }
 
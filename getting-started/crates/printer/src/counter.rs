@@ -1,6 +1,6 @@
 use std::io;
 use std::io::Write;
-use termcolor::{ColorSpec, WriteColor};
+use termcolor::{ColorSpec, HyperlinkSpec, WriteColor};
 
 /// 会记录成功输出字节数的 Writer (实际使用时是 WriteColor 类型, WriteColor: io::Write)
 #[derive(Clone, Debug)]
@@ -25,6 +25,11 @@ impl<W> CounterWriter<W> {
         self.count
     }
 
+    #[inline]
+    pub(crate) fn get_mut(&mut self) -> &mut W {
+        &mut self.wtr
+    }
+
     #[inline]
     pub(crate) fn total_count(&self) -> u64 {
         self.total_count + self.count
@@ -61,6 +66,16 @@ impl<W: WriteColor> WriteColor for CounterWriter<W> {
         self.wtr.set_color(spec)
     }
 
+    #[inline]
+    fn supports_hyperlinks(&self) -> bool {
+        self.wtr.supports_hyperlinks()
+    }
+
+    #[inline]
+    fn set_hyperlink(&mut self, link: &HyperlinkSpec) -> io::Result<()> {
+        self.wtr.set_hyperlink(link)
+    }
+
     /// 重置为原始的设置
     #[inline]
     fn reset(&mut self) -> io::Result<()> {
@@ -0,0 +1,311 @@
+//! 终端超链接（OSC 8）的格式模板
+//! 现代终端支持在输出中嵌入可点击的链接，这里把匹配行的文件路径包装成链接，点击后用编辑器或文件管理器打开
+
+use std::fmt;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::str::FromStr;
+use crate::util::DecimalFormatter;
+
+/// 内置的编辑器别名，可以直接作为 --hyperlink-format 的值
+const ALIASES: &[(&str, &str)] = &[
+    ("cursor", "cursor://file{path}:{line}:{column}"),
+    ("default", "file://{host}{path}"),
+    ("file", "file://{host}{path}"),
+    ("grep+", "grep+://{path}:{line}"),
+    ("kitty", "file://{host}{path}#{line}"),
+    ("macvim", "mvim://open?url=file://{path}&line={line}&column={column}"),
+    ("none", ""),
+    ("textmate", "txmt://open?url=file://{path}&line={line}&column={column}"),
+    ("vscode", "vscode://file{path}:{line}:{column}"),
+    ("vscode-insiders", "vscode-insiders://file{path}:{line}:{column}"),
+    ("vscodium", "vscodium://file{path}:{line}:{column}"),
+];
+
+/// 超链接格式，由字面量和变量（`{path}` `{line}` `{column}` `{host}`）组成，比如 `vscode://file{path}:{line}:{column}`
+/// 空格式表示不输出超链接
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HyperlinkFormat {
+    /// 模板拆分后的各个部分
+    parts: Vec<Part>,
+    /// 模板中是否用到了行号或列号，用到时每个匹配行的链接都不同
+    is_line_dependent: bool,
+    /// 替换 `{host}` 的主机名，没有设置时替换为空，即 `file:///path`
+    host: Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Part {
+    Text(Vec<u8>),
+    Path,
+    Line,
+    Column,
+    Host,
+}
+
+impl HyperlinkFormat {
+    /// 不输出超链接的空格式
+    pub fn empty() -> HyperlinkFormat {
+        HyperlinkFormat::default()
+    }
+
+    /// 是否是空格式
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// 模板中是否用到了行号或列号
+    pub fn is_line_dependent(&self) -> bool {
+        self.is_line_dependent
+    }
+
+    /// 设置 `{host}` 使用的主机名
+    pub fn with_host(mut self, host: Option<String>) -> HyperlinkFormat {
+        self.host = host;
+        self
+    }
+
+    /// 内置的别名及其对应的模板
+    pub fn aliases() -> &'static [(&'static str, &'static str)] {
+        ALIASES
+    }
+
+    /// 按模板生成链接写到 buf，行号和列号未知时使用 1
+    pub(crate) fn write_uri(
+        &self,
+        path: &HyperlinkPath,
+        line: Option<u64>,
+        column: Option<u64>,
+        buf: &mut Vec<u8>,
+    ) {
+        for part in self.parts.iter() {
+            match *part {
+                Part::Text(ref text) => buf.extend_from_slice(text),
+                Part::Path => buf.extend_from_slice(&path.0),
+                Part::Line => {
+                    buf.extend_from_slice(DecimalFormatter::new(line.unwrap_or(1)).as_bytes())
+                }
+                Part::Column => {
+                    buf.extend_from_slice(DecimalFormatter::new(column.unwrap_or(1)).as_bytes())
+                }
+                Part::Host => {
+                    if let Some(ref host) = self.host {
+                        buf.extend_from_slice(host.as_bytes());
+                    }
+                }
+            }
+        }
+    }
+
+    /// 追加字面量，和前一个字面量合并
+    fn push_text(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(Part::Text(prev)) = self.parts.last_mut() {
+            prev.extend_from_slice(text.as_bytes());
+        } else {
+            self.parts.push(Part::Text(text.as_bytes().to_vec()));
+        }
+    }
+}
+
+impl FromStr for HyperlinkFormat {
+    type Err = HyperlinkFormatError;
+
+    /// 解析模板或者别名，模板必须以 URI 协议（比如 `file:`）开头且包含 `{path}`
+    fn from_str(s: &str) -> Result<HyperlinkFormat, HyperlinkFormatError> {
+        let template = match ALIASES.iter().find(|&&(name, _)| name == s) {
+            Some(&(_, template)) => template,
+            None => s,
+        };
+        if template.is_empty() {
+            return Ok(HyperlinkFormat::empty());
+        }
+        let has_scheme = template.find(':').is_some_and(|i| {
+            let scheme = &template.as_bytes()[..i];
+            scheme.first().is_some_and(|b| b.is_ascii_alphabetic())
+                && scheme.iter().all(|&b| b.is_ascii_alphanumeric() || b"+-.".contains(&b))
+        });
+        if !has_scheme {
+            return Err(HyperlinkFormatError::new(HyperlinkFormatErrorKind::NoScheme));
+        }
+
+        let mut format = HyperlinkFormat::empty();
+        let mut rest = template;
+        while !rest.is_empty() {
+            let Some(start) = rest.find(['{', '}']) else {
+                format.push_text(rest);
+                break;
+            };
+            if rest.as_bytes()[start] == b'}' {
+                return Err(HyperlinkFormatError::new(HyperlinkFormatErrorKind::UnopenedVariable));
+            }
+            format.push_text(&rest[..start]);
+            let Some(len) = rest[start..].find('}') else {
+                return Err(HyperlinkFormatError::new(HyperlinkFormatErrorKind::UnclosedVariable));
+            };
+            let name = &rest[start + 1..start + len];
+            let part = match name {
+                "path" => Part::Path,
+                "line" => Part::Line,
+                "column" => Part::Column,
+                "host" => Part::Host,
+                _ => {
+                    let kind = HyperlinkFormatErrorKind::InvalidVariable(name.to_string());
+                    return Err(HyperlinkFormatError::new(kind));
+                }
+            };
+            format.is_line_dependent |= matches!(part, Part::Line | Part::Column);
+            format.parts.push(part);
+            rest = &rest[start + len + 1..];
+        }
+        if !format.parts.contains(&Part::Path) {
+            return Err(HyperlinkFormatError::new(HyperlinkFormatErrorKind::NoPathVariable));
+        }
+        Ok(format)
+    }
+}
+
+/// 解析超链接格式的错误
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HyperlinkFormatError {
+    kind: HyperlinkFormatErrorKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum HyperlinkFormatErrorKind {
+    /// 模板没有以 URI 协议开头
+    NoScheme,
+    /// 模板中没有 `{path}`
+    NoPathVariable,
+    /// 未知的变量名
+    InvalidVariable(String),
+    /// `{` 没有对应的 `}`
+    UnclosedVariable,
+    /// `}` 没有对应的 `{`
+    UnopenedVariable,
+}
+
+impl HyperlinkFormatError {
+    fn new(kind: HyperlinkFormatErrorKind) -> HyperlinkFormatError {
+        HyperlinkFormatError { kind }
+    }
+}
+
+impl std::error::Error for HyperlinkFormatError {}
+
+impl fmt::Display for HyperlinkFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            HyperlinkFormatErrorKind::NoScheme => {
+                write!(f, "hyperlink format must start with a URI scheme like 'file:'")
+            }
+            HyperlinkFormatErrorKind::NoPathVariable => {
+                write!(f, "hyperlink format must contain the {{path}} variable")
+            }
+            HyperlinkFormatErrorKind::InvalidVariable(ref name) => {
+                let names: Vec<&str> = ALIASES.iter().map(|&(name, _)| name).collect();
+                write!(
+                    f,
+                    "invalid hyperlink format variable '{{{name}}}', expected one of \
+                     {{path}}, {{line}}, {{column}} or {{host}} (or an alias: {})",
+                    names.join(", "),
+                )
+            }
+            HyperlinkFormatErrorKind::UnclosedVariable => {
+                write!(f, "unclosed variable in hyperlink format, missing '}}'")
+            }
+            HyperlinkFormatErrorKind::UnopenedVariable => {
+                write!(f, "unopened variable in hyperlink format, missing '{{'")
+            }
+        }
+    }
+}
+
+/// 已经转义好的绝对路径，以 `/` 开头，可以直接放进 URI
+#[derive(Clone, Debug)]
+pub(crate) struct HyperlinkPath(Vec<u8>);
+
+impl HyperlinkPath {
+    /// 把路径转成绝对路径并做百分号编码，路径不存在（无法规范化）时返回 None
+    pub(crate) fn from_path(path: &Path) -> Option<HyperlinkPath> {
+        let path = path.canonicalize().ok()?;
+        Some(HyperlinkPath::encode(path.as_os_str().as_bytes()))
+    }
+
+    /// 除了 URI 中不需要转义的字符和路径分隔符，其他字节（包括非 ASCII 字节）都编码成 `%XX`
+    pub(crate) fn encode(path: &[u8]) -> HyperlinkPath {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+        let mut buf = Vec::with_capacity(path.len());
+        for &b in path {
+            match b {
+                b'0'..=b'9' | b'A'..=b'Z' | b'a'..=b'z' | b'/' | b'-' | b'.' | b'_' | b'~' => {
+                    buf.push(b)
+                }
+                _ => {
+                    buf.push(b'%');
+                    buf.push(HEX[usize::from(b >> 4)]);
+                    buf.push(HEX[usize::from(b & 0xF)]);
+                }
+            }
+        }
+        HyperlinkPath(buf)
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uri(format: &str, path: &[u8], line: Option<u64>, column: Option<u64>) -> String {
+        let format: HyperlinkFormat = format.parse().unwrap();
+        let mut buf = vec![];
+        format.write_uri(&HyperlinkPath::encode(path), line, column, &mut buf);
+        String::from_utf8(buf).unwrap()
+    }
+
+    #[test]
+    fn aliases_and_templates() {
+        assert_eq!(
+            "vscode://file/home/a/src/main.rs:3:7",
+            uri("vscode", b"/home/a/src/main.rs", Some(3), Some(7)),
+        );
+        assert_eq!("file:///tmp/x.txt", uri("default", b"/tmp/x.txt", None, None));
+        assert_eq!("kitty:/a#1", uri("kitty:{path}#{line}", b"/a", None, None));
+        let format = "file://{host}{path}".parse::<HyperlinkFormat>().unwrap()
+            .with_host(Some("box".to_string()));
+        let mut buf = vec![];
+        format.write_uri(&HyperlinkPath::encode(b"/a"), None, None, &mut buf);
+        assert_eq!(b"file://box/a", buf.as_slice());
+
+        assert!("none".parse::<HyperlinkFormat>().unwrap().is_empty());
+        assert!("vscode".parse::<HyperlinkFormat>().unwrap().is_line_dependent());
+        assert!(!"file".parse::<HyperlinkFormat>().unwrap().is_line_dependent());
+    }
+
+    #[test]
+    fn path_escaping() {
+        assert_eq!(
+            "file:///a%20b/%23c%25/%E4%B8%AD.rs",
+            uri("file://{path}", "/a b/#c%/中.rs".as_bytes(), None, None),
+        );
+    }
+
+    #[test]
+    fn invalid_formats() {
+        let err = |s: &str| s.parse::<HyperlinkFormat>().unwrap_err().kind;
+        assert_eq!(HyperlinkFormatErrorKind::NoScheme, err("{path}"));
+        assert_eq!(HyperlinkFormatErrorKind::NoPathVariable, err("file://{host}"));
+        assert_eq!(HyperlinkFormatErrorKind::UnclosedVariable, err("file://{path"));
+        assert_eq!(HyperlinkFormatErrorKind::UnopenedVariable, err("file://path}"));
+        assert_eq!(
+            HyperlinkFormatErrorKind::InvalidVariable("file".to_string()),
+            err("x://{file}"),
+        );
+    }
+}
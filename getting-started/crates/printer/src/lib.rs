@@ -1,9 +1,23 @@
 pub use standard::{
-    Standard, StandardBuilder
+    Standard, StandardBuilder, StandardSink
+};
+pub use buffer::{Buffer, BufferWriter};
+pub use color::ColorSpecs;
+pub use hyperlink::{HyperlinkFormat, HyperlinkFormatError};
+pub use lint::{Lint, LintBuilder, LintSink};
+pub use sarif::{Sarif, SarifBuilder, SarifLevel, SarifLevelError, SarifRule, SarifSink};
+pub use stats::Stats;
+pub use template::{
+    Template, TemplateBuilder, TemplateFormat, TemplateFormatError, TemplateSink
 };
 
+mod buffer;
 mod counter;
 mod standard;
 mod color;
+mod hyperlink;
+mod lint;
+mod sarif;
 mod util;
 mod stats;
+mod template;
@@ -0,0 +1,427 @@
+//! 按规则分组输出的 Printer，用于 --rules 指定的规则文件
+//! 每个模式是一条规则（规则的序号和模式序号 PatternID 一一对应），所有文件搜索完后调用 Lint::end() 按规则分组输出结果，
+//! 最后输出各个严重程度的结果数，所以只能单线程搜索
+
+use std::io::{self, Write};
+use std::path::{Component, Path};
+use std::time::Instant;
+use bstr::ByteVec;
+use termcolor::{Color, ColorSpec, WriteColor};
+use grep_matcher::{Match, Matcher, PatternID};
+use grep_searcher::{Searcher, Sink, SinkFinish, SinkMatch};
+use crate::counter::CounterWriter;
+use crate::sarif::{SarifLevel, SarifRule};
+use crate::stats::Stats;
+use crate::util::{find_iter_at_in_context, find_iter_per_pattern, trim_line_terminator};
+
+#[derive(Clone, Debug, Default)]
+struct Config {
+    rules: Vec<SarifRule>,
+    /// 结果中是否输出文件路径，默认 true
+    path: bool,
+    /// 是否统计搜索数据
+    stats: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct LintBuilder {
+    config: Config,
+}
+
+impl Default for LintBuilder {
+    fn default() -> LintBuilder {
+        LintBuilder::new()
+    }
+}
+
+impl LintBuilder {
+    pub fn new() -> LintBuilder {
+        LintBuilder { config: Config { path: true, ..Config::default() } }
+    }
+
+    pub fn build<W: WriteColor>(&self, wtr: W) -> Lint<W> {
+        Lint {
+            results: vec![vec![]; self.config.rules.len()],
+            config: self.config.clone(),
+            wtr: CounterWriter::new(wtr),
+            matches: vec![],
+        }
+    }
+
+    /// 设置规则，第 i 条规则对应 PatternID i，没有对应规则的匹配不输出
+    pub fn rules(&mut self, rules: Vec<SarifRule>) -> &mut LintBuilder {
+        self.config.rules = rules;
+        self
+    }
+
+    /// 结果中是否输出文件路径，默认 true
+    pub fn path(&mut self, yes: bool) -> &mut LintBuilder {
+        self.config.path = yes;
+        self
+    }
+
+    /// 是否统计搜索数据，默认不统计
+    pub fn stats(&mut self, yes: bool) -> &mut LintBuilder {
+        self.config.stats = yes;
+        self
+    }
+}
+
+/// 一个匹配结果，输出前先缓存起来，所有文件搜索完后按规则分组输出
+#[derive(Clone, Debug)]
+struct LintResult {
+    path: Option<Vec<u8>>,
+    line_number: Option<u64>,
+    /// 匹配在行中的列号（按字节计算，从 1 开始）
+    column: u64,
+    /// 不含行终止符、去掉了行首空白的匹配行
+    line: Vec<u8>,
+}
+
+/// 按规则分组输出的 Printer
+#[derive(Clone, Debug)]
+pub struct Lint<W> {
+    config: Config,
+    wtr: CounterWriter<W>,
+    /// 每条规则的结果，和 config.rules 一一对应
+    results: Vec<Vec<LintResult>>,
+    /// 当前匹配行中所有匹配的范围（相对于行首）及其模式序号
+    matches: Vec<(Match, PatternID)>,
+}
+
+impl<W> Lint<W> {
+    /// 输出目的地的可变引用
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut()
+    }
+
+    /// 目前为止找到的结果中最高的严重程度，没有结果时返回 None
+    pub fn max_level(&self) -> Option<SarifLevel> {
+        self.config.rules.iter().zip(self.results.iter())
+            .filter(|(_, results)| !results.is_empty())
+            .map(|(rule, _)| rule.get_level())
+            .max()
+    }
+}
+
+impl<W: WriteColor> Lint<W> {
+    /// 结果中没有文件路径，比如搜索标准输入
+    pub fn sink<'p, 's, M: Matcher>(&'s mut self, matcher: M) -> LintSink<'p, 's, M, W> {
+        let stats = if self.config.stats { Some(Stats::new()) } else { None };
+        LintSink {
+            matcher,
+            lint: self,
+            path: None,
+            pattern_filter: None,
+            pattern_matchers: None,
+            start_time: Instant::now(),
+            match_count: 0,
+            stats,
+        }
+    }
+
+    pub fn sink_with_path<'p, 's, M: Matcher>(
+        &'s mut self,
+        matcher: M,
+        path: &'p Path,
+    ) -> LintSink<'p, 's, M, W> {
+        if !self.config.path {
+            return self.sink(matcher);
+        }
+        let mut sink = self.sink(matcher);
+        sink.path = Some(path);
+        sink
+    }
+
+    /// 按规则的顺序输出所有结果，没有结果的规则不输出，最后输出各个严重程度的结果数，所有搜索结束后调用一次
+    pub fn end(&mut self) -> io::Result<()> {
+        let mut counts = [0u64; 4];
+        let mut first = true;
+        for (rule, results) in self.config.rules.iter().zip(self.results.iter()) {
+            if results.is_empty() {
+                continue;
+            }
+            counts[rule.get_level() as usize] += results.len() as u64;
+            if !first {
+                self.wtr.write_all(b"\n")?;
+            }
+            first = false;
+            write_level(&mut self.wtr, rule.get_level())?;
+            self.wtr.set_color(ColorSpec::new().set_bold(true))?;
+            write!(self.wtr, "[{}]: {}", rule.id(), rule.message_text())?;
+            self.wtr.reset()?;
+            writeln!(self.wtr, " ({})", plural(results.len() as u64, "match", "matches"))?;
+            for result in results {
+                if let Some(ref path) = result.path {
+                    self.wtr.set_color(ColorSpec::new().set_fg(Some(Color::Magenta)))?;
+                    self.wtr.write_all(path)?;
+                    self.wtr.reset()?;
+                    self.wtr.write_all(b":")?;
+                }
+                if let Some(line_number) = result.line_number {
+                    self.wtr.set_color(ColorSpec::new().set_fg(Some(Color::Green)))?;
+                    write!(self.wtr, "{line_number}")?;
+                    self.wtr.reset()?;
+                    self.wtr.write_all(b":")?;
+                }
+                write!(self.wtr, "{}:", result.column)?;
+                self.wtr.write_all(&result.line)?;
+                self.wtr.write_all(b"\n")?;
+            }
+        }
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return Ok(());
+        }
+        // 比如：3 problems (1 error, 2 warnings)
+        let levels = [
+            (SarifLevel::Error, "error", "errors"),
+            (SarifLevel::Warning, "warning", "warnings"),
+            (SarifLevel::Note, "note", "notes"),
+            (SarifLevel::None, "other", "others"),
+        ];
+        let details: Vec<String> = levels.iter()
+            .filter(|(level, _, _)| counts[*level as usize] > 0)
+            .map(|&(level, one, many)| plural(counts[level as usize], one, many))
+            .collect();
+        writeln!(self.wtr, "\n{} ({})", plural(total, "problem", "problems"), details.join(", "))
+    }
+}
+
+/// 按严重程度着色输出 level 名称
+fn write_level<W: WriteColor>(wtr: &mut W, level: SarifLevel) -> io::Result<()> {
+    let color = match level {
+        SarifLevel::Error => Color::Red,
+        SarifLevel::Warning => Color::Yellow,
+        SarifLevel::Note | SarifLevel::None => Color::Cyan,
+    };
+    wtr.set_color(ColorSpec::new().set_fg(Some(color)).set_bold(true))?;
+    wtr.write_all(level.as_str().as_bytes())?;
+    wtr.reset()
+}
+
+/// 比如 `1 match` `2 matches`
+fn plural(count: u64, one: &str, many: &str) -> String {
+    format!("{count} {}", if count == 1 { one } else { many })
+}
+
+#[derive(Debug)]
+pub struct LintSink<'p, 's, M: Matcher, W> {
+    matcher: M,
+    lint: &'s mut Lint<W>,
+    path: Option<&'p Path>,
+    /// 本次搜索启用的规则，为 None 时启用所有规则
+    pattern_filter: Option<&'p [bool]>,
+    /// 每条规则单独的匹配器，为 None 时用 matcher 查找所有规则的匹配项
+    pattern_matchers: Option<&'p [M]>,
+    /// Sink 创建的时间，用于统计搜索耗时
+    start_time: Instant,
+    /// 匹配的行计数
+    match_count: u64,
+    /// 统计记录，通过 LintBuilder::stats() 开启
+    stats: Option<Stats>,
+}
+
+impl<'p, M: Matcher, W: WriteColor> LintSink<'p, '_, M, W> {
+    /// 只保留 filter[PatternID] 为 true 的规则的匹配，比如规则只适用于部分文件
+    pub fn pattern_filter(mut self, filter: &'p [bool]) -> Self {
+        self.pattern_filter = Some(filter);
+        self
+    }
+
+    /// 设置每条规则单独的匹配器，matchers[i] 只包含第 i 条规则的模式
+    /// matcher 只用来找出匹配行，匹配行中的每条规则分别查找，不会被同一位置序号更小或者未启用的规则遮挡
+    pub fn pattern_matchers(mut self, matchers: &'p [M]) -> Self {
+        self.pattern_matchers = Some(matchers);
+        self
+    }
+
+    /// 是否有匹配的行
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    /// 本次搜索的统计数据，只有开启了统计才会返回
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+}
+
+impl<M: Matcher, W: WriteColor> Sink for LintSink<'_, '_, M, W> {
+    type Error = io::Error;
+
+    /// 匹配行中的每个匹配作为对应规则的一个结果缓存起来
+    fn matched(
+        &mut self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+    ) -> Result<bool, Self::Error> {
+        let range = mat.bytes_range_in_buffer();
+        let matches = &mut self.lint.matches;
+        let filter = self.pattern_filter;
+        let rule_count = self.lint.results.len();
+        matches.clear();
+        if let Some(matchers) = self.pattern_matchers {
+            find_iter_per_pattern(searcher, matchers, filter, mat.buffer(), range.clone(), |m, pid| {
+                if pid.as_usize() < rule_count {
+                    matches.push((Match::new(m.start() - range.start, m.end() - range.start), pid));
+                }
+            })?;
+            matches.sort_by_key(|&(m, pid)| (m.start(), pid));
+        } else {
+            find_iter_at_in_context(searcher, &self.matcher, mat.buffer(), range.clone(), |m, pid| {
+                let enabled = filter.is_none_or(|filter| filter.get(pid.as_usize()) == Some(&true));
+                if enabled && pid.as_usize() < rule_count {
+                    matches.push((Match::new(m.start() - range.start, m.end() - range.start), pid));
+                }
+                true
+            })?;
+        }
+        // 匹配行中的匹配都属于未启用的规则
+        if matches.is_empty() {
+            return Ok(true);
+        }
+        self.match_count += 1;
+        if let Some(ref mut stats) = self.stats {
+            stats.add_matches(matches.len() as u64);
+            stats.add_matched_lines(1);
+        }
+
+        let bytes = mat.bytes();
+        let mut line = Match::new(0, bytes.len());
+        trim_line_terminator(searcher, bytes, &mut line);
+        let indent = bytes[line].iter().take_while(|b| b.is_ascii_whitespace()).count();
+        let path = self.path.map(|path| {
+            let path = path.strip_prefix(Component::CurDir).unwrap_or(path);
+            Vec::from_path_lossy(path).into_owned()
+        });
+        for &(m, pid) in self.lint.matches.iter() {
+            self.lint.results[pid.as_usize()].push(LintResult {
+                path: path.clone(),
+                line_number: mat.line_number(),
+                column: m.start() as u64 + 1,
+                line: bytes[line.with_start(line.start() + indent)].to_vec(),
+            });
+        }
+        Ok(true)
+    }
+
+    /// 搜索开始，重置本次搜索的匹配计数和计时
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, Self::Error> {
+        self.lint.wtr.reset_count();
+        self.start_time = Instant::now();
+        self.match_count = 0;
+        Ok(true)
+    }
+
+    /// 搜索结束，汇总本次搜索的统计数据，结果在 end() 时才输出，所以这里输出的字节数总是 0
+    fn finish(
+        &mut self,
+        _searcher: &Searcher,
+        finish: &SinkFinish,
+    ) -> Result<(), Self::Error> {
+        if let Some(ref mut stats) = self.stats {
+            stats.add_elapsed(self.start_time.elapsed());
+            stats.add_searches(1);
+            if self.match_count > 0 {
+                stats.add_searches_with_match(1);
+            }
+            stats.add_bytes_searched(finish.byte_count());
+            stats.add_bytes_printed(self.lint.wtr.count());
+        }
+        self.lint.wtr.reset_count();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_regex::RegexMatcherBuilder;
+    use grep_searcher::SearcherBuilder;
+    use termcolor::NoColor;
+    use super::*;
+
+    fn rules() -> Vec<SarifRule> {
+        vec![
+            SarifRule::new("no-todo".to_string(), "TODO".to_string())
+                .level(SarifLevel::Note)
+                .message(Some("TODO left in code".to_string())),
+            SarifRule::new("no-unwrap".to_string(), r"\.unwrap\(\)".to_string())
+                .level(SarifLevel::Error),
+        ]
+    }
+
+    fn search<W: WriteColor>(lint: &mut Lint<W>, path: &str, filter: &[bool], haystack: &str) {
+        let patterns = ["TODO", r"\.unwrap\(\)"];
+        let matcher = RegexMatcherBuilder::new().build_many(&patterns).unwrap();
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        let mut sink = lint.sink_with_path(&matcher, Path::new(path)).pattern_filter(filter);
+        searcher.search_slice(&matcher, haystack.as_bytes(), &mut sink).unwrap();
+    }
+
+    #[test]
+    fn grouped_by_rule() {
+        let mut lint = LintBuilder::new().rules(rules()).build(NoColor::new(vec![]));
+        search(&mut lint, "./src/main.rs", &[true, true], "fn main() {\n    x.unwrap(); // TODO\n}\n");
+        // 第二个文件只启用了 no-todo
+        search(&mut lint, "README.md", &[true, false], "TODO: y.unwrap()\nz.unwrap()\n");
+        assert_eq!(Some(SarifLevel::Error), lint.max_level());
+        lint.end().unwrap();
+        let got = String::from_utf8(lint.get_mut().get_ref().clone()).unwrap();
+        let expected = "\
+note[no-todo]: TODO left in code (2 matches)
+src/main.rs:2:20:x.unwrap(); // TODO
+README.md:1:1:TODO: y.unwrap()
+
+error[no-unwrap]: Match for pattern '\\.unwrap\\(\\)' (1 match)
+src/main.rs:2:6:x.unwrap(); // TODO
+
+3 problems (1 error, 2 notes)
+";
+        assert_eq!(expected, got);
+    }
+
+    /// 设置了每条规则的匹配器时，同一位置的多条规则都会报告，不适用的规则也不会遮挡其他规则
+    #[test]
+    fn pattern_matchers() {
+        let patterns = ["unwrap", r"unwrap\(\)"];
+        let rules = vec![
+            SarifRule::new("unwrap".to_string(), patterns[0].to_string()).level(SarifLevel::Note),
+            SarifRule::new("unwrap-call".to_string(), patterns[1].to_string()).level(SarifLevel::Error),
+        ];
+        let matcher = RegexMatcherBuilder::new().build_many(&patterns).unwrap();
+        let matchers: Vec<_> = patterns.iter().map(|p| RegexMatcherBuilder::new().build(p).unwrap()).collect();
+        let matchers: Vec<_> = matchers.iter().collect();
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        let mut lint = LintBuilder::new().rules(rules).build(NoColor::new(vec![]));
+        for (path, filter) in [("a.rs", [true, true]), ("b.rs", [false, true])] {
+            let mut sink = lint.sink_with_path(&matcher, Path::new(path))
+                .pattern_filter(&filter)
+                .pattern_matchers(&matchers);
+            searcher.search_slice(&matcher, b"x.unwrap();\n", &mut sink).unwrap();
+        }
+        assert_eq!(Some(SarifLevel::Error), lint.max_level());
+        lint.end().unwrap();
+        let got = String::from_utf8(lint.get_mut().get_ref().clone()).unwrap();
+        let expected = "\
+note[unwrap]: Match for pattern 'unwrap' (1 match)
+a.rs:1:3:x.unwrap();
+
+error[unwrap-call]: Match for pattern 'unwrap\\(\\)' (2 matches)
+a.rs:1:3:x.unwrap();
+b.rs:1:3:x.unwrap();
+
+3 problems (2 errors, 1 note)
+";
+        assert_eq!(expected, got);
+    }
+
+    #[test]
+    fn no_results() {
+        let mut lint = LintBuilder::new().rules(rules()).build(NoColor::new(vec![]));
+        search(&mut lint, "a.txt", &[false, true], "TODO\n");
+        assert_eq!(None, lint.max_level());
+        lint.end().unwrap();
+        assert!(lint.get_mut().get_ref().is_empty());
+    }
+}
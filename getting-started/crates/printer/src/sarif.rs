@@ -0,0 +1,630 @@
+//! SARIF 2.1.0 格式的 Printer，用于把搜索结果接入 GitHub/GitLab 等平台的代码扫描面板
+//! 每个模式是一条规则（rule），每个匹配是一个结果（result），结果的位置包括文件路径和匹配所在的行列范围
+//! 整个搜索只输出一个 JSON 文档，所有文件搜索完后需要调用 Sarif::end() 结束文档，所以只能单线程搜索
+
+use std::fmt;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Component, Path};
+use std::str::FromStr;
+use std::time::Instant;
+use termcolor::WriteColor;
+use grep_matcher::{Match, Matcher, PatternID};
+use grep_searcher::{Searcher, Sink, SinkError, SinkFinish, SinkMatch};
+use crate::counter::CounterWriter;
+use crate::hyperlink::HyperlinkPath;
+use crate::stats::Stats;
+use crate::util::{find_iter_at_in_context, find_iter_per_pattern, trim_line_terminator};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// 结果的严重程度，对应 SARIF 的 level，按严重程度从低到高排序
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SarifLevel {
+    None,
+    Note,
+    #[default]
+    Warning,
+    Error,
+}
+
+impl SarifLevel {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            SarifLevel::None => "none",
+            SarifLevel::Note => "note",
+            SarifLevel::Warning => "warning",
+            SarifLevel::Error => "error",
+        }
+    }
+}
+
+impl FromStr for SarifLevel {
+    type Err = SarifLevelError;
+
+    fn from_str(s: &str) -> Result<SarifLevel, SarifLevelError> {
+        match s {
+            "none" => Ok(SarifLevel::None),
+            "note" => Ok(SarifLevel::Note),
+            "warning" => Ok(SarifLevel::Warning),
+            "error" => Ok(SarifLevel::Error),
+            _ => Err(SarifLevelError { given: s.to_string() }),
+        }
+    }
+}
+
+/// 解析严重程度的错误
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SarifLevelError {
+    given: String,
+}
+
+impl std::error::Error for SarifLevelError {}
+
+impl fmt::Display for SarifLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid severity '{}', expected one of error, warning, note or none", self.given)
+    }
+}
+
+/// 一条规则，规则的序号和模式序号（PatternID）一一对应
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SarifRule {
+    id: String,
+    pattern: String,
+    level: SarifLevel,
+    message: Option<String>,
+}
+
+impl SarifRule {
+    /// 规则 id 和模式，严重程度默认是 warning
+    pub fn new(id: String, pattern: String) -> SarifRule {
+        SarifRule { id, pattern, level: SarifLevel::default(), message: None }
+    }
+
+    /// 设置严重程度
+    pub fn level(mut self, level: SarifLevel) -> SarifRule {
+        self.level = level;
+        self
+    }
+
+    /// 设置结果的说明，没有设置时使用 `Match for pattern '...'`
+    pub fn message(mut self, message: Option<String>) -> SarifRule {
+        self.message = message;
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn get_level(&self) -> SarifLevel {
+        self.level
+    }
+
+    pub(crate) fn message_text(&self) -> String {
+        match self.message {
+            Some(ref message) => message.clone(),
+            None => format!("Match for pattern '{}'", self.pattern),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Config {
+    /// 输出在 tool.driver 中的工具名称和版本
+    tool_name: String,
+    tool_version: Option<String>,
+    rules: Vec<SarifRule>,
+    /// 是否统计搜索数据
+    stats: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct SarifBuilder {
+    config: Config,
+}
+
+impl Default for SarifBuilder {
+    fn default() -> SarifBuilder {
+        SarifBuilder::new()
+    }
+}
+
+impl SarifBuilder {
+    pub fn new() -> SarifBuilder {
+        SarifBuilder {
+            config: Config {
+                tool_name: env!("CARGO_PKG_NAME").to_string(),
+                tool_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+                rules: vec![],
+                stats: false,
+            },
+        }
+    }
+
+    pub fn build<W: WriteColor>(&self, wtr: W) -> Sarif<W> {
+        Sarif {
+            config: self.config.clone(),
+            wtr: CounterWriter::new(wtr),
+            started: false,
+            result_count: 0,
+            matches: vec![],
+            max_level: None,
+        }
+    }
+
+    /// 设置输出在 tool.driver 中的工具名称和版本，默认是 grep-printer 的包名和版本
+    pub fn tool(&mut self, name: String, version: Option<String>) -> &mut SarifBuilder {
+        self.config.tool_name = name;
+        self.config.tool_version = version;
+        self
+    }
+
+    /// 设置规则，第 i 条规则对应 PatternID i，没有对应规则的匹配不输出 ruleId 和 level
+    pub fn rules(&mut self, rules: Vec<SarifRule>) -> &mut SarifBuilder {
+        self.config.rules = rules;
+        self
+    }
+
+    /// 是否统计搜索数据，默认不统计
+    pub fn stats(&mut self, yes: bool) -> &mut SarifBuilder {
+        self.config.stats = yes;
+        self
+    }
+}
+
+/// SARIF 格式的 Printer，第一个结果输出前（或者 end() 时）先输出文档头和规则
+#[derive(Clone, Debug)]
+pub struct Sarif<W> {
+    config: Config,
+    wtr: CounterWriter<W>,
+    /// 是否已经输出了文档头
+    started: bool,
+    /// 已经输出的结果数，用于在结果之间输出逗号
+    result_count: u64,
+    /// 当前匹配行中所有匹配的范围（相对于行首）及其模式序号
+    matches: Vec<(Match, PatternID)>,
+    /// 已经输出的结果中最高的严重程度
+    max_level: Option<SarifLevel>,
+}
+
+impl<W> Sarif<W> {
+    /// 输出目的地的可变引用
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut()
+    }
+
+    /// 已经输出的有对应规则的结果中最高的严重程度，没有这样的结果时返回 None
+    pub fn max_level(&self) -> Option<SarifLevel> {
+        self.max_level
+    }
+}
+
+impl<W: WriteColor> Sarif<W> {
+    /// 结果的位置使用 `stdin` 作为路径，比如搜索标准输入
+    pub fn sink<'p, 's, M: Matcher>(&'s mut self, matcher: M) -> SarifSink<'p, 's, M, W> {
+        let stats = if self.config.stats { Some(Stats::new()) } else { None };
+        SarifSink {
+            matcher,
+            sarif: self,
+            path: None,
+            pattern_filter: None,
+            pattern_matchers: None,
+            start_time: Instant::now(),
+            match_count: 0,
+            stats,
+        }
+    }
+
+    pub fn sink_with_path<'p, 's, M: Matcher>(
+        &'s mut self,
+        matcher: M,
+        path: &'p Path,
+    ) -> SarifSink<'p, 's, M, W> {
+        let mut sink = self.sink(matcher);
+        sink.path = Some(path);
+        sink
+    }
+
+    /// 结束 SARIF 文档，所有搜索结束后调用一次，没有任何结果时也会输出完整的文档
+    pub fn end(&mut self) -> io::Result<()> {
+        self.write_header()?;
+        self.wtr.write_all(b"\n]}]}\n")
+    }
+
+    /// 输出文档头、工具信息和所有规则，最后是结果数组的开头
+    fn write_header(&mut self) -> io::Result<()> {
+        if self.started {
+            return Ok(());
+        }
+        self.started = true;
+        let mut buf = vec![];
+        buf.extend_from_slice(b"{\"$schema\":");
+        write_json_string(&mut buf, SCHEMA.as_bytes());
+        buf.extend_from_slice(b",\"version\":\"2.1.0\",\"runs\":[{\"tool\":{\"driver\":{\"name\":");
+        write_json_string(&mut buf, self.config.tool_name.as_bytes());
+        if let Some(ref version) = self.config.tool_version {
+            buf.extend_from_slice(b",\"version\":");
+            write_json_string(&mut buf, version.as_bytes());
+        }
+        buf.extend_from_slice(b",\"rules\":[");
+        for (i, rule) in self.config.rules.iter().enumerate() {
+            if i > 0 {
+                buf.push(b',');
+            }
+            buf.extend_from_slice(b"\n{\"id\":");
+            write_json_string(&mut buf, rule.id.as_bytes());
+            buf.extend_from_slice(b",\"shortDescription\":{\"text\":");
+            write_json_string(&mut buf, rule.message_text().as_bytes());
+            buf.extend_from_slice(b"},\"defaultConfiguration\":{\"level\":");
+            write_json_string(&mut buf, rule.level.as_str().as_bytes());
+            buf.extend_from_slice(b"},\"properties\":{\"pattern\":");
+            write_json_string(&mut buf, rule.pattern.as_bytes());
+            buf.extend_from_slice(b"}}");
+        }
+        // 列号按 Unicode 码点计算，SARIF 默认按 UTF-16 码元计算
+        buf.extend_from_slice(b"]}},\"columnKind\":\"unicodeCodePoints\",\"results\":[");
+        self.wtr.write_all(&buf)
+    }
+
+    /// 输出一个结果，line 是不含行终止符的行内容，m 是匹配在行中的范围
+    fn write_result(
+        &mut self,
+        uri: &[u8],
+        line_number: Option<u64>,
+        line_offset: u64,
+        line: &[u8],
+        m: Match,
+        pid: PatternID,
+    ) -> io::Result<()> {
+        self.write_header()?;
+        let mut buf = vec![];
+        if self.result_count > 0 {
+            buf.push(b',');
+        }
+        self.result_count += 1;
+        buf.extend_from_slice(b"\n{");
+        let rule = self.config.rules.get(pid.as_usize());
+        if let Some(rule) = rule {
+            self.max_level = self.max_level.max(Some(rule.level));
+            buf.extend_from_slice(b"\"ruleId\":");
+            write_json_string(&mut buf, rule.id.as_bytes());
+            write!(buf, ",\"ruleIndex\":{},\"level\":", pid.as_usize())?;
+            write_json_string(&mut buf, rule.level.as_str().as_bytes());
+            buf.push(b',');
+        }
+        buf.extend_from_slice(b"\"message\":{\"text\":");
+        let message = rule.map_or_else(|| "Match".to_string(), |rule| rule.message_text());
+        write_json_string(&mut buf, message.as_bytes());
+        buf.extend_from_slice(b"},\"locations\":[{\"physicalLocation\":{\"artifactLocation\":{\"uri\":");
+        write_json_string(&mut buf, uri);
+        buf.extend_from_slice(b"},\"region\":{");
+        if let Some(line_number) = line_number {
+            let start_column = char_count(&line[..m.start()]) + 1;
+            let end_column = start_column + char_count(&line[m]);
+            write!(
+                buf,
+                "\"startLine\":{line_number},\"startColumn\":{start_column},\
+                 \"endLine\":{line_number},\"endColumn\":{end_column},",
+            )?;
+        }
+        write!(
+            buf,
+            "\"byteOffset\":{},\"byteLength\":{},\"snippet\":{{\"text\":",
+            line_offset + m.start() as u64,
+            m.len(),
+        )?;
+        write_json_string(&mut buf, line);
+        buf.extend_from_slice(b"}}}}]}");
+        self.wtr.write_all(&buf)
+    }
+}
+
+#[derive(Debug)]
+pub struct SarifSink<'p, 's, M: Matcher, W> {
+    matcher: M,
+    sarif: &'s mut Sarif<W>,
+    path: Option<&'p Path>,
+    /// 本次搜索启用的规则，为 None 时启用所有规则
+    pattern_filter: Option<&'p [bool]>,
+    /// 每条规则单独的匹配器，为 None 时用 matcher 查找所有规则的匹配项
+    pattern_matchers: Option<&'p [M]>,
+    /// Sink 创建的时间，用于统计搜索耗时
+    start_time: Instant,
+    /// 匹配的行计数
+    match_count: u64,
+    /// 统计记录，通过 SarifBuilder::stats() 开启
+    stats: Option<Stats>,
+}
+
+impl<'p, M: Matcher, W: WriteColor> SarifSink<'p, '_, M, W> {
+    /// 只输出 filter[PatternID] 为 true 的规则的结果，比如规则只适用于部分文件
+    pub fn pattern_filter(mut self, filter: &'p [bool]) -> Self {
+        self.pattern_filter = Some(filter);
+        self
+    }
+
+    /// 设置每条规则单独的匹配器，matchers[i] 只包含第 i 条规则的模式
+    /// matcher 只用来找出匹配行，匹配行中的每条规则分别查找，不会被同一位置序号更小或者未启用的规则遮挡
+    pub fn pattern_matchers(mut self, matchers: &'p [M]) -> Self {
+        self.pattern_matchers = Some(matchers);
+        self
+    }
+
+    /// 是否有匹配的行
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    /// 本次搜索的统计数据，只有开启了统计才会返回
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+}
+
+impl<M: Matcher, W: WriteColor> Sink for SarifSink<'_, '_, M, W> {
+    type Error = io::Error;
+
+    /// 匹配行中的每个匹配输出为一个结果
+    fn matched(
+        &mut self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+    ) -> Result<bool, Self::Error> {
+        let range = mat.bytes_range_in_buffer();
+        let matches = &mut self.sarif.matches;
+        let filter = self.pattern_filter;
+        let mut filtered = false;
+        matches.clear();
+        if let Some(matchers) = self.pattern_matchers {
+            find_iter_per_pattern(searcher, matchers, filter, mat.buffer(), range.clone(), |m, pid| {
+                matches.push((Match::new(m.start() - range.start, m.end() - range.start), pid));
+            })?;
+            matches.sort_by_key(|&(m, pid)| (m.start(), pid));
+            filtered = filter.is_some_and(|filter| filter.contains(&false));
+        } else {
+            find_iter_at_in_context(searcher, &self.matcher, mat.buffer(), range.clone(), |m, pid| {
+                if filter.is_none_or(|filter| filter.get(pid.as_usize()) == Some(&true)) {
+                    matches.push((Match::new(m.start() - range.start, m.end() - range.start), pid));
+                } else {
+                    filtered = true;
+                }
+                true
+            })?;
+        }
+        // 匹配行中的匹配都属于未启用的规则
+        if matches.is_empty() && filtered {
+            return Ok(true);
+        }
+        self.match_count += 1;
+
+        let bytes = mat.bytes();
+        let mut line = Match::new(0, bytes.len());
+        trim_line_terminator(searcher, bytes, &mut line);
+        if matches.is_empty() {
+            // 比如没有行终止符的最后一行末尾的空匹配（`\z`），上面不会报告行末尾的空匹配
+            // 这时在整个匹配行中找出是哪个模式匹配的，而不是都归到第一个模式
+            let mut pattern = None;
+            let haystack = &mat.buffer()[..range.end];
+            self.matcher.find_pattern_iter_at(haystack, range.start, |_, pid| {
+                if filter.is_none_or(|filter| filter.get(pid.as_usize()) == Some(&true)) {
+                    pattern = Some(pid);
+                    return false;
+                }
+                filtered = true;
+                true
+            }).map_err(io::Error::error_message)?;
+            match pattern {
+                Some(pid) => matches.push((line, pid)),
+                None if filtered => return Ok(true),
+                None => matches.push((line, PatternID::ZERO)),
+            }
+        }
+        if let Some(ref mut stats) = self.stats {
+            stats.add_matches(matches.len() as u64);
+            stats.add_matched_lines(1);
+        }
+        let uri = self.path.map_or_else(|| b"stdin".to_vec(), artifact_uri);
+        for i in 0..self.sarif.matches.len() {
+            let (m, pid) = self.sarif.matches[i];
+            let m = m.with_end(m.end().min(line.end()));
+            self.sarif.write_result(
+                &uri,
+                mat.line_number(),
+                mat.absolute_byte_offset(),
+                &bytes[line],
+                m,
+                pid,
+            )?;
+        }
+        Ok(true)
+    }
+
+    /// 搜索开始，重置本次搜索的匹配计数、计时和输出计数
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, Self::Error> {
+        self.sarif.wtr.reset_count();
+        self.start_time = Instant::now();
+        self.match_count = 0;
+        Ok(true)
+    }
+
+    /// 搜索结束，汇总本次搜索的统计数据
+    fn finish(
+        &mut self,
+        _searcher: &Searcher,
+        finish: &SinkFinish,
+    ) -> Result<(), Self::Error> {
+        if let Some(ref mut stats) = self.stats {
+            stats.add_elapsed(self.start_time.elapsed());
+            stats.add_searches(1);
+            if self.match_count > 0 {
+                stats.add_searches_with_match(1);
+            }
+            stats.add_bytes_searched(finish.byte_count());
+            stats.add_bytes_printed(self.sarif.wtr.count());
+        }
+        self.sarif.wtr.reset_count();
+        Ok(())
+    }
+}
+
+/// 文件路径转成 SARIF 的 artifactLocation.uri：相对路径去掉开头的 `./` 作为相对 URI，绝对路径使用 `file://` URI
+fn artifact_uri(path: &Path) -> Vec<u8> {
+    let path = path.strip_prefix(Component::CurDir).unwrap_or(path);
+    let encoded = HyperlinkPath::encode(path.as_os_str().as_bytes());
+    if path.is_absolute() {
+        [b"file://", encoded.as_bytes()].concat()
+    } else {
+        encoded.as_bytes().to_vec()
+    }
+}
+
+/// UTF-8 字符数，非法的 UTF-8 字节按替换字符计算，和 write_json_string 的输出一致
+fn char_count(bytes: &[u8]) -> u64 {
+    String::from_utf8_lossy(bytes).chars().count() as u64
+}
+
+/// 输出带引号的 JSON 字符串，非法的 UTF-8 字节替换为 U+FFFD
+fn write_json_string(buf: &mut Vec<u8>, s: &[u8]) {
+    buf.push(b'"');
+    for ch in String::from_utf8_lossy(s).chars() {
+        match ch {
+            '"' => buf.extend_from_slice(b"\\\""),
+            '\\' => buf.extend_from_slice(b"\\\\"),
+            '\n' => buf.extend_from_slice(b"\\n"),
+            '\r' => buf.extend_from_slice(b"\\r"),
+            '\t' => buf.extend_from_slice(b"\\t"),
+            c if (c as u32) < 0x20 => write!(buf, "\\u{:04x}", c as u32).unwrap(),
+            c => buf.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    buf.push(b'"');
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_regex::RegexMatcherBuilder;
+    use grep_searcher::SearcherBuilder;
+    use serde_json::Value;
+    use termcolor::NoColor;
+    use super::*;
+
+    /// SARIF 2.1.0 schema 中这里输出的对象的定义
+    const SARIF_SCHEMA: &str = include_str!("../tests/data/sarif-schema-2.1.0.json");
+
+    /// 返回文档不符合 schema 的地方，每个错误带上出错的位置
+    fn schema_errors(doc: &Value) -> Vec<String> {
+        let schema: Value = serde_json::from_str(SARIF_SCHEMA).unwrap();
+        let validator = jsonschema::options().should_validate_formats(true).build(&schema).unwrap();
+        validator.iter_errors(doc).map(|err| format!("{}: {err}", err.instance_path())).collect()
+    }
+
+    /// 用 SARIF 2.1.0 的 schema 校验文档，再检查 schema 无法表达的 ruleIndex 和 ruleId 的对应关系
+    fn check_required_properties(doc: &Value) {
+        let errors = schema_errors(doc);
+        assert!(errors.is_empty(), "{errors:#?}");
+        assert_eq!(SCHEMA, doc["$schema"]);
+        for run in doc["runs"].as_array().unwrap() {
+            let rules = &run["tool"]["driver"]["rules"];
+            for result in run["results"].as_array().unwrap() {
+                if let Some(index) = result["ruleIndex"].as_u64() {
+                    assert_eq!(rules[index as usize]["id"], result["ruleId"]);
+                }
+            }
+        }
+    }
+
+    fn search(sarif: &mut Sarif<NoColor<Vec<u8>>>, patterns: &[&str], path: Option<&str>, haystack: &str) {
+        let matcher = RegexMatcherBuilder::new().build_many(patterns).unwrap();
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        match path {
+            Some(path) => {
+                let mut sink = sarif.sink_with_path(&matcher, Path::new(path));
+                searcher.search_slice(&matcher, haystack.as_bytes(), &mut sink).unwrap();
+            }
+            None => {
+                let mut sink = sarif.sink(&matcher);
+                searcher.search_slice(&matcher, haystack.as_bytes(), &mut sink).unwrap();
+            }
+        }
+    }
+
+    fn output(mut sarif: Sarif<NoColor<Vec<u8>>>) -> Value {
+        sarif.end().unwrap();
+        serde_json::from_slice(sarif.get_mut().get_ref()).unwrap()
+    }
+
+    #[test]
+    fn required_properties() {
+        let patterns = ["TODO", "unsafe"];
+        let rules = vec![
+            SarifRule::new("no-todo".to_string(), "TODO".to_string())
+                .message(Some("TODO \"left\" in code".to_string())),
+            SarifRule::new("pattern-2".to_string(), "unsafe".to_string()).level(SarifLevel::Error),
+        ];
+        let mut sarif = SarifBuilder::new().tool("gs".to_string(), None).rules(rules).build(NoColor::new(vec![]));
+        search(&mut sarif, &patterns, Some("./src/main.rs"), "fn main() {}\n\té // TODO unsafe\n");
+        search(&mut sarif, &patterns, Some("a b.rs"), "nothing\n");
+        search(&mut sarif, &patterns, None, "x\ty\u{1}TODO\n");
+        let doc = output(sarif);
+        check_required_properties(&doc);
+
+        let run = &doc["runs"][0];
+        assert_eq!("gs", run["tool"]["driver"]["name"]);
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(3, results.len());
+        assert_eq!("no-todo", results[0]["ruleId"]);
+        assert_eq!("warning", results[0]["level"]);
+        assert_eq!("TODO \"left\" in code", results[0]["message"]["text"]);
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!("src/main.rs", location["artifactLocation"]["uri"]);
+        let region = &location["region"];
+        assert_eq!(2, region["startLine"]);
+        // 列号按字符计算，`é` 是两个字节但只算一列
+        assert_eq!(7, region["startColumn"]);
+        assert_eq!(11, region["endColumn"]);
+        assert_eq!(20, region["byteOffset"]);
+        assert_eq!("\té // TODO unsafe", region["snippet"]["text"]);
+        assert_eq!("pattern-2", results[1]["ruleId"]);
+        assert_eq!("error", results[1]["level"]);
+        assert_eq!("Match for pattern 'unsafe'", results[1]["message"]["text"]);
+        let location = &results[2]["locations"][0]["physicalLocation"];
+        assert_eq!("stdin", location["artifactLocation"]["uri"]);
+
+        // schema 能发现取值错误和 SARIF 中没有的属性
+        let mut invalid = doc.clone();
+        invalid["runs"][0]["columnKind"] = "bytes".into();
+        invalid["runs"][0]["results"][0]["level"] = "fatal".into();
+        invalid["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"]["startLine"] = 0.into();
+        invalid["runs"][0]["results"][1]["severity"] = "error".into();
+        assert_eq!(4, schema_errors(&invalid).len(), "{:#?}", schema_errors(&invalid));
+    }
+
+    /// 找不到匹配项的匹配行（没有行终止符的最后一行末尾的 `\z`）也要归到实际匹配的模式
+    #[test]
+    fn pattern_of_line_without_matches() {
+        let rules = vec![
+            SarifRule::new("foo".to_string(), "foo".to_string()),
+            SarifRule::new("end".to_string(), r"\z".to_string()),
+        ];
+        let mut sarif = SarifBuilder::new().rules(rules).build(NoColor::new(vec![]));
+        search(&mut sarif, &["foo", r"\z"], Some("a.txt"), "foo\nb");
+        let doc = output(sarif);
+        check_required_properties(&doc);
+        let results = doc["runs"][0]["results"].as_array().unwrap();
+        let rule_ids: Vec<&str> = results.iter().map(|result| result["ruleId"].as_str().unwrap()).collect();
+        assert_eq!(vec!["foo", "end"], rule_ids);
+    }
+
+    #[test]
+    fn empty_document() {
+        let doc = output(SarifBuilder::new().build(NoColor::new(vec![])));
+        check_required_properties(&doc);
+        assert!(doc["runs"][0]["results"].as_array().unwrap().is_empty());
+        assert_eq!(Ok(SarifLevel::Note), "note".parse());
+        assert!("fatal".parse::<SarifLevel>().is_err());
+        assert!(SarifLevel::Error > SarifLevel::Warning && SarifLevel::Note > SarifLevel::None);
+        assert_eq!(b"file:///a%20b".to_vec(), artifact_uri(Path::new("/a b")));
+    }
+}
@@ -6,11 +6,14 @@ use std::io::Write;
 use std::os::unix::ffi::OsStrExt;
 use std::path::Path;
 use std::sync::Arc;
-use termcolor::{ColorSpec, WriteColor};
-use grep_matcher::{LineTerminator, Match, Matcher};
-use grep_searcher::{Searcher, Sink, SinkMatch};
+use std::time::Instant;
+use termcolor::{ColorSpec, HyperlinkSpec, WriteColor};
+use grep_matcher::{Match, Matcher, PatternID};
+use grep_searcher::{Searcher, Sink, SinkContext, SinkContextKind, SinkFinish, SinkMatch};
 use crate::color::ColorSpecs;
 use crate::counter::CounterWriter;
+use crate::hyperlink::{HyperlinkFormat, HyperlinkPath};
+use crate::stats::Stats;
 use crate::util::{DecimalFormatter, find_iter_at_in_context, Sunk, trim_ascii_prefix, trim_line_terminator};
 
 #[derive(Debug, Clone)]
@@ -21,16 +24,32 @@ struct Config {
     trim_ascii: bool,
     /// 单行的长度最大值
     max_columns: Option<u64>,
+    /// 超过 max_columns 的行是否输出前 max_columns 个字节作为预览，而不是整行省略
+    max_columns_preview: bool,
     /// 打印匹配数据时是否带上文件路径信息，默认带上
     path: bool,
     /// 是否将匹配行所属文件路径作为标题打印，默认true, 否则会作为每一个匹配行的前缀每次打印匹配行的时候都打印一次
     heading: bool,
+    /// 以标题方式打印时，是否在不同文件的结果之间空一行，默认 true
+    heading_separator: bool,
     /// 文件路径终止符，比如打印匹配行所属文件路径的时候后面带上冒号
     path_terminator: Option<u8>,
     /// 是否打印匹配字符串首字节在匹配行中的列号
     column: bool,
+    /// 是否打印行首在整个输入中的字节偏移
+    byte_offset: bool,
     /// 字段分隔符，打印匹配行时，输出内容可能包括文件路径、行号、列号、行内容，需要使用字符分隔符分隔这些部分
     separator_field_match: Arc<Vec<u8>>,
+    /// 上下文行的字段分隔符，和 separator_field_match 区分开方便看出哪些是匹配行
+    separator_field_context: Arc<Vec<u8>>,
+    /// 不连续的上下文之间输出的分隔行，None 表示不输出
+    separator_context: Option<Arc<Vec<u8>>>,
+    /// 是否统计搜索数据，开启后每次搜索结束可以通过 StandardSink::stats() 获取
+    stats: bool,
+    /// 文件路径的超链接格式，为空时不输出超链接
+    hyperlink: HyperlinkFormat,
+    /// 是否以 pretty 模式输出，标题带上文件的匹配数，行号按文件中最大的行号右对齐
+    pretty: bool,
 }
 
 impl Default for Config {
@@ -39,11 +58,19 @@ impl Default for Config {
             colors: ColorSpecs::default(),
             trim_ascii: false,
             max_columns: None,
+            max_columns_preview: false,
             path: true,
             heading: true,
+            heading_separator: true,
             path_terminator: None,
             column: false,
+            byte_offset: false,
             separator_field_match: Arc::new(b":".to_vec()), // b":" 表示字符串字面量":"的字节数组
+            separator_field_context: Arc::new(b"-".to_vec()),
+            separator_context: Some(Arc::new(b"--".to_vec())),
+            stats: false,
+            hyperlink: HyperlinkFormat::empty(),
+            pretty: false,
         }
     }
 }
@@ -63,6 +90,8 @@ impl StandardBuilder {
             config: self.config.clone(),
             wtr: RefCell::new(CounterWriter::new(wtr)),
             matches: vec![],
+            pattern_ids: vec![],
+            pretty_lines: vec![],
         }
     }
 
@@ -71,11 +100,48 @@ impl StandardBuilder {
         self
     }
 
+    /// 是否打印行首在整个输入中的字节偏移（从 0 开始），位于行号和列号之后，默认 false
+    pub fn byte_offset(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.byte_offset = yes;
+        self
+    }
+
+    /// 打印时是否带上文件路径，默认 true，设置为 false 时 sink_with_path 和 sink 一样
+    pub fn path(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.path = yes;
+        self
+    }
+
+    /// 匹配行中路径、行号、列号、字节偏移和行内容之间的分隔符，默认 `:`，可以是多个字节
+    pub fn separator_field_match(&mut self, sep: Vec<u8>) -> &mut StandardBuilder {
+        self.config.separator_field_match = Arc::new(sep);
+        self
+    }
+
+    /// 上下文行中各个字段之间的分隔符，默认 `-`，可以是多个字节
+    pub fn separator_field_context(&mut self, sep: Vec<u8>) -> &mut StandardBuilder {
+        self.config.separator_field_context = Arc::new(sep);
+        self
+    }
+
+    /// 不连续的上下文之间输出的分隔行，默认 `--`，None 表示不输出
+    pub fn separator_context(&mut self, sep: Option<Vec<u8>>) -> &mut StandardBuilder {
+        self.config.separator_context = sep.map(Arc::new);
+        self
+    }
+
     pub fn heading(&mut self, heading: bool) -> &mut StandardBuilder {
         self.config.heading = heading;
         self
     }
 
+    /// 以标题方式打印时，是否由 Printer 在不同文件的结果之间空一行，默认 true
+    /// 并行搜索时每个文件的结果写到单独的 Buffer，Printer 不知道其他线程有没有输出过，这时应该关掉，改用 BufferWriter::separator
+    pub fn heading_separator(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.heading_separator = yes;
+        self
+    }
+
     pub fn path_terminator(&mut self, path_terminator: Option<u8>) -> &mut StandardBuilder {
         self.config.path_terminator = path_terminator;
         self
@@ -95,6 +161,34 @@ impl StandardBuilder {
         self.config.max_columns = limit;
         self
     }
+
+    /// 超过 max_columns 的行输出前 max_columns 个字节（在字符边界截断）作为预览，预览中的匹配照常高亮，
+    /// 后面跟上 `[... N more matches]` 说明预览之外还有几个匹配，默认 false，即整行省略
+    pub fn max_columns_preview(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.max_columns_preview = yes;
+        self
+    }
+
+    /// 是否统计搜索数据（匹配次数、搜索字节数、耗时等），默认不统计
+    pub fn stats(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.stats = yes;
+        self
+    }
+
+    /// 设置文件路径的超链接格式，输出路径时使用 OSC 8 转义序列把路径包装成可点击的链接，默认不输出超链接
+    /// 只有 WriteColor 支持超链接（比如 ANSI 终端）时才会输出
+    pub fn hyperlink(&mut self, format: HyperlinkFormat) -> &mut StandardBuilder {
+        self.config.hyperlink = format;
+        self
+    }
+
+    /// 是否以 pretty 模式输出，默认 false
+    /// 标题带上文件中的匹配数，比如 `src/main.rs (3 matches)`，行号按文件中最大的行号的宽度右对齐
+    /// 这两者都要等文件搜索完才知道，所以每个文件的结果先缓存起来，搜索结束时再一起输出
+    pub fn pretty(&mut self, yes: bool) -> &mut StandardBuilder {
+        self.config.pretty = yes;
+        self
+    }
 }
 
 /// 标准输出的Printer类型
@@ -106,14 +200,61 @@ pub struct Standard<W> {
     wtr: RefCell<CounterWriter<W>>,
     /// 使用向量存储匹配的字符串在行中的位置范围，因为一行中可能有多个匹配的字符串所以用向量存储
     matches: Vec<Match>,
+    /// matches 中每个匹配字符串所属的模式序号，用于按模式使用不同的颜色高亮
+    pattern_ids: Vec<PatternID>,
+    /// pretty 模式下当前文件缓存的输出行
+    pretty_lines: Vec<PrettyLine>,
+}
+
+/// pretty 模式下缓存的一行输出
+#[derive(Clone, Debug)]
+enum PrettyLine {
+    /// 匹配行或上下文行
+    Line {
+        bytes: Vec<u8>,
+        absolute_byte_offset: u64,
+        line_number: Option<u64>,
+        /// 上下文行的类型，匹配行为 None
+        context_kind: Option<SinkContextKind>,
+        matches: Vec<Match>,
+        pattern_ids: Vec<PatternID>,
+    },
+    /// 不连续的上下文之间的分隔行
+    ContextBreak,
+}
+
+/// pretty 模式下回放一个文件的缓存结果时使用的汇总信息
+#[derive(Clone, Copy, Debug)]
+struct PrettySummary {
+    /// 文件中的匹配数，输出在标题中
+    match_count: u64,
+    /// 文件中最大的行号的宽度，行号按这个宽度右对齐
+    line_number_width: usize,
+}
+
+impl<W> Standard<W> {
+    /// 输出目的地的可变引用，比如并行搜索时每搜索完一个文件需要取出 Buffer 输出并清空
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut().get_mut()
+    }
 }
 
 impl<W: WriteColor> Standard<W> {
-    // pub fn sink(&mut self) -> StandardSink<W> {
-    //     StandardSink {
-    //         standard: self,
-    //     }
-    // }
+    /// 打印匹配信息时不带文件路径信息，比如搜索标准输入
+    pub fn sink<'s, M: Matcher>(&'s mut self, matcher: M) -> StandardSink<'static, 's, M, W> {
+        let stats = if self.config.stats { Some(Stats::new()) } else { None };
+        StandardSink {
+            matcher,
+            standard: self,
+            path: None,
+            hyperlink_path: None,
+            start_time: Instant::now(),
+            match_count: 0,
+            stats,
+            needs_match_granularity: true,
+            pretty_summary: None,
+        }
+    }
 
     /// 打印匹配信息时会带着文件路径信息
     // pub fn sink_with_path<M>(
@@ -129,20 +270,31 @@ impl<W: WriteColor> Standard<W> {
     where
         M: Matcher,
     {
-        // if !self.config.path {  //如果设置打印时不带文件路径信息
-        //     return self.sink();
-        // }
+        if !self.config.path {  //如果设置打印时不带文件路径信息
+            return self.sink(matcher);
+        }
 
         // 带文件路径信息的处理
         // 这一步是兼容不同系统不同的路径格式，这里不需要
         // let ppath = PrinterPath::new(path.as_ref())
         //     .with_separator(self.config.separator_path);
+        let stats = if self.config.stats { Some(Stats::new()) } else { None };
+        // 超链接需要绝对路径，每个文件只需要转换一次
+        let hyperlink_path = if self.config.hyperlink.is_empty() {
+            None
+        } else {
+            HyperlinkPath::from_path(path)
+        };
         StandardSink {
             matcher,
             standard: self,
-            path,
+            path: Some(path),
+            hyperlink_path,
+            start_time: Instant::now(),
             match_count: 0,
+            stats,
             needs_match_granularity: true,
+            pretty_summary: None,
         }
     }
 }
@@ -153,8 +305,8 @@ struct StandardImpl<'a, M: Matcher, W> {
     searcher: &'a Searcher,
     sink: &'a StandardSink<'a, 'a, M, W>,
     sunk: Sunk<'a>,
-    /// 是否已经为匹配的字段设置好颜色显示，当输出无颜色的字符串前清除（false），当输出有颜色的字符串前设置（true）
-    in_color_match: Cell<bool>,
+    /// 当前为哪个模式的匹配字段设置好了颜色，当输出无颜色的字符串前清除（None），当输出有颜色的字符串前设置
+    in_color_match: Cell<Option<PatternID>>,
 }
 
 impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
@@ -166,7 +318,7 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
             searcher,
             sink,
             sunk: Sunk::empty(),
-            in_color_match: Cell::new(false),
+            in_color_match: Cell::new(None),
         }
     }
 
@@ -178,11 +330,21 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         let sunk = Sunk::from_sink_match(
             mat,
             &sink.standard.matches,
+            &sink.standard.pattern_ids,
             // sink.replacer.replacement(),
         );
         StandardImpl { sunk, ..StandardImpl::new(searcher, sink) }  //这里 .. 是解构并赋值
     }
 
+    fn from_context(
+        searcher: &'a Searcher,
+        sink: &'a StandardSink<'_, '_, M, W>,
+        ctx: &'a SinkContext<'a>,
+    ) -> StandardImpl<'a, M, W> {
+        let sunk = Sunk::from_sink_context(ctx);
+        StandardImpl { sunk, ..StandardImpl::new(searcher, sink) }
+    }
+
     fn sink(&self) -> io::Result<()> {
         //打印匹配行前处理（用于配置以标题的形式打印文件路径）
         self.write_search_prelude()?;
@@ -194,9 +356,14 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         }
     }
 
+    /// 没有需要高亮的匹配字段（比如上下文行）时直接输出整行
     fn sink_fast(&self) -> io::Result<()> {
-        //TODO
-        Ok(())
+        self.write_prelude(
+            self.sunk.absolute_byte_offset(),
+            self.sunk.line_number(),
+            None,
+        )?;
+        self.write_line(self.sunk.bytes())
     }
 
     fn sink_slow(&self) -> io::Result<()> {
@@ -222,13 +389,13 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         prelude.start()?;
         // 1 前面也有调用打印路径的方法，区别是前面调用的方法将路径作为标题的方式打印，
         // 这里的方法则是将路径作为匹配行的前缀，即每个匹配行都会打印一次路径信息
-        prelude.write_path()?;
+        prelude.write_path(line_number, column)?;
         // 2 打印行号
         prelude.write_line_number(line_number)?;
         // 3 打印列号，匹配字符串在匹配行中开始的列
         prelude.write_column_number(column)?;
-        // 4 ripgrep  还支持打印绝对偏移量，但是不重要，忽略
-        // prelude.write_byte_offset(absolute_byte_offset)?;
+        // 4 打印行首在整个输入中的字节偏移
+        prelude.write_byte_offset(absolute_byte_offset)?;
         prelude.end()
     }
 
@@ -240,21 +407,30 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
             return Ok(());
         }
 
-        // 之前是否有写过，是的话就写个行终止符，比如换个行
+        // 没有路径时不需要标题
+        let Some(path) = self.path() else { return Ok(()) };
+        // 以标题的方式打印时，如果之前有写过，就写个行终止符，即不同文件的匹配结果之间空一行
         let ever_written = self.wtr().borrow().total_count() > 0;
-        if ever_written {
+        if ever_written && self.config().heading && self.config().heading_separator {
             self.write_line_term()?;
         }
         // 以标题的方式打印匹配行所属文件路径
         if self.config().heading {
-            self.write_path_line()?;
+            self.write_path_line(path)?;
         }
         Ok(())
     }
 
     /// 打印文件路径带路径终止符
-    fn write_path_line(&self) -> io::Result<()> {
-        self.write_path(self.path())?;
+    fn write_path_line(&self, path: &Path) -> io::Result<()> {
+        self.write_path(path, None, None)?;
+        if let Some(summary) = self.sink.pretty_summary {
+            let count = match summary.match_count {
+                1 => " (1 match)".to_string(),
+                n => format!(" ({n} matches)"),
+            };
+            self.write(count.as_bytes())?;
+        }
         if let Some(term) = self.config().path_terminator {
             self.write(&[term])?;
         } else {
@@ -263,14 +439,33 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         Ok(())
     }
 
-    fn write_path(&self, path: &Path) -> io::Result<()> {
+    /// 打印文件路径，设置了超链接格式时用超链接包装路径，line 和 column 用于替换格式中的 `{line}` `{column}`
+    fn write_path(&self, path: &Path, line: Option<u64>, column: Option<u64>) -> io::Result<()> {
+        let linked = self.start_hyperlink(line, column)?;
         let mut wtr = self.wtr().borrow_mut();
         wtr.set_color(self.config().colors.path())?;
         wtr.write_all(path.as_os_str().as_bytes())?;
-        wtr.reset()
+        wtr.reset()?;
+        if linked {
+            wtr.set_hyperlink(&HyperlinkSpec::close())?;
+        }
+        Ok(())
     }
 
-    fn path(&self) -> &'a Path {
+    /// 开始输出超链接，返回是否真的输出了，输出了的话写完路径后需要关闭超链接
+    fn start_hyperlink(&self, line: Option<u64>, column: Option<u64>) -> io::Result<bool> {
+        let Some(ref path) = self.sink.hyperlink_path else { return Ok(false) };
+        let mut wtr = self.wtr().borrow_mut();
+        if !wtr.supports_hyperlinks() {
+            return Ok(false);
+        }
+        let mut uri = vec![];
+        self.config().hyperlink.write_uri(path, line, column, &mut uri);
+        wtr.set_hyperlink(&HyperlinkSpec::open(&uri))?;
+        Ok(true)
+    }
+
+    fn path(&self) -> Option<&'a Path> {
         self.sink.path
     }
 
@@ -282,8 +477,22 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         Ok(())
     }
 
+    /// 匹配行和上下文行使用不同的字段分隔符
     fn separator_field(&self) -> &[u8] {
-        &self.config().separator_field_match
+        if self.sunk.context_kind().is_some() {
+            &self.config().separator_field_context
+        } else {
+            &self.config().separator_field_match
+        }
+    }
+
+    /// 不连续的上下文之间输出分隔行
+    fn write_context_separator(&self) -> io::Result<()> {
+        if let Some(ref sep) = self.config().separator_context {
+            self.write(sep)?;
+            self.write_line_term()?;
+        }
+        Ok(())
     }
 
     /// 根据配置决定是否使用颜色高亮输出匹配的行
@@ -298,10 +507,10 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         }
 
         let mut line = Match::new(0, bytes.len());
+        self.trim_line_terminator(bytes, &mut line);
         self.trim_ascii_prefix(bytes, &mut line);
-        if self.exceeds_max_columns(bytes) {
-            // self.write_exceeded_line(bytes, line, matches, &mut 0)
-            self.write(b"[Omitted long context line]")
+        if self.exceeds_max_columns(&bytes[line]) {
+            self.write_exceeded_line(bytes, line, matches, &mut 0)
         } else {
             self.write_colored_matches(bytes, line, matches, &mut 0)?;
             self.write_line_term()?;
@@ -315,47 +524,63 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
 
     /// 无颜色输出匹配的行
     fn write_line(&self, line: &[u8]) -> io::Result<()> {
-        /// 裁剪 line 中前缀 ascii 空白字符操作
-        let line = if !self.config().trim_ascii {
-            line
-        } else {
-            // let line_terminator = self.searcher.line_terminator();   //TODO
-            let line_terminator = LineTerminator::default();
-            let full_range = Match::new(0, line.len());
-            let range = trim_ascii_prefix(line_terminator, line, full_range);
-            &line[range]
-        };
+        // 先去掉行终止符（--crlf 时连同 `\r` 一起去掉），输出完行内容后统一由 write_line_term() 输出行终止符
+        let mut range = Match::new(0, line.len());
+        self.trim_line_terminator(line, &mut range);
+        // 裁剪 line 中前缀 ascii 空白字符操作
+        if self.config().trim_ascii {
+            range = trim_ascii_prefix(self.searcher.line_terminator(), line, range);
+        }
 
-        if self.exceeds_max_columns(line) { //当前行大于设置的行最大长度
-            // let range = Match::new(0, line.len());
-            // ripgrep 对于超过最大长度的行的处理参考 write_exceeded_line(） 这个方法，不是很重要的功能这里不深入了
-            // self.write_exceeded_line(
-            //     line,
-            //     range,
-            //     self.sunk.matches(),
-            //     &mut 0,
-            // )?;
-            self.write(b"[Omitted long context line]")?;
+        if self.exceeds_max_columns(&line[range]) { //当前行大于设置的行最大长度
+            self.write_exceeded_line(line, range, self.sunk.matches(), &mut 0)?;
         } else {
-            self.write(line)?;  //直接输出行
-            if !self.has_line_terminator(line) {    //没有行终止符就输出一个行终止符进行换行
-                self.write_line_term()?;
-            }
+            self.write(&line[range])?;  //直接输出行
+            self.write_line_term()?;
         }
         Ok(())
     }
 
+    /// 输出超过 max_columns 的行，开启了预览时输出前 max_columns 个字节并高亮其中的匹配，否则整行省略
+    fn write_exceeded_line(
+        &self,
+        bytes: &[u8],
+        mut line: Match,        //行内容（不含行终止符）的范围
+        matches: &[Match],
+        match_index: &mut usize,
+    ) -> io::Result<()> {
+        if !self.config().max_columns_preview {
+            let message = match matches.len() {
+                0 => "[Omitted long line]".to_string(),
+                1 => "[Omitted long line with 1 match]".to_string(),
+                n => format!("[Omitted long line with {n} matches]"),
+            };
+            self.write(message.as_bytes())?;
+            return self.write_line_term();
+        }
+        // 在字符边界截断，避免把多字节字符截成两半
+        let max_columns = usize::try_from(self.config().max_columns.unwrap_or(u64::MAX))
+            .unwrap_or(usize::MAX);
+        let end = floor_char_boundary(bytes, line.start(), line.start().saturating_add(max_columns).min(line.end()));
+        line = line.with_end(end);
+        self.write_colored_matches(bytes, line, matches, match_index)?;
+        // 开始位置在预览之外的匹配数量，跨越预览末尾的匹配已经部分输出，不计入
+        let remaining = matches.iter().filter(|m| m.start() >= end).count();
+        let message = match remaining {
+            0 => " [... omitted end of long line]".to_string(),
+            1 => " [... 1 more match]".to_string(),
+            n => format!(" [... {n} more matches]"),
+        };
+        self.write(message.as_bytes())?;
+        self.write_line_term()
+    }
+
+    /// line 是不含行终止符的行内容
     fn exceeds_max_columns(&self, line: &[u8]) -> bool {
         // max_columns 为空 None, 就返回默认值 false, 否则调用闭包判断当前行的长度是否大于设置的行最大长度
         self.config().max_columns.map_or(false, |m| line.len() as u64 > m)
     }
 
-    fn has_line_terminator(&self, buf: &[u8]) -> bool {
-        // TODO: 先用默认的行终止符替换调通基本功能
-        // self.searcher.line_terminator().is_suffix(buf)
-        LineTerminator::default().is_suffix(buf)
-    }
-
     /// 输出匹配的行
     /// 匹配的行的输出是一部分一部分写的，因为termcolor对颜色的控制无法做到精细控制，
     /// 只能写完非高亮部分，然后设置颜色再写颜色高亮部分，写完高亮部分再重置颜色配置再写非高亮部分，这样交替
@@ -395,7 +620,7 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
                 line = line.with_start(upto);
             } else {    //高亮部分
                 let upto = cmp::min(line.end(), m.end());
-                self.start_color_match()?;
+                self.start_color_match(self.sunk.pattern_id(*match_index))?;
                 self.write(&bytes[line.with_end(upto)])?;
                 line = line.with_start(upto);
             }
@@ -409,9 +634,9 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
         trim_line_terminator(&self.searcher, buf, line);
     }
 
+    /// 输出行终止符，CRLF 模式下只输出 `\n`，即不会输出 `\r`
     fn write_line_term(&self) -> io::Result<()> {
-        // self.write(LineTerminator::default().as_bytes())
-        self.write(self.searcher.line_terminator().as_bytes())
+        self.write(&[self.searcher.line_terminator().as_byte()])
     }
 
     /// 将数据写入 io::Write 即输出
@@ -424,24 +649,28 @@ impl<'a, M: Matcher, W: WriteColor> StandardImpl<'a, M, W> {
     }
 
     /// 设置 WriteColor Writer 的颜色配置
-    /// 写匹配的行的高亮部分时使用
-    fn start_color_match(&self) -> io::Result<()> {
-        if self.in_color_match.get() {
+    /// 写匹配的行的高亮部分时使用，相邻的两个匹配字段属于不同模式时需要切换颜色
+    fn start_color_match(&self, pid: PatternID) -> io::Result<()> {
+        if self.in_color_match.get() == Some(pid) {
             return Ok(());
         }
-        self.wtr().borrow_mut().set_color(self.config().colors.matched())?;
-        self.in_color_match.set(true);
+        let mut wtr = self.wtr().borrow_mut();
+        if self.in_color_match.get().is_some() {
+            wtr.reset()?;
+        }
+        wtr.set_color(self.config().colors.matched_pattern(pid))?;
+        self.in_color_match.set(Some(pid));
         Ok(())
     }
 
     /// 重置 WriteColor Writer 的配置，比如清除颜色配置
     /// 写匹配的行的非高亮部分时使用
     fn end_color_match(&self) -> io::Result<()> {
-        if !self.in_color_match.get() {
+        if self.in_color_match.get().is_none() {
             return Ok(());
         }
         self.wtr().borrow_mut().reset()?;
-        self.in_color_match.set(false);
+        self.in_color_match.set(None);
         Ok(())
     }
 
@@ -487,17 +716,19 @@ impl<'a, M: Matcher, W: WriteColor> PreludeWriter<'a, M, W> {
 
     /// 以匹配行前缀的方式打印文件路径
     #[inline(always)]
-    fn write_path(&mut self) -> io::Result<()> {
+    fn write_path(&mut self, line: Option<u64>, column: Option<u64>) -> io::Result<()> {
         if self.config().heading {
             // true, 说明是选择了标题的方式打印匹配行，这里不需要执行
             return Ok(())
         }
         // 下面是以匹配行前缀的方式打印文件路径的实现
-        let path= self.std.path();
+        let Some(path) = self.std.path() else { return Ok(()) };
         // 1 先打印分隔符
         self.write_separator()?;
         // 2 打印文件路径
-        self.std.write_path(path)?;
+        // 没有打印列号时链接也不带列号，跳转到行首
+        let column = if self.config().column { column } else { None };
+        self.std.write_path(path, line, column)?;
         // 3 设置下一个分隔符类型，作为文件路径和匹配行之间的分隔符，如果有配置单独的路径分隔符则设置 PathTerminator，否则使用 FieldSeparator
         self.next_separator = if self.config().path_terminator.is_some() {
             PreludeSeparator::PathTerminator
@@ -514,6 +745,11 @@ impl<'a, M: Matcher, W: WriteColor> PreludeWriter<'a, M, W> {
         self.write_separator()?;
         // 十进制数转 u8 数组
         let n = DecimalFormatter::new(line_number);
+        // pretty 模式下行号右对齐
+        if let Some(summary) = self.std.sink.pretty_summary {
+            let padding = summary.line_number_width.saturating_sub(n.as_bytes().len());
+            self.std.write(&b" ".repeat(padding))?;
+        }
         // 颜色高亮打印行号
         self.std.write_spec(self.config().colors.line(), n.as_bytes())?;
         self.next_separator = PreludeSeparator::FieldSeparator;
@@ -534,6 +770,19 @@ impl<'a, M: Matcher, W: WriteColor> PreludeWriter<'a, M, W> {
         Ok(())
     }
 
+    /// 打印行首在整个输入中的字节偏移
+    #[inline(always)]
+    fn write_byte_offset(&mut self, offset: u64) -> io::Result<()> {
+        if !self.config().byte_offset {
+            return Ok(());
+        }
+        self.write_separator()?;
+        let n = DecimalFormatter::new(offset);
+        self.std.write(n.as_bytes())?;
+        self.next_separator = PreludeSeparator::FieldSeparator;
+        Ok(())
+    }
+
     #[inline(always)]
     fn end(&mut self) -> io::Result<()> {
         self.write_separator()
@@ -573,17 +822,23 @@ pub struct StandardSink<'p, 's, M: Matcher, W> {
     /// 其实是为了兼容类Unix系统和Windows系统不同的路径格式，所以 ripgrep 封装了一层实现两种路径格式可以根据实际的系统环境进行转换
     /// 但是这里只是想简单展示 ripgrep 核心流程所以不需要，所以使用原生的路径类型
     // path: Option<PrinterPath<'p>>,
-    path: &'p Path,
-    // start_time: Instant,
+    /// 通过 sink() 创建时没有路径
+    path: Option<&'p Path>,
+    /// 转义好的绝对路径，用于生成超链接，没有设置超链接格式时为 None
+    hyperlink_path: Option<HyperlinkPath>,
+    /// Sink 创建的时间，用于统计搜索耗时
+    start_time: Instant,
     ///匹配的行计数
     match_count: u64,
     // 搭配最大可打印匹配行数使用，这个值记录还可以打印多少行
     // after_context_remaining: u64,
     // binary_byte_offset: Option<u64>,
-    // 统计记录，可以通过配置开启，但是先略
-    // stats: Option<Stats>,
+    /// 统计记录，通过 StandardBuilder::stats() 开启
+    stats: Option<Stats>,
     /// 看 ripgrep 实现逻辑推测这个参数控制是否高亮展示匹配行中所有匹配字段
     needs_match_granularity: bool,
+    /// pretty 模式下回放缓存结果时设置
+    pretty_summary: Option<PrettySummary>,
 }
 
 impl<'p, 's, M: Matcher, W: WriteColor> StandardSink<'p, 's, M, W> {
@@ -592,6 +847,11 @@ impl<'p, 's, M: Matcher, W: WriteColor> StandardSink<'p, 's, M, W> {
         self.match_count > 0
     }
 
+    /// 本次搜索的统计数据，只有开启了统计才会返回，搜索结束（Sink::finish）后才是完整的数据
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+
     /// 如果配置了 needs_match_granularity 需要找出匹配行中所有匹配字符串相对于行首的 Range 记录到 Standard matches
     fn record_matches(
         &mut self,
@@ -603,14 +863,61 @@ impl<'p, 's, M: Matcher, W: WriteColor> StandardSink<'p, 's, M, W> {
             return Ok(())
         }
         self.standard.matches.clear();
+        self.standard.pattern_ids.clear();
         let matches = &mut self.standard.matches;
-        find_iter_at_in_context(searcher, &self.matcher, bytes, range.clone(), |m| {
+        let pattern_ids = &mut self.standard.pattern_ids;
+        find_iter_at_in_context(searcher, &self.matcher, bytes, range.clone(), |m, pid| {
             let (s, e) = (m.start() - range.start, m.end() - range.start);
             matches.push(Match::new(s, e));
+            pattern_ids.push(pid);
             true
         })?;
         Ok(())
     }
+
+    /// pretty 模式下文件搜索结束后输出缓存的结果，这时才知道标题中的匹配数和行号的对齐宽度
+    fn write_pretty(&mut self, searcher: &Searcher) -> io::Result<()> {
+        let mut lines = std::mem::take(&mut self.standard.pretty_lines);
+        let (mut match_count, mut max_line_number) = (0, 0);
+        for line in lines.iter() {
+            if let PrettyLine::Line { line_number, context_kind, matches, .. } = line {
+                if context_kind.is_none() {
+                    match_count += cmp::max(1, matches.len()) as u64;
+                }
+                max_line_number = cmp::max(max_line_number, line_number.unwrap_or(0));
+            }
+        }
+        self.pretty_summary = Some(PrettySummary {
+            match_count,
+            line_number_width: DecimalFormatter::new(max_line_number).as_bytes().len(),
+        });
+        let result = lines.iter().try_for_each(|line| match line {
+            PrettyLine::Line {
+                bytes,
+                absolute_byte_offset,
+                line_number,
+                context_kind,
+                matches,
+                pattern_ids,
+            } => {
+                let sunk = Sunk::new(
+                    bytes,
+                    *absolute_byte_offset,
+                    *line_number,
+                    context_kind.as_ref(),
+                    matches,
+                    pattern_ids,
+                );
+                StandardImpl { sunk, ..StandardImpl::new(searcher, self) }.sink()
+            }
+            PrettyLine::ContextBreak => StandardImpl::new(searcher, self).write_context_separator(),
+        });
+        self.pretty_summary = None;
+        // 留着分配好的空间给下一个文件用
+        lines.clear();
+        self.standard.pretty_lines = lines;
+        result
+    }
 }
 
 // impl<'p, 's, M: Matcher, W: WriteColor> Sink for StandardSink<'p, 's, M, W> {
@@ -631,39 +938,240 @@ impl<M: Matcher, W: WriteColor> Sink for StandardSink<'_, '_, M, W> {
         // 前面的逻辑是查到一个匹配行，但是只是知道这行里面有匹配的字符串并不知道实际有几个匹配的字符串，这里需要找出行里所有匹配字符串
         // 因为后面需要颜色高亮打印所有匹配字符串
         self.record_matches(searcher, mat.buffer(), mat.bytes_range_in_buffer())?;
+        if let Some(ref mut stats) = self.stats {
+            stats.add_matches(self.standard.matches.len() as u64);
+            stats.add_matched_lines(1);
+        }
 
+        if self.standard.config.pretty {
+            let line = PrettyLine::Line {
+                bytes: mat.bytes().to_vec(),
+                absolute_byte_offset: mat.absolute_byte_offset(),
+                line_number: mat.line_number(),
+                context_kind: None,
+                matches: self.standard.matches.clone(),
+                pattern_ids: self.standard.pattern_ids.clone(),
+            };
+            self.standard.pretty_lines.push(line);
+            return Ok(true);
+        }
         // 创建Printer实现类型，并打印匹配结果
         StandardImpl::from_match(searcher, self, mat).sink()?;
         // Ok(!self.should_quit())  //用于有最大匹配打印行数限制等场景，这里全部搜索不需要
         Ok(true)
     }
+
+    /// 输出上下文行，上下文行不高亮匹配字段
+    fn context(
+        &mut self,
+        searcher: &Searcher,
+        context: &SinkContext<'_>,
+    ) -> Result<bool, Self::Error> {
+        self.standard.matches.clear();
+        self.standard.pattern_ids.clear();
+        if self.standard.config.pretty {
+            let line = PrettyLine::Line {
+                bytes: context.bytes().to_vec(),
+                absolute_byte_offset: context.absolute_byte_offset(),
+                line_number: context.line_number(),
+                context_kind: Some(context.kind().clone()),
+                matches: vec![],
+                pattern_ids: vec![],
+            };
+            self.standard.pretty_lines.push(line);
+            return Ok(true);
+        }
+        StandardImpl::from_context(searcher, self, context).sink()?;
+        Ok(true)
+    }
+
+    /// 不连续的上下文之间输出分隔行
+    fn context_break(&mut self, searcher: &Searcher) -> Result<bool, Self::Error> {
+        if self.standard.config.pretty {
+            self.standard.pretty_lines.push(PrettyLine::ContextBreak);
+            return Ok(true);
+        }
+        StandardImpl::new(searcher, self).write_context_separator()?;
+        Ok(true)
+    }
+
+    /// 搜索开始，重置本次搜索的匹配计数、计时和输出计数
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, Self::Error> {
+        self.standard.wtr.borrow_mut().reset_count();
+        self.start_time = Instant::now();
+        self.match_count = 0;
+        self.standard.pretty_lines.clear();
+        Ok(true)
+    }
+
+    /// 搜索结束，汇总本次搜索的统计数据，并重置本次搜索的输出计数
+    fn finish(
+        &mut self,
+        searcher: &Searcher,
+        finish: &SinkFinish,
+    ) -> Result<(), Self::Error> {
+        if self.standard.config.pretty {
+            self.write_pretty(searcher)?;
+        }
+        let mut wtr = self.standard.wtr.borrow_mut();
+        if let Some(ref mut stats) = self.stats {
+            stats.add_elapsed(self.start_time.elapsed());
+            stats.add_searches(1);
+            if self.match_count > 0 {
+                stats.add_searches_with_match(1);
+            }
+            stats.add_bytes_searched(finish.byte_count());
+            stats.add_bytes_printed(wtr.count());
+        }
+        wtr.reset_count();
+        Ok(())
+    }
+}
+
+/// 不大于 pos 的最大的字符边界，pos 处是 UTF-8 多字节字符的后续字节时向前退到这个字符的开头
+/// 非法的 UTF-8 最多退 3 个字节，避免在二进制数据中退回到行首，也不会退到 start 之前
+fn floor_char_boundary(bytes: &[u8], start: usize, pos: usize) -> usize {
+    let mut end = pos;
+    while end > start && pos - end < 3 && bytes.get(end).is_some_and(|&b| b & 0xC0 == 0x80) {
+        end -= 1;
+    }
+    end
 }
 
 #[cfg(test)]
 mod tests {
-    use termcolor::ColorChoice;
-    use grep_matcher::Match;
-    use crate::standard::{StandardBuilder, StandardImpl};
+    use grep_regex::RegexMatcherBuilder;
+    use grep_searcher::SearcherBuilder;
+    use termcolor::NoColor;
+    use std::path::Path;
+    use crate::standard::StandardBuilder;
+
+    const SHERLOCK: &str = "\
+For the Doctor Watsons of this world, as opposed to the Sherlock
+Holmeses, success in the province of detective work must always
+be, to a very large extent, the result of luck. Sherlock Holmes
+can extract a clew from a wisp of straw or a flake of cigar ash;
+";
+
+    /// 用 builder 创建的 Standard 搜索 haystack，返回无颜色的输出，path 为 None 时不带路径
+    fn print(
+        builder: &StandardBuilder,
+        searcher: &mut grep_searcher::Searcher,
+        pattern: &str,
+        haystack: &str,
+        path: Option<&str>,
+    ) -> String {
+        let matcher = RegexMatcherBuilder::new().build(pattern).unwrap();
+        let mut standard = builder.build(NoColor::new(vec![]));
+        match path {
+            Some(path) => {
+                let mut sink = standard.sink_with_path(&matcher, Path::new(path));
+                searcher.search_slice(&matcher, haystack.as_bytes(), &mut sink).unwrap();
+            }
+            None => {
+                let mut sink = standard.sink(&matcher);
+                searcher.search_slice(&matcher, haystack.as_bytes(), &mut sink).unwrap();
+            }
+        }
+        String::from_utf8(standard.get_mut().get_ref().clone()).unwrap()
+    }
+
+    #[test]
+    fn path_and_heading() {
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        let mut builder = StandardBuilder::new();
+        builder.heading(false);
+        let got = print(&builder, &mut searcher, "Sherlock", SHERLOCK, Some("sherlock"));
+        assert_eq!(
+            "sherlock:1:For the Doctor Watsons of this world, as opposed to the Sherlock\n\
+             sherlock:3:be, to a very large extent, the result of luck. Sherlock Holmes\n",
+            got,
+        );
+        // 不带路径时 sink_with_path 和 sink 一样
+        builder.path(false);
+        let got = print(&builder, &mut searcher, "Sherlock", SHERLOCK, Some("sherlock"));
+        assert_eq!(
+            "1:For the Doctor Watsons of this world, as opposed to the Sherlock\n\
+             3:be, to a very large extent, the result of luck. Sherlock Holmes\n",
+            got,
+        );
+        let got = print(&StandardBuilder::new(), &mut searcher, "Watson", SHERLOCK, None);
+        assert_eq!("1:For the Doctor Watsons of this world, as opposed to the Sherlock\n", got);
+    }
+
+    #[test]
+    fn byte_offset_and_separators() {
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        let mut builder = StandardBuilder::new();
+        builder.heading(false).byte_offset(true).separator_field_match(b"\xE2\x94\x82".to_vec());
+        let got = print(&builder, &mut searcher, "clew", SHERLOCK, Some("sherlock"));
+        assert_eq!("sherlock│4│193│can extract a clew from a wisp of straw or a flake of cigar ash;\n", got);
+    }
 
-    // 参考 hiargs.rs 中从 printer() 创建 Printer 实例到 ReadByLine run() 中输出匹配结果的流程
     #[test]
-    fn print() {
-        // 1 创建 Standard Printer
-        let out = termcolor::StandardStream::stdout(ColorChoice::Auto);
+    fn context_lines() {
+        let mut searcher = SearcherBuilder::new().line_number(true).before_context(1).build();
         let mut builder = StandardBuilder::new();
-        builder.max_columns(Option::Some(4096))
-            .trim_ascii(true);
-        let mut standard = builder.build(out);
-
-        // 2 假设经过Matcher匹配到的行和匹配的字符串
-        let bytes = "software to the public domain. We make this dedication for the benefit".as_bytes();
-        let binding = vec!(Match::new(25, 29));
-        let matches = binding.as_slice();
-        // let mut line = Match::new(0, bytes.len());
-
-        // 3 输出
-        let sink = standard.sink();
-        let standard_impl = StandardImpl::new(&sink);
-        standard_impl.write_colored_line(matches, bytes).unwrap();
-    }
-}
\ No newline at end of file
+        builder.heading(false).separator_field_context(b"~~".to_vec());
+        let got = print(&builder, &mut searcher, "clew|success", SHERLOCK, Some("s"));
+        assert_eq!(
+            "s~~1~~For the Doctor Watsons of this world, as opposed to the Sherlock\n\
+             s:2:Holmeses, success in the province of detective work must always\n\
+             s~~3~~be, to a very large extent, the result of luck. Sherlock Holmes\n\
+             s:4:can extract a clew from a wisp of straw or a flake of cigar ash;\n",
+            got,
+        );
+        let got = print(&StandardBuilder::new(), &mut searcher, "^For|^can", SHERLOCK, None);
+        assert_eq!(
+            "1:For the Doctor Watsons of this world, as opposed to the Sherlock\n\
+             --\n\
+             3-be, to a very large extent, the result of luck. Sherlock Holmes\n\
+             4:can extract a clew from a wisp of straw or a flake of cigar ash;\n",
+            got,
+        );
+    }
+
+    #[test]
+    fn pretty() {
+        let haystack: String = (1..=12).map(|i| format!("line {i}\n")).collect();
+        let matcher = RegexMatcherBuilder::new().build("line (3|1[12])|2$").unwrap();
+        let mut searcher = SearcherBuilder::new().line_number(true).after_context(1).build();
+        let mut standard = StandardBuilder::new().pretty(true).build(NoColor::new(vec![]));
+        for path in ["a", "b"] {
+            let mut sink = standard.sink_with_path(&matcher, Path::new(path));
+            searcher.search_slice(&matcher, haystack.as_bytes(), &mut sink).unwrap();
+        }
+        let mut sink = standard.sink_with_path(&matcher, Path::new("c"));
+        searcher.search_slice(&matcher, "line 3\n".as_bytes(), &mut sink).unwrap();
+        let got = String::from_utf8(standard.get_mut().get_ref().clone()).unwrap();
+        // 行号按文件中最大的行号右对齐，每个文件单独计算宽度
+        let file = " 2:line 2\n 3:line 3\n 4-line 4\n--\n11:line 11\n12:line 12\n";
+        assert_eq!(format!("a (4 matches)\n{file}\nb (4 matches)\n{file}\nc (1 match)\n1:line 3\n"), got);
+    }
+
+    #[test]
+    fn max_columns_preview() {
+        let mut searcher = SearcherBuilder::new().line_number(false).build();
+        let mut builder = StandardBuilder::new();
+        builder.max_columns(Some(10));
+        let got = print(&builder, &mut searcher, "a", "short\nxx a 中文 a a a\n", None);
+        assert_eq!("[Omitted long line with 4 matches]\n", got);
+        builder.max_columns_preview(true);
+        let got = print(&builder, &mut searcher, "a", "short\nxx a 中文 a a a\n", None);
+        assert_eq!("xx a 中 [... 3 more matches]\n", got);
+    }
+
+    /// 去掉行首空白后紧跟着非法 UTF-8 的后续字节时，预览的截断位置不能退到行内容开头之前
+    #[test]
+    fn max_columns_preview_invalid_utf8() {
+        let matcher = RegexMatcherBuilder::new().build("abc").unwrap();
+        let mut searcher = SearcherBuilder::new().line_number(false).build();
+        let mut builder = StandardBuilder::new();
+        builder.max_columns(Some(1)).max_columns_preview(true).trim_ascii(true);
+        let mut standard = builder.build(NoColor::new(vec![]));
+        let haystack = b"  \x80\x80\x80abc\n\xE4\xB8\xADabc\n";
+        searcher.search_slice(&matcher, &haystack[..], standard.sink(&matcher)).unwrap();
+        let got = standard.get_mut().get_ref().clone();
+        assert_eq!(&b" [... 1 more match]\n [... 1 more match]\n"[..], &got[..]);
+    }
+}
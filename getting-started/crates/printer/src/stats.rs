@@ -1,13 +1,23 @@
+use std::ops::{Add, AddAssign};
+use std::time::Duration;
+
 /// 搜索过程中的一些统计数据
+/// 每次搜索（即每个文件）由 Printer 的 Sink 单独统计，多个搜索（包括多线程搜索）的统计结果可以通过 `+` `+=` 合并
 #[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct Stats {
-    // searches: u64,
-    // searches_with_match: u64,
-    // bytes_searched: u64,
-    // bytes_printed: u64,
+    /// 搜索耗费的总时间
+    elapsed: Duration,
+    /// 执行的搜索次数
+    searches: u64,
+    /// 至少有一个匹配的搜索次数
+    searches_with_match: u64,
+    /// 搜索过的总字节数
+    bytes_searched: u64,
+    /// 输出的总字节数
+    bytes_printed: u64,
     /// 搜索过程中匹配的行数
     matched_lines: u64,
-    /// 搜索过程中匹配行的总字节数
+    /// 搜索过程中匹配的次数，一行中可能有多个匹配
     matches: u64,
 }
 
@@ -16,6 +26,54 @@ impl Stats {
         Stats::default()
     }
 
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    pub fn searches(&self) -> u64 {
+        self.searches
+    }
+
+    pub fn searches_with_match(&self) -> u64 {
+        self.searches_with_match
+    }
+
+    pub fn bytes_searched(&self) -> u64 {
+        self.bytes_searched
+    }
+
+    pub fn bytes_printed(&self) -> u64 {
+        self.bytes_printed
+    }
+
+    pub fn matched_lines(&self) -> u64 {
+        self.matched_lines
+    }
+
+    pub fn matches(&self) -> u64 {
+        self.matches
+    }
+
+    pub fn add_elapsed(&mut self, duration: Duration) {
+        self.elapsed += duration;
+    }
+
+    pub fn add_searches(&mut self, n: u64) {
+        self.searches += n;
+    }
+
+    pub fn add_searches_with_match(&mut self, n: u64) {
+        self.searches_with_match += n;
+    }
+
+    pub fn add_bytes_searched(&mut self, n: u64) {
+        self.bytes_searched += n;
+    }
+
+    pub fn add_bytes_printed(&mut self, n: u64) {
+        self.bytes_printed += n;
+    }
+
     pub fn add_matched_lines(&mut self, n: u64) {
         self.matched_lines += n;
     }
@@ -23,4 +81,84 @@ impl Stats {
     pub fn add_matches(&mut self, n: u64) {
         self.matches += n;
     }
-}
\ No newline at end of file
+}
+
+impl Add for Stats {
+    type Output = Stats;
+
+    fn add(self, rhs: Stats) -> Stats {
+        self + &rhs
+    }
+}
+
+impl<'a> Add<&'a Stats> for Stats {
+    type Output = Stats;
+
+    fn add(self, rhs: &'a Stats) -> Stats {
+        Stats {
+            elapsed: self.elapsed + rhs.elapsed,
+            searches: self.searches + rhs.searches,
+            searches_with_match: self.searches_with_match + rhs.searches_with_match,
+            bytes_searched: self.bytes_searched + rhs.bytes_searched,
+            bytes_printed: self.bytes_printed + rhs.bytes_printed,
+            matched_lines: self.matched_lines + rhs.matched_lines,
+            matches: self.matches + rhs.matches,
+        }
+    }
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, rhs: Stats) {
+        *self += &rhs;
+    }
+}
+
+impl<'a> AddAssign<&'a Stats> for Stats {
+    fn add_assign(&mut self, rhs: &'a Stats) {
+        self.elapsed += rhs.elapsed;
+        self.searches += rhs.searches;
+        self.searches_with_match += rhs.searches_with_match;
+        self.bytes_searched += rhs.bytes_searched;
+        self.bytes_printed += rhs.bytes_printed;
+        self.matched_lines += rhs.matched_lines;
+        self.matches += rhs.matches;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use crate::stats::Stats;
+
+    /// 模拟两个线程各自统计后合并
+    #[test]
+    fn merge() {
+        let mut a = Stats::new();
+        a.add_searches(1);
+        a.add_searches_with_match(1);
+        a.add_bytes_searched(100);
+        a.add_matched_lines(2);
+        a.add_matches(3);
+        a.add_elapsed(Duration::from_millis(5));
+
+        let mut b = Stats::new();
+        b.add_searches(2);
+        b.add_bytes_searched(50);
+        b.add_bytes_printed(10);
+        b.add_elapsed(Duration::from_millis(7));
+
+        let sum = a.clone() + &b;
+        assert_eq!(3, sum.searches());
+        assert_eq!(1, sum.searches_with_match());
+        assert_eq!(150, sum.bytes_searched());
+        assert_eq!(10, sum.bytes_printed());
+        assert_eq!(2, sum.matched_lines());
+        assert_eq!(3, sum.matches());
+        assert_eq!(Duration::from_millis(12), sum.elapsed());
+
+        let mut total = Stats::new();
+        total += a;
+        total += b;
+        assert_eq!(sum, total);
+    }
+}
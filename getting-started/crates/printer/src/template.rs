@@ -0,0 +1,671 @@
+//! 按用户模板输出匹配结果的 Printer
+//! 每个匹配输出一行，比如模板 `{path}:{line}:{col}: {match}` 输出 `src/main.rs:3:7: foo`
+//! 变量后面可以跟上 `:` 和逗号分隔的指令，比如 `{line:>4}` 右对齐到 4 个字符，`{match:fg=red,bold}` 设置颜色，
+//! `{text:escape}` 把不可打印的字节转义输出
+
+use std::fmt;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Instant;
+use termcolor::{Color, ColorSpec, WriteColor};
+use grep_matcher::{Match, Matcher};
+use grep_searcher::{Searcher, Sink, SinkFinish, SinkMatch};
+use crate::counter::CounterWriter;
+use crate::stats::Stats;
+use crate::util::{DecimalFormatter, find_iter_at_in_context, trim_line_terminator};
+
+/// 没有设置模板时使用的默认模板
+const DEFAULT_TEMPLATE: &str = "{path}:{line}:{col}: {match}";
+
+/// 模板中可用的变量名，`column` 是 `col` 的别名
+const VARIABLES: &[&str] = &["path", "line", "col", "byte", "match", "text"];
+
+/// 输出模板，由字面量和带指令的变量组成
+/// 字面量中 `{{` `}}` 表示花括号本身，还支持 `\t` `\n` `\r` `\0` `\\` `\xHH` 转义
+#[derive(Clone, Debug)]
+pub struct TemplateFormat {
+    parts: Vec<Part>,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(Vec<u8>),
+    Var(Var),
+}
+
+/// 模板中的一个变量及其指令
+#[derive(Clone, Debug)]
+struct Var {
+    kind: VarKind,
+    /// 对齐宽度，按字符数计算
+    align: Option<Align>,
+    /// 值的输出颜色，为空时不设置颜色
+    color: ColorSpec,
+    /// 是否转义不可打印的字节
+    escape: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum VarKind {
+    /// 文件路径，没有路径时为空
+    Path,
+    /// 行号，没有行号时为空
+    Line,
+    /// 匹配在行中的列号，从 1 开始
+    Column,
+    /// 匹配在整个输入中的字节偏移，从 0 开始
+    Byte,
+    /// 匹配的内容
+    Match,
+    /// 匹配所在的整行，不含行终止符
+    Text,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Align {
+    /// `<N` 左对齐，在右边补空格
+    Left(usize),
+    /// `>N` 右对齐，在左边补空格
+    Right(usize),
+}
+
+/// 输出一个匹配时模板变量的取值
+struct Record<'a> {
+    path: Option<&'a Path>,
+    line_number: Option<u64>,
+    /// 匹配在行中的范围
+    mat: Match,
+    /// 行首在整个输入中的字节偏移
+    absolute_byte_offset: u64,
+    /// 不含行终止符的行内容
+    line: &'a [u8],
+}
+
+impl Default for TemplateFormat {
+    fn default() -> TemplateFormat {
+        DEFAULT_TEMPLATE.parse().expect("default template is valid")
+    }
+}
+
+impl TemplateFormat {
+    /// 模板中是否用到了行号，用到时搜索需要计算行号
+    pub fn needs_line_number(&self) -> bool {
+        self.parts.iter().any(|part| matches!(part, Part::Var(var) if var.kind == VarKind::Line))
+    }
+
+    /// 按模板输出一个匹配，不含行终止符
+    fn write<W: WriteColor>(&self, wtr: &mut W, record: &Record<'_>) -> io::Result<()> {
+        for part in self.parts.iter() {
+            match *part {
+                Part::Text(ref text) => wtr.write_all(text)?,
+                Part::Var(ref var) => var.write(wtr, record)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// 追加字面量，和前一个字面量合并
+    fn push_text(&mut self, text: &[u8]) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(Part::Text(prev)) = self.parts.last_mut() {
+            prev.extend_from_slice(text);
+        } else {
+            self.parts.push(Part::Text(text.to_vec()));
+        }
+    }
+}
+
+impl Var {
+    /// 解析 `{` `}` 之间的内容，比如 `line:>4,fg=green`
+    fn parse(s: &str) -> Result<Var, TemplateFormatError> {
+        let (name, directives) = match s.split_once(':') {
+            Some((name, directives)) => (name, Some(directives)),
+            None => (s, None),
+        };
+        let kind = match name.trim() {
+            "path" => VarKind::Path,
+            "line" => VarKind::Line,
+            "col" | "column" => VarKind::Column,
+            "byte" => VarKind::Byte,
+            "match" => VarKind::Match,
+            "text" => VarKind::Text,
+            _ => {
+                let kind = TemplateFormatErrorKind::InvalidVariable(name.to_string());
+                return Err(TemplateFormatError::new(kind));
+            }
+        };
+        let mut var = Var { kind, align: None, color: ColorSpec::new(), escape: false };
+        for directive in directives.into_iter().flat_map(|d| d.split(',')) {
+            var.apply(name.trim(), directive.trim())?;
+        }
+        Ok(var)
+    }
+
+    /// 应用一条指令
+    fn apply(&mut self, name: &str, directive: &str) -> Result<(), TemplateFormatError> {
+        let invalid = || {
+            let kind = TemplateFormatErrorKind::InvalidDirective {
+                variable: name.to_string(),
+                directive: directive.to_string(),
+            };
+            TemplateFormatError::new(kind)
+        };
+        if let Some(width) = directive.strip_prefix('<') {
+            self.align = Some(Align::Left(width.parse().map_err(|_| invalid())?));
+        } else if let Some(width) = directive.strip_prefix('>') {
+            self.align = Some(Align::Right(width.parse().map_err(|_| invalid())?));
+        } else if let Some(color) = directive.strip_prefix("fg=") {
+            self.color.set_fg(Some(parse_color(color)?));
+        } else if let Some(color) = directive.strip_prefix("bg=") {
+            self.color.set_bg(Some(parse_color(color)?));
+        } else {
+            match directive {
+                "escape" => self.escape = true,
+                "bold" => { self.color.set_bold(true); }
+                "dimmed" => { self.color.set_dimmed(true); }
+                "intense" => { self.color.set_intense(true); }
+                "italic" => { self.color.set_italic(true); }
+                "underline" => { self.color.set_underline(true); }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(())
+    }
+
+    /// 输出变量的值，补齐的空格不带颜色
+    fn write<W: WriteColor>(&self, wtr: &mut W, record: &Record<'_>) -> io::Result<()> {
+        let number;
+        let value: &[u8] = match self.kind {
+            VarKind::Path => record.path.map_or(&[], |path| path.as_os_str().as_bytes()),
+            VarKind::Line => match record.line_number {
+                Some(n) => {
+                    number = DecimalFormatter::new(n);
+                    number.as_bytes()
+                }
+                None => &[],
+            },
+            VarKind::Column => {
+                number = DecimalFormatter::new(record.mat.start() as u64 + 1);
+                number.as_bytes()
+            }
+            VarKind::Byte => {
+                number = DecimalFormatter::new(record.absolute_byte_offset + record.mat.start() as u64);
+                number.as_bytes()
+            }
+            VarKind::Match => &record.line[record.mat],
+            VarKind::Text => record.line,
+        };
+        let escaped;
+        let value = if self.escape {
+            escaped = escape(value);
+            &escaped
+        } else {
+            value
+        };
+
+        // 按 UTF-8 字符数对齐
+        let chars = value.iter().filter(|&&b| b & 0xC0 != 0x80).count();
+        let (left, right) = match self.align {
+            None => (0, 0),
+            Some(Align::Left(width)) => (0, width.saturating_sub(chars)),
+            Some(Align::Right(width)) => (width.saturating_sub(chars), 0),
+        };
+        wtr.write_all(&b" ".repeat(left))?;
+        if self.color.is_none() || !wtr.supports_color() {
+            wtr.write_all(value)?;
+        } else {
+            wtr.set_color(&self.color)?;
+            wtr.write_all(value)?;
+            wtr.reset()?;
+        }
+        wtr.write_all(&b" ".repeat(right))
+    }
+}
+
+/// 解析颜色名（比如 `red`）或 ANSI 256 色号，指令以逗号分隔，所以不支持 `r,g,b` 形式
+fn parse_color(s: &str) -> Result<Color, TemplateFormatError> {
+    Color::from_str(s)
+        .map_err(|_| TemplateFormatError::new(TemplateFormatErrorKind::InvalidColor(s.to_string())))
+}
+
+/// 转义不可打印的字节：`\t` `\n` `\r` `\\` 使用对应的转义序列，其他控制字符和非法的 UTF-8 字节输出为 `\xHH`
+fn escape(bytes: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        for ch in chunk.valid().chars() {
+            match ch {
+                '\t' => buf.extend_from_slice(b"\\t"),
+                '\n' => buf.extend_from_slice(b"\\n"),
+                '\r' => buf.extend_from_slice(b"\\r"),
+                '\\' => buf.extend_from_slice(b"\\\\"),
+                c if c.is_ascii_control() => write!(buf, "\\x{:02X}", c as u8).unwrap(),
+                c => buf.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        for &b in chunk.invalid() {
+            write!(buf, "\\x{b:02X}").unwrap();
+        }
+    }
+    buf
+}
+
+impl FromStr for TemplateFormat {
+    type Err = TemplateFormatError;
+
+    fn from_str(s: &str) -> Result<TemplateFormat, TemplateFormatError> {
+        if s.is_empty() {
+            return Err(TemplateFormatError::new(TemplateFormatErrorKind::Empty));
+        }
+        let mut format = TemplateFormat { parts: vec![] };
+        let mut text = vec![];
+        let mut rest = s;
+        while let Some(ch) = rest.chars().next() {
+            match ch {
+                '{' if rest.starts_with("{{") => {
+                    text.push(b'{');
+                    rest = &rest[2..];
+                }
+                '}' if rest.starts_with("}}") => {
+                    text.push(b'}');
+                    rest = &rest[2..];
+                }
+                '{' => {
+                    let Some(end) = rest.find('}') else {
+                        return Err(TemplateFormatError::new(TemplateFormatErrorKind::UnclosedVariable));
+                    };
+                    format.push_text(&text);
+                    text.clear();
+                    format.parts.push(Part::Var(Var::parse(&rest[1..end])?));
+                    rest = &rest[end + 1..];
+                }
+                '}' => {
+                    return Err(TemplateFormatError::new(TemplateFormatErrorKind::UnopenedVariable));
+                }
+                '\\' => {
+                    let (byte, len) = unescape(rest)?;
+                    text.push(byte);
+                    rest = &rest[len..];
+                }
+                _ => {
+                    text.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes());
+                    rest = &rest[ch.len_utf8()..];
+                }
+            }
+        }
+        format.push_text(&text);
+        Ok(format)
+    }
+}
+
+/// 解析 s 开头的转义序列，返回转义后的字节和转义序列的长度
+fn unescape(s: &str) -> Result<(u8, usize), TemplateFormatError> {
+    let invalid = |len: usize| {
+        let seq = s.chars().take(len).collect();
+        TemplateFormatError::new(TemplateFormatErrorKind::InvalidEscape(seq))
+    };
+    let bytes = s.as_bytes();
+    match bytes.get(1) {
+        Some(b't') => Ok((b'\t', 2)),
+        Some(b'n') => Ok((b'\n', 2)),
+        Some(b'r') => Ok((b'\r', 2)),
+        Some(b'0') => Ok((b'\0', 2)),
+        Some(b'\\') => Ok((b'\\', 2)),
+        Some(b'x') => {
+            let hex = s.get(2..4).ok_or_else(|| invalid(4))?;
+            let byte = u8::from_str_radix(hex, 16).map_err(|_| invalid(4))?;
+            Ok((byte, 4))
+        }
+        _ => Err(invalid(2)),
+    }
+}
+
+/// 解析输出模板的错误
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TemplateFormatError {
+    kind: TemplateFormatErrorKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum TemplateFormatErrorKind {
+    /// 模板为空
+    Empty,
+    /// 未知的变量名
+    InvalidVariable(String),
+    /// 未知的指令
+    InvalidDirective { variable: String, directive: String },
+    /// 无法识别的颜色
+    InvalidColor(String),
+    /// 未知的转义序列
+    InvalidEscape(String),
+    /// `{` 没有对应的 `}`
+    UnclosedVariable,
+    /// `}` 没有对应的 `{`
+    UnopenedVariable,
+}
+
+impl TemplateFormatError {
+    fn new(kind: TemplateFormatErrorKind) -> TemplateFormatError {
+        TemplateFormatError { kind }
+    }
+}
+
+impl std::error::Error for TemplateFormatError {}
+
+impl fmt::Display for TemplateFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            TemplateFormatErrorKind::Empty => write!(f, "format template must not be empty"),
+            TemplateFormatErrorKind::InvalidVariable(ref name) => {
+                let names: Vec<String> = VARIABLES.iter().map(|name| format!("{{{name}}}")).collect();
+                write!(
+                    f,
+                    "invalid format template variable '{{{name}}}', expected one of {}",
+                    names.join(", "),
+                )
+            }
+            TemplateFormatErrorKind::InvalidDirective { ref variable, ref directive } => {
+                write!(
+                    f,
+                    "invalid directive '{directive}' for variable '{{{variable}}}', expected \
+                     <N, >N, escape, bold, dimmed, intense, italic, underline, fg=COLOR or bg=COLOR",
+                )
+            }
+            TemplateFormatErrorKind::InvalidColor(ref color) => {
+                write!(
+                    f,
+                    "invalid color '{color}' in format template, expected a color name \
+                     (like red) or an ANSI 256 color number",
+                )
+            }
+            TemplateFormatErrorKind::InvalidEscape(ref seq) => {
+                write!(
+                    f,
+                    "invalid escape sequence '{seq}' in format template, expected one of \
+                     \\t, \\n, \\r, \\0, \\\\ or \\xHH",
+                )
+            }
+            TemplateFormatErrorKind::UnclosedVariable => {
+                write!(f, "unclosed variable in format template, missing '}}'")
+            }
+            TemplateFormatErrorKind::UnopenedVariable => {
+                write!(f, "unopened variable in format template, missing '{{' (use '}}}}' for a literal '}}')")
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Config {
+    /// 输出模板
+    format: TemplateFormat,
+    /// 模板中的 `{path}` 是否输出文件路径，默认 true
+    path: bool,
+    /// 是否统计搜索数据
+    stats: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct TemplateBuilder {
+    config: Config,
+}
+
+impl Default for TemplateBuilder {
+    fn default() -> TemplateBuilder {
+        TemplateBuilder::new()
+    }
+}
+
+impl TemplateBuilder {
+    pub fn new() -> TemplateBuilder {
+        TemplateBuilder { config: Config { path: true, ..Config::default() } }
+    }
+
+    pub fn build<W: WriteColor>(&self, wtr: W) -> Template<W> {
+        Template {
+            config: self.config.clone(),
+            wtr: CounterWriter::new(wtr),
+            matches: vec![],
+        }
+    }
+
+    /// 设置输出模板，默认是 `{path}:{line}:{col}: {match}`
+    pub fn format(&mut self, format: TemplateFormat) -> &mut TemplateBuilder {
+        self.config.format = format;
+        self
+    }
+
+    /// 模板中的 `{path}` 是否输出文件路径，默认 true，设置为 false 时输出为空
+    pub fn path(&mut self, yes: bool) -> &mut TemplateBuilder {
+        self.config.path = yes;
+        self
+    }
+
+    /// 是否统计搜索数据，默认不统计
+    pub fn stats(&mut self, yes: bool) -> &mut TemplateBuilder {
+        self.config.stats = yes;
+        self
+    }
+}
+
+/// 按模板输出的 Printer，每个匹配输出一行，不输出上下文行
+#[derive(Clone, Debug)]
+pub struct Template<W> {
+    config: Config,
+    wtr: CounterWriter<W>,
+    /// 当前匹配行中所有匹配的范围，相对于行首
+    matches: Vec<Match>,
+}
+
+impl<W> Template<W> {
+    /// 输出目的地的可变引用
+    pub fn get_mut(&mut self) -> &mut W {
+        self.wtr.get_mut()
+    }
+}
+
+impl<W: WriteColor> Template<W> {
+    /// 输出时 `{path}` 为空，比如搜索标准输入
+    pub fn sink<'s, M: Matcher>(&'s mut self, matcher: M) -> TemplateSink<'static, 's, M, W> {
+        let stats = if self.config.stats { Some(Stats::new()) } else { None };
+        TemplateSink {
+            matcher,
+            template: self,
+            path: None,
+            start_time: Instant::now(),
+            match_count: 0,
+            stats,
+        }
+    }
+
+    /// 输出时 `{path}` 为 path
+    pub fn sink_with_path<'p, 's, M: Matcher>(
+        &'s mut self,
+        matcher: M,
+        path: &'p Path,
+    ) -> TemplateSink<'p, 's, M, W> {
+        if !self.config.path {
+            return self.sink(matcher);
+        }
+        let mut sink = self.sink(matcher);
+        sink.path = Some(path);
+        sink
+    }
+}
+
+#[derive(Debug)]
+pub struct TemplateSink<'p, 's, M: Matcher, W> {
+    matcher: M,
+    template: &'s mut Template<W>,
+    path: Option<&'p Path>,
+    /// Sink 创建的时间，用于统计搜索耗时
+    start_time: Instant,
+    /// 匹配的行计数
+    match_count: u64,
+    /// 统计记录，通过 TemplateBuilder::stats() 开启
+    stats: Option<Stats>,
+}
+
+impl<M: Matcher, W: WriteColor> TemplateSink<'_, '_, M, W> {
+    /// 是否有匹配的行
+    pub fn has_match(&self) -> bool {
+        self.match_count > 0
+    }
+
+    /// 本次搜索的统计数据，只有开启了统计才会返回
+    pub fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+}
+
+impl<M: Matcher, W: WriteColor> Sink for TemplateSink<'_, '_, M, W> {
+    type Error = io::Error;
+
+    /// 找出匹配行中的所有匹配，每个匹配按模板输出一行
+    fn matched(
+        &mut self,
+        searcher: &Searcher,
+        mat: &SinkMatch<'_>,
+    ) -> Result<bool, Self::Error> {
+        self.match_count += 1;
+        let range = mat.bytes_range_in_buffer();
+        let matches = &mut self.template.matches;
+        matches.clear();
+        find_iter_at_in_context(searcher, &self.matcher, mat.buffer(), range.clone(), |m, _| {
+            matches.push(Match::new(m.start() - range.start, m.end() - range.start));
+            true
+        })?;
+
+        let bytes = mat.bytes();
+        let mut line = Match::new(0, bytes.len());
+        trim_line_terminator(searcher, bytes, &mut line);
+        // 匹配行中找不到具体的匹配（比如多行模式）时把整行当作一个匹配输出
+        if matches.is_empty() {
+            matches.push(line);
+        }
+        if let Some(ref mut stats) = self.stats {
+            stats.add_matches(matches.len() as u64);
+            stats.add_matched_lines(1);
+        }
+        let line_term = searcher.line_terminator().as_byte();
+        for &m in self.template.matches.iter() {
+            let record = Record {
+                path: self.path,
+                line_number: mat.line_number(),
+                mat: m.with_end(m.end().min(line.end())),
+                absolute_byte_offset: mat.absolute_byte_offset(),
+                line: &bytes[line],
+            };
+            self.template.config.format.write(&mut self.template.wtr, &record)?;
+            self.template.wtr.write_all(&[line_term])?;
+        }
+        Ok(true)
+    }
+
+    /// 搜索开始，重置本次搜索的匹配计数、计时和输出计数
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, Self::Error> {
+        self.template.wtr.reset_count();
+        self.start_time = Instant::now();
+        self.match_count = 0;
+        Ok(true)
+    }
+
+    /// 搜索结束，汇总本次搜索的统计数据
+    fn finish(
+        &mut self,
+        _searcher: &Searcher,
+        finish: &SinkFinish,
+    ) -> Result<(), Self::Error> {
+        if let Some(ref mut stats) = self.stats {
+            stats.add_elapsed(self.start_time.elapsed());
+            stats.add_searches(1);
+            if self.match_count > 0 {
+                stats.add_searches_with_match(1);
+            }
+            stats.add_bytes_searched(finish.byte_count());
+            stats.add_bytes_printed(self.template.wtr.count());
+        }
+        self.template.wtr.reset_count();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_regex::RegexMatcherBuilder;
+    use grep_searcher::SearcherBuilder;
+    use termcolor::{Ansi, NoColor};
+    use super::*;
+
+    fn print<W: WriteColor>(format: &str, wtr: W, path: Option<&str>, haystack: &str) -> Template<W> {
+        let matcher = RegexMatcherBuilder::new().build(r"b\w+").unwrap();
+        let mut searcher = SearcherBuilder::new().line_number(true).build();
+        let mut template = TemplateBuilder::new().format(format.parse().unwrap()).build(wtr);
+        match path {
+            Some(path) => {
+                let mut sink = template.sink_with_path(&matcher, Path::new(path));
+                searcher.search_slice(&matcher, haystack.as_bytes(), &mut sink).unwrap();
+            }
+            None => {
+                let mut sink = template.sink(&matcher);
+                searcher.search_slice(&matcher, haystack.as_bytes(), &mut sink).unwrap();
+            }
+        }
+        template
+    }
+
+    fn render(format: &str, path: Option<&str>, haystack: &str) -> String {
+        String::from_utf8(print(format, NoColor::new(vec![]), path, haystack).get_mut().get_ref().clone()).unwrap()
+    }
+
+    #[test]
+    fn variables_and_padding() {
+        let haystack = "foo\na bar baz\n";
+        assert_eq!(
+            "x:2:3: bar\nx:2:7: baz\n",
+            render("{path}:{line}:{col}: {match}", Some("x"), haystack),
+        );
+        assert_eq!(
+            "[ 2|bar  |a bar baz|6]\n[ 2|baz  |a bar baz|10]\n",
+            render("[{line:>2}|{match:<5}|{text}|{byte}]", None, haystack),
+        );
+        assert_eq!(":{bar}\\\t\n:{baz}\\\t\n", render("{path}:{{{match}}}\\\\\\t", None, haystack));
+        assert_eq!("bx\\tc\\x01é\\\\\n", render("{text:escape}", None, "bx\tc\x01é\\\n"));
+        assert_eq!(b"a\\xFFb".to_vec(), escape(b"a\xFFb"));
+        assert!(TemplateFormat::default().needs_line_number());
+        assert!(!"{path}".parse::<TemplateFormat>().unwrap().needs_line_number());
+    }
+
+    #[test]
+    fn colors() {
+        let mut got = print("{match:fg=red,bold}|{line:>3,fg=green}", Ansi::new(vec![]), None, "bar\n");
+        assert_eq!(
+            "\x1b[0m\x1b[1m\x1b[31mbar\x1b[0m|  \x1b[0m\x1b[32m1\x1b[0m\n",
+            String::from_utf8(got.get_mut().get_ref().clone()).unwrap(),
+        );
+        // 不支持颜色时不输出转义序列
+        assert_eq!("bar\n", render("{match:fg=red}", None, "bar\n"));
+    }
+
+    #[test]
+    fn invalid_templates() {
+        let err = |s: &str| s.parse::<TemplateFormat>().unwrap_err().kind;
+        assert_eq!(TemplateFormatErrorKind::Empty, err(""));
+        assert_eq!(TemplateFormatErrorKind::UnclosedVariable, err("{path"));
+        assert_eq!(TemplateFormatErrorKind::UnopenedVariable, err("path}"));
+        assert_eq!(TemplateFormatErrorKind::InvalidVariable("file".to_string()), err("{file}"));
+        assert_eq!(
+            TemplateFormatErrorKind::InvalidDirective {
+                variable: "line".to_string(),
+                directive: "^3".to_string(),
+            },
+            err("{line:^3}"),
+        );
+        assert_eq!(TemplateFormatErrorKind::InvalidColor("reddish".to_string()), err("{match:fg=reddish}"));
+        assert_eq!(TemplateFormatErrorKind::InvalidEscape("\\q".to_string()), err("\\q"));
+        assert_eq!(TemplateFormatErrorKind::InvalidEscape("\\xZZ".to_string()), err("\\xZZ"));
+    }
+}
@@ -3,8 +3,8 @@ use std::io;
 use std::path::Path;
 use bstr::ByteVec;
 use termcolor::WriteColor;
-use grep_matcher::{LineTerminator, Match, Matcher};
-use grep_searcher::{Searcher, SinkError, SinkMatch};
+use grep_matcher::{LineTerminator, Match, Matcher, PatternID};
+use grep_searcher::{Searcher, SinkContext, SinkContextKind, SinkError, SinkMatch};
 
 #[derive(Debug)]
 pub(crate) struct Sunk<'a> {
@@ -14,12 +14,15 @@ pub(crate) struct Sunk<'a> {
     absolute_byte_offset: u64,
     /// 缓冲中匹配行的数量
     line_number: Option<u64>,
-    // context_kind: Option<&'a SinkContextKind>,
+    /// 上下文行的类型，匹配行为 None
+    context_kind: Option<&'a SinkContextKind>,
     /// 这个字段 ripgrep 用于记录通过 Replacer 替换之后匹配行在缓冲中的范围，缓冲中可能有多个匹配行所以是个数组
     /// 这里还保持和 original_matches 一致即可
     matches: &'a [Match],
     /// 原始匹配行在缓冲中的范围，缓冲中可能有多个匹配行所以是个数组
     original_matches: &'a [Match],
+    /// matches 中每个匹配切片所属的模式序号，和 matches 一一对应
+    pattern_ids: &'a [PatternID],
 }
 
 impl<'a> Sunk<'a> {
@@ -29,9 +32,10 @@ impl<'a> Sunk<'a> {
             bytes: &[],
             absolute_byte_offset: 0,
             line_number: None,
-            // context_kind: None,
+            context_kind: None,
             matches: &[],
             original_matches: &[],
+            pattern_ids: &[],
         }
     }
 
@@ -39,6 +43,7 @@ impl<'a> Sunk<'a> {
     pub(crate) fn from_sink_match(
         sunk: &'a SinkMatch<'a>,
         original_matches: &'a [Match],
+        pattern_ids: &'a [PatternID],
         // replacement: Option<(&'a [u8], &'a [Match])>,
     ) -> Sunk<'a> {
         // let (bytes, matches) =
@@ -47,12 +52,52 @@ impl<'a> Sunk<'a> {
             bytes: sunk.bytes(),
             absolute_byte_offset: sunk.absolute_byte_offset(),
             line_number: sunk.line_number(),
-            // context_kind: None,
+            context_kind: None,
             matches: original_matches,
             original_matches,
+            pattern_ids,
         }
     }
 
+    #[inline]
+    pub(crate) fn from_sink_context(sunk: &'a SinkContext<'a>) -> Sunk<'a> {
+        Sunk {
+            bytes: sunk.bytes(),
+            absolute_byte_offset: sunk.absolute_byte_offset(),
+            line_number: sunk.line_number(),
+            context_kind: Some(sunk.kind()),
+            matches: &[],
+            original_matches: &[],
+            pattern_ids: &[],
+        }
+    }
+
+    /// 由缓存的行数据创建，比如 pretty 模式下一个文件搜索结束后回放缓存的结果
+    #[inline]
+    pub(crate) fn new(
+        bytes: &'a [u8],
+        absolute_byte_offset: u64,
+        line_number: Option<u64>,
+        context_kind: Option<&'a SinkContextKind>,
+        matches: &'a [Match],
+        pattern_ids: &'a [PatternID],
+    ) -> Sunk<'a> {
+        Sunk {
+            bytes,
+            absolute_byte_offset,
+            line_number,
+            context_kind,
+            matches,
+            original_matches: matches,
+            pattern_ids,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn context_kind(&self) -> Option<&'a SinkContextKind> {
+        self.context_kind
+    }
+
     #[inline]
     pub(crate) fn bytes(&self) -> &'a [u8] {
         self.bytes
@@ -63,6 +108,12 @@ impl<'a> Sunk<'a> {
         self.matches
     }
 
+    /// 第 i 个匹配切片所属的模式序号
+    #[inline]
+    pub(crate) fn pattern_id(&self, i: usize) -> PatternID {
+        self.pattern_ids.get(i).copied().unwrap_or(PatternID::ZERO)
+    }
+
     #[inline]
     pub(crate) fn absolute_byte_offset(&self) -> u64 {
         self.absolute_byte_offset
@@ -170,7 +221,7 @@ pub(crate) fn trim_ascii_prefix(
 }
 
 /// 迭代查找 bytes[range] 中所有匹配的字符串交给闭包中的 matched 处理
-pub(crate) fn find_iter_at_in_context<M: Matcher, F: FnMut(Match) -> bool>(
+pub(crate) fn find_iter_at_in_context<M: Matcher, F: FnMut(Match, PatternID) -> bool>(
     searcher: &Searcher,
     matcher: M,
     mut bytes: &[u8],   //缓冲
@@ -181,15 +232,38 @@ pub(crate) fn find_iter_at_in_context<M: Matcher, F: FnMut(Match) -> bool>(
     trim_line_terminator(searcher, bytes, &mut m);
     bytes = &bytes[..m.end()];
     matcher
-        .find_iter_at(bytes, range.start, |m| {
+        .find_pattern_iter_at(bytes, range.start, |m, pid| {
             if m.start() >= range.end {
                 return false;
             }
-            matched(m)
+            matched(m, pid)
         })
         .map_err(io::Error::error_message)
 }
 
+/// 在匹配行中分别用每个启用的模式的匹配器查找，模式之间互不影响，同一位置可以有多个模式的匹配项
+/// matchers[i] 只包含第 i 个模式，报告的模式序号就是 i，filter[i] 为 false 的模式跳过
+pub(crate) fn find_iter_per_pattern<M: Matcher, F: FnMut(Match, PatternID)>(
+    searcher: &Searcher,
+    matchers: &[M],
+    filter: Option<&[bool]>,
+    bytes: &[u8],
+    range: std::ops::Range<usize>,
+    mut matched: F,
+) -> io::Result<()> {
+    for (i, matcher) in matchers.iter().enumerate() {
+        if filter.is_some_and(|filter| filter.get(i) != Some(&true)) {
+            continue;
+        }
+        let pid = PatternID::new(i);
+        find_iter_at_in_context(searcher, matcher, bytes, range.clone(), |m, _| {
+            matched(m, pid);
+            true
+        })?;
+    }
+    Ok(())
+}
+
 /// 清除行中的行终止符，从前面的逻辑看如果行中有行终止符，只可能在最后面，所以这里只需要判断最后一位是否是行终止符
 pub(crate) fn trim_line_terminator(
     searcher: &Searcher,
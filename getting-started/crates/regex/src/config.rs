@@ -1,3 +1,4 @@
+use std::cmp;
 use regex_automata::meta::Regex;
 use regex_syntax::{ast, hir};
 use regex_syntax::hir::Hir;
@@ -76,6 +77,44 @@ impl Config {
         ConfiguredHIR::new(self.clone(), patterns)
     }
 
+    /// 多行模式下 `^` `$` 使用的行终止符，没有配置时默认是 `\n`（CRLF 时也是 `\n`，`\r` 由 crlf 配置处理）
+    fn line_terminator_byte(&self) -> u8 {
+        self.line_terminator.map_or(b'\n', |line_term| line_term.as_byte())
+    }
+
+    fn parse(&self, pattern: &str) -> Result<ast::Ast, Error> {
+        let mut parser = ast::parse::ParserBuilder::new()
+            .nest_limit(self.nest_limit)
+            .octal(self.octal)
+            .ignore_whitespace(self.ignore_whitespace)
+            .build();
+        parser.parse(pattern).map_err(Error::ast)
+    }
+
+    /// Ast -> Hir，同时检查禁止的字节并从 Hir 中去掉行终止符
+    fn translate(&self, pattern: &str, ast: &ast::Ast, case_insensitive: bool) -> Result<Hir, Error> {
+        let mut hir = hir::translate::TranslatorBuilder::new()
+            .utf8(false)
+            .case_insensitive(case_insensitive)
+            .multi_line(self.multi_line)
+            .dot_matches_new_line(self.dot_matches_new_line)
+            .crlf(self.crlf)
+            .line_terminator(self.line_terminator_byte())
+            .swap_greed(self.swap_greed)
+            .unicode(self.unicode)
+            .build()
+            .translate(pattern, ast)
+            .map_err(Error::generic)?;
+        if let Some(byte) = self.ban {
+            ban::check(&hir, byte)?;
+        }
+        hir = match self.line_terminator {
+            None => hir,
+            Some(line_term) => strip_from_match(hir, line_term)?,
+        };
+        Ok(hir)
+    }
+
     fn is_case_insensitive(&self, analysis: &AstAnalysis) -> bool {
         if self.case_insensitive {
             return true;
@@ -92,6 +131,8 @@ pub(crate) struct ConfiguredHIR {
     config: Config,
     /// high-level intermediate representation, 意为高级中间表示
     hir: Hir,
+    /// 有多个模式时每个模式单独的 Hir，用于构建能区分匹配项属于哪个模式的正则，只有一个模式时为空
+    pattern_hirs: Vec<Hir>,
 }
 
 impl ConfiguredHIR {
@@ -106,7 +147,11 @@ impl ConfiguredHIR {
         let mut alts = vec![];
         for p in patterns.iter() {
             alts.push(if config.fixed_strings {
-                format!("(?:{})", regex_syntax::escape(p.as_ref())) //TODO
+                // 关闭忽略空白模式，否则固定字符串中的空白会被去掉
+                format!("(?-x:{})", regex_syntax::escape(p.as_ref())) //TODO
+            } else if config.ignore_whitespace {
+                // 忽略空白模式下模式里的 `#` 注释会一直延续到行尾，所以在闭合括号前换行，避免把括号当作注释
+                format!("(?:{}\n)", p.as_ref())
             } else {
                 format!("(?:{})", p.as_ref())
             });
@@ -114,34 +159,20 @@ impl ConfiguredHIR {
         let pattern = alts.join("|");
         // 后面的流程看不懂，因为对正则表达式引擎的工作原理和实现不清楚，不过也不是短时间就能理清的，暂时不纠结了，后面有空再看 TODO 正则表达式引擎工作原理
         // 2 Ast
-        let mut parser = ast::parse::ParserBuilder::new()
-            .nest_limit(config.nest_limit)
-            .octal(config.octal)
-            .ignore_whitespace(config.ignore_whitespace)
-            .build();
-        let ast = parser.parse(&pattern)
-            .map_err(Error::generic)?;
+        let ast = config.parse(&pattern)?;
         // 3 Hir
+        // 智能大小写是根据所有模式一起判断的，单独转换每个模式时也使用这个结果，保证和拼接后的模式行为一致
         let analysis = AstAnalysis::from_ast(&ast);
-        let mut hir = hir::translate::TranslatorBuilder::new()
-            .utf8(false)
-            .case_insensitive(config.is_case_insensitive(&analysis))
-            .multi_line(config.multi_line)
-            .dot_matches_new_line(config.dot_matches_new_line)
-            .crlf(config.crlf)
-            .swap_greed(config.swap_greed)
-            .unicode(config.unicode)
-            .build()
-            .translate(&pattern, &ast)
-            .map_err(Error::generic)?;
-        if let Some(byte) = config.ban {
-            ban::check(&hir, byte)?;
+        let case_insensitive = config.is_case_insensitive(&analysis);
+        let hir = config.translate(&pattern, &ast, case_insensitive)?;
+        let mut pattern_hirs = vec![];
+        if alts.len() > 1 {
+            for alt in alts.iter() {
+                let ast = config.parse(alt)?;
+                pattern_hirs.push(config.translate(alt, &ast, case_insensitive)?);
+            }
         }
-        hir = match config.line_terminator {
-            None => hir,
-            Some(line_term) => strip_from_match(hir, line_term)?,
-        };
-        Ok(ConfiguredHIR { config, hir })
+        Ok(ConfiguredHIR { config, hir, pattern_hirs })
     }
 
     pub(crate) fn config(&self) -> &Config {
@@ -154,8 +185,32 @@ impl ConfiguredHIR {
 
     /// Hir -> Regex
     pub(crate) fn to_regex(&self) -> Result<Regex, Error> {
-        let meta = Regex::config()
+        Regex::builder()
+            .configure(self.regex_config())
+            .build_from_hir(&self.hir)
+            .map_err(Error::regex)
+    }
+
+    /// 有多个模式时构建一个多模式的 Regex，匹配结果和 to_regex() 一致，但是可以知道匹配项是哪个模式匹配到的
+    pub(crate) fn to_pattern_regex(&self) -> Result<Option<Regex>, Error> {
+        if self.pattern_hirs.is_empty() {
+            return Ok(None);
+        }
+        Regex::builder()
+            .configure(self.regex_config())
+            .build_many_from_hir(&self.pattern_hirs)
+            .map(Some)
+            .map_err(Error::regex)
+    }
+
+    pub(crate) fn pattern_count(&self) -> usize {
+        cmp::max(1, self.pattern_hirs.len())
+    }
+
+    fn regex_config(&self) -> regex_automata::meta::Config {
+        Regex::config()
             .utf8_empty(false)
+            .line_terminator(self.config.line_terminator_byte())
             .nfa_size_limit(Some(self.config.size_limit))
             // We don't expose a knob for this because the one-pass DFA is
             // usually not a perf bottleneck for ripgrep. But we give it some
@@ -166,11 +221,7 @@ impl ConfiguredHIR {
             // building them I think.
             .dfa_size_limit(Some(1 * (1 << 20)))
             .dfa_state_limit(Some(1_000))
-            .hybrid_cache_capacity(self.config.dfa_size_limit);
-        Regex::builder()
-            .configure(meta)
-            .build_from_hir(&self.hir)
-            .map_err(Error::regex)
+            .hybrid_cache_capacity(self.config.dfa_size_limit)
     }
 
     pub(crate) fn non_matching_bytes(&self) -> ByteSet {
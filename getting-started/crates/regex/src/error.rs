@@ -10,10 +10,7 @@ impl Error {
 
     pub(crate) fn regex(err: regex_automata::meta::BuildError) -> Error {
         if let Some(size_limit) = err.size_limit() {
-            let kind = ErrorKind::Regex(format!(
-                "compiled regex exceeds size limit of {size_limit}",
-            ));
-            Error { kind }
+            Error { kind: ErrorKind::SizeLimitExceeded(size_limit) }
         } else if let Some(ref err) = err.syntax_error() {
             Error::generic(err)
         } else {
@@ -21,6 +18,16 @@ impl Error {
         }
     }
 
+    /// 解析模式时的错误，嵌套深度超过限制时单独报告
+    pub(crate) fn ast(err: regex_syntax::ast::Error) -> Error {
+        match *err.kind() {
+            regex_syntax::ast::ErrorKind::NestLimitExceeded(limit) => {
+                Error { kind: ErrorKind::NestLimitExceeded(limit) }
+            }
+            _ => Error::generic(err),
+        }
+    }
+
     pub(crate) fn generic<E: std::error::Error>(err: E) -> Error {
         Error { kind: ErrorKind::Regex(err.to_string()) }
     }
@@ -37,6 +44,10 @@ pub enum ErrorKind {
     NotAllowed(String),
     InvalidLineTerminator(u8),
     Banned(u8),
+    /// 编译后的正则超过了 RegexMatcherBuilder::size_limit 设置的大小，值是这个限制
+    SizeLimitExceeded(usize),
+    /// 模式的嵌套深度超过了 RegexMatcherBuilder::nest_limit 设置的深度，值是这个限制
+    NestLimitExceeded(u32),
 }
 
 impl std::error::Error for Error {}
@@ -57,6 +68,12 @@ impl std::fmt::Display for Error {
                     byte = [byte].as_bstr(),
                 )
             }
+            ErrorKind::SizeLimitExceeded(limit) => {
+                write!(f, "compiled regex exceeds size limit of {limit}")
+            }
+            ErrorKind::NestLimitExceeded(limit) => {
+                write!(f, "regex exceeds nest limit of {limit}")
+            }
             ErrorKind::Banned(byte) => {
                 write!(
                     f,
@@ -1,6 +1,8 @@
 /// pub use 是向外部暴露类型， use 则是使用外部的类型
 pub use crate::{
-    matcher::{RegexMatcher, RegexMatcherBuilder}
+    error::{Error, ErrorKind},
+    literals::{Explanation, LiteralStrategy},
+    matcher::{RegexCaptures, RegexMatcher, RegexMatcherBuilder}
 };
 
 mod matcher;
@@ -7,11 +7,127 @@ use {
     },
 };
 
+use grep_matcher::ByteSet;
 use crate::{config::ConfiguredHIR, error::Error};
 
+/// 构建 RegexMatcher 时对内部字面量优化做出的选择，即为什么用或者不用提取的字面量构建快速行匹配正则
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum LiteralStrategy {
+    /// 没有配置行终止符，内部字面量优化不可用
+    NoLineTerminator,
+    /// 正则引擎自身已经有字面量加速，交给正则引擎处理
+    Accelerated,
+    /// 模式是多个字面量的选择（比如 `foo|bar`），正则引擎处理得更好
+    AlternationLiteral,
+    /// 提取到的字面量可能匹配得太频繁（比如太短或者太多），反而会更慢，所以丢弃了
+    Discarded,
+    /// 使用提取到的字面量构建快速行匹配正则，先找候选行再用完整的正则确认
+    InnerLiterals,
+}
+
+impl std::fmt::Display for LiteralStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s = match *self {
+            LiteralStrategy::NoLineTerminator => "none (no line terminator is set)",
+            LiteralStrategy::Accelerated => "none (regex engine is already accelerated)",
+            LiteralStrategy::AlternationLiteral => "none (alternation of literals, deferring to regex engine)",
+            LiteralStrategy::Discarded => "none (extracted literals might be slow)",
+            LiteralStrategy::InnerLiterals => "inner literals",
+        };
+        f.write_str(s)
+    }
+}
+
+/// RegexMatcher::explain() 的结果，描述匹配器最终使用的正则、字面量优化策略和不可能匹配的字节
+/// Display 输出适合直接给人看，gs --debug-regex 就是打印的这个
+#[derive(Clone, Debug)]
+pub struct Explanation {
+    pub(crate) regex: String,
+    pub(crate) strategy: LiteralStrategy,
+    pub(crate) seq: Seq,
+    pub(crate) non_matching_bytes: ByteSet,
+}
+
+impl Explanation {
+    /// 所有模式合并后最终编译的正则
+    pub fn regex(&self) -> &str {
+        &self.regex
+    }
+
+    pub fn strategy(&self) -> LiteralStrategy {
+        self.strategy
+    }
+
+    /// 提取的内部字面量，没有使用内部字面量优化时是无限集合
+    pub fn seq(&self) -> &Seq {
+        &self.seq
+    }
+
+    /// 提取的字面量，无限集合时返回 None
+    pub fn literals(&self) -> Option<&[Literal]> {
+        self.seq.literals()
+    }
+
+    /// 提取的字面量是否都是精确的，即匹配到字面量就一定匹配整个正则
+    pub fn is_exact(&self) -> bool {
+        self.seq.is_exact()
+    }
+
+    /// 正则永远不会匹配到的字节
+    pub fn non_matching_bytes(&self) -> &ByteSet {
+        &self.non_matching_bytes
+    }
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "regex: {}", self.regex)?;
+        writeln!(f, "literal strategy: {}", self.strategy)?;
+        match self.literals() {
+            None => writeln!(f, "literals: none")?,
+            Some(lits) => {
+                let exact = if self.is_exact() { "exact" } else { "inexact" };
+                write!(f, "literals ({}, {}):", lits.len(), exact)?;
+                for lit in lits {
+                    write!(f, " \"{}\"", lit.as_bytes().escape_ascii())?;
+                }
+                writeln!(f)?;
+            }
+        }
+        // 连续的字节合并成一个范围输出，比如 \x80-\xff
+        let mut ranges = vec![];
+        let mut byte = 0u16;
+        while byte <= 255 {
+            if !self.non_matching_bytes.contains(byte as u8) {
+                byte += 1;
+                continue;
+            }
+            let start = byte as u8;
+            while byte <= 255 && self.non_matching_bytes.contains(byte as u8) {
+                byte += 1;
+            }
+            ranges.push((start, (byte - 1) as u8));
+        }
+        write!(f, "non-matching bytes:")?;
+        if ranges.is_empty() {
+            write!(f, " none")?;
+        }
+        for (start, end) in ranges {
+            if start == end {
+                write!(f, " {}", [start].escape_ascii())?;
+            } else {
+                write!(f, " {}-{}", [start].escape_ascii(), [end].escape_ascii())?;
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct InnerLiterals {
     seq: Seq,
+    strategy: LiteralStrategy,
 }
 
 impl InnerLiterals {
@@ -32,7 +148,7 @@ impl InnerLiterals {
                 "skipping inner literal extraction, \
                  no line terminator is set"
             );
-            return InnerLiterals::none();
+            return InnerLiterals::none(LiteralStrategy::NoLineTerminator);
         }
         // If we believe the regex is already accelerated, then just let
         // the regex engine do its thing. We'll skip the inner literal
@@ -48,7 +164,7 @@ impl InnerLiterals {
                     "skipping inner literal extraction, \
                      existing regex is believed to already be accelerated",
                 );
-                return InnerLiterals::none();
+                return InnerLiterals::none(LiteralStrategy::Accelerated);
             }
         }
         // In this case, we pretty much know that the regex engine will handle
@@ -58,16 +174,30 @@ impl InnerLiterals {
                 "skipping inner literal extraction, \
                  found alternation of literals, deferring to regex engine",
             );
-            return InnerLiterals::none();
+            return InnerLiterals::none(LiteralStrategy::AlternationLiteral);
         }
         let seq = Extractor::new().extract_untagged(chir.hir());
-        InnerLiterals { seq }
+        let strategy = match seq.literals() {
+            Some(lits) if !lits.is_empty() => LiteralStrategy::InnerLiterals,
+            _ => LiteralStrategy::Discarded,
+        };
+        InnerLiterals { seq, strategy }
     }
 
     /// Returns a infinite set of inner literals, such that it can never
     /// produce a matcher.
-    pub(crate) fn none() -> InnerLiterals {
-        InnerLiterals { seq: Seq::infinite() }
+    pub(crate) fn none(strategy: LiteralStrategy) -> InnerLiterals {
+        InnerLiterals { seq: Seq::infinite(), strategy }
+    }
+
+    /// 生成诊断信息，non_matching_bytes 和最终的正则由 RegexMatcher 提供
+    pub(crate) fn explain(&self, regex: String, non_matching_bytes: ByteSet) -> Explanation {
+        Explanation {
+            regex,
+            strategy: self.strategy,
+            seq: self.seq.clone(),
+            non_matching_bytes,
+        }
     }
 
     pub(crate) fn one_regex(&self) -> Result<Option<Regex>, Error> {
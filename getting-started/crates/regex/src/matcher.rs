@@ -1,9 +1,12 @@
-use regex_automata::Input;
+use regex_automata::{Input, PatternID as AutomataPatternID};
 use regex_automata::meta::Regex;
-use grep_matcher::{ByteSet, LineMatchKind, LineTerminator, Match, Matcher, NoError};
-use crate::config::Config;
+use regex_syntax::hir::Hir;
+use regex_automata::util::captures::Captures as AutomataCaptures;
+use grep_matcher::{ByteSet, Captures, LineMatchKind, LineTerminator, Match, Matcher, NoError, PatternID};
+use std::sync::{Arc, OnceLock};
+use crate::config::{Config, ConfiguredHIR};
 use crate::error::Error;
-use crate::literals::InnerLiterals;
+use crate::literals::{Explanation, InnerLiterals};
 
 /// ripgrep 搜索流程的3个重要的类型之一 RegexMatcher (ripgrep支持两种正则引擎：Rust Regex、PCRE2，这里只展示 Rust Regex)
 /// RegexMatcher 用于执行正则表达式匹配，匹配符合 PATTERN 参数的行
@@ -45,11 +48,23 @@ impl RegexMatcherBuilder {
         log::debug!("final regex: {:?}", chir.hir().to_string());
 
         let non_matching_bytes = chir.non_matching_bytes();
-        let fast_line_regex = InnerLiterals::new(&chir, &regex).one_regex()?;
+        let inner_literals = InnerLiterals::new(&chir, &regex);
+        let fast_line_regex = inner_literals.one_regex()?;
+        let pattern_count = chir.pattern_count();
+        let hir = chir.hir().clone();
 
         let mut config = self.config.clone();
         config.line_terminator = chir.line_terminator();
-        Ok(RegexMatcher { config, regex, fast_line_regex, non_matching_bytes })
+        Ok(RegexMatcher {
+            config,
+            regex,
+            fast_line_regex,
+            non_matching_bytes,
+            pattern_regex: (pattern_count > 1).then(|| PatternRegex::new(chir)),
+            pattern_count,
+            hir,
+            inner_literals,
+        })
     }
 
     // 后面都是些配置定制方法 --------------------------------------------------------------
@@ -75,6 +90,38 @@ impl RegexMatcherBuilder {
         self
     }
 
+    /// 设置编译后正则的大小限制（字节），超过时 build 返回 ErrorKind::SizeLimitExceeded，默认 100MB
+    /// 很大的自动生成的选择（比如上万个关键字）可能需要调大
+    pub fn size_limit(&mut self, bytes: usize) -> &mut RegexMatcherBuilder {
+        self.config.size_limit = bytes;
+        self
+    }
+
+    /// 设置惰性 DFA 缓存的大小（字节），默认 1000MB
+    /// 缓存太小时正则引擎会频繁清空缓存或者退回到更慢的引擎，但不会报错
+    pub fn dfa_size_limit(&mut self, bytes: usize) -> &mut RegexMatcherBuilder {
+        self.config.dfa_size_limit = bytes;
+        self
+    }
+
+    /// 设置模式的最大嵌套深度，超过时 build 返回 ErrorKind::NestLimitExceeded，默认 250
+    pub fn nest_limit(&mut self, limit: u32) -> &mut RegexMatcherBuilder {
+        self.config.nest_limit = limit;
+        self
+    }
+
+    /// 设置是否交换贪婪和非贪婪量词，即 `a*` 变成非贪婪，`a*?` 变成贪婪，相当于 `(?U)`
+    pub fn swap_greed(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.swap_greed = yes;
+        self
+    }
+
+    /// 设置是否忽略模式中的空白字符并允许 `#` 注释，相当于 `(?x)`，方便书写很长的模式
+    pub fn ignore_whitespace(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        self.config.ignore_whitespace = yes;
+        self
+    }
+
     pub fn fixed_strings(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
         self.config.fixed_strings = yes;
         self
@@ -106,6 +153,18 @@ impl RegexMatcherBuilder {
         self
     }
 
+    /// 设置是否启用 CRLF 模式，开启后多行模式下 `$` 可以匹配在 `\r\n` 之前，且匹配结果中不会包含 `\r`
+    /// 注意这个设置会覆盖 line_terminator() 的设置（开启时设置为 CRLF，关闭时清除）
+    pub fn crlf(&mut self, yes: bool) -> &mut RegexMatcherBuilder {
+        if yes {
+            self.config.line_terminator = Some(LineTerminator::crlf());
+        } else {
+            self.config.line_terminator = None;
+        }
+        self.config.crlf = yes;
+        self
+    }
+
     pub fn ban_byte(&mut self, byte: Option<u8>) -> &mut RegexMatcherBuilder {
         self.config.ban = byte;
         self
@@ -120,12 +179,79 @@ pub struct RegexMatcher {
     /// TODO ???
     fast_line_regex: Option<Regex>,
     non_matching_bytes: ByteSet,
+    /// 有多个模式时每个模式作为单独模式构建的 Regex，只在需要知道匹配项属于哪个模式时才构建
+    pattern_regex: Option<PatternRegex>,
+    /// 构建时的模式数量
+    pattern_count: usize,
+    /// 所有模式合并后的 Hir，以及内部字面量优化的选择，只用于 explain()
+    hir: Hir,
+    inner_literals: InnerLiterals,
 }
 
-impl Matcher for RegexMatcher {
+/// 多模式的 Regex 构建开销和合并后的 Regex 差不多，所以第一次调用 find_pattern_at 时才构建，克隆出的匹配器共享构建结果
+#[derive(Clone, Debug)]
+struct PatternRegex {
+    chir: Arc<ConfiguredHIR>,
+    regex: Arc<OnceLock<Option<Regex>>>,
+}
+
+impl PatternRegex {
+    fn new(chir: ConfiguredHIR) -> PatternRegex {
+        PatternRegex { chir: Arc::new(chir), regex: Arc::new(OnceLock::new()) }
+    }
+
+    /// 构建失败（比如超过大小限制）时返回 None，这时匹配项都报告为 PatternID::ZERO
+    fn get(&self) -> Option<&Regex> {
+        self.regex.get_or_init(|| match self.chir.to_pattern_regex() {
+            Ok(regex) => regex,
+            Err(err) => {
+                log::debug!("failed to build pattern regex: {}", err);
+                None
+            }
+        }).as_ref()
+    }
+}
 
+impl RegexMatcher {
+    /// 返回匹配器的诊断信息：最终的正则、是否使用了内部字面量优化及提取的字面量、不可能匹配的字节
+    /// 用于排查为什么有些模式搜索得很慢
+    pub fn explain(&self) -> Explanation {
+        self.inner_literals.explain(self.hir.to_string(), self.non_matching_bytes.clone())
+    }
+}
+
+impl Matcher for RegexMatcher {
+    type Captures = RegexCaptures;
     type Error = NoError;
 
+    #[inline]
+    fn new_captures(&self) -> Result<RegexCaptures, NoError> {
+        Ok(RegexCaptures(self.regex.create_captures()))
+    }
+
+    #[inline]
+    fn capture_count(&self) -> usize {
+        self.regex.captures_len()
+    }
+
+    #[inline]
+    fn capture_index(&self, name: &str) -> Option<usize> {
+        self.regex.group_info().to_index(AutomataPatternID::ZERO, name)
+    }
+
+    /// 使用 meta::Regex 的 search_captures 查找，和 find_at 一样只搜索 [at, haystack.len) 范围
+    #[inline]
+    fn captures_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+        caps: &mut RegexCaptures,
+    ) -> Result<bool, NoError> {
+        let input = Input::new(haystack).span(at..haystack.len());
+        self.regex.search_captures(&input, &mut caps.0);
+        Ok(caps.0.is_match())
+    }
+
     #[inline]
     fn find_candidate_line(
         &self,
@@ -155,11 +281,55 @@ impl Matcher for RegexMatcher {
         let input = Input::new(haystack).span(at..haystack.len());
         Ok(self.regex.find(input).map(|m| Match::new(m.start(), m.end())))
     }
+
+    #[inline]
+    fn pattern_count(&self) -> usize {
+        self.pattern_count
+    }
+
+    /// 只有一个模式时匹配项都属于 PatternID::ZERO，否则使用多模式的 Regex 查找，匹配范围和 find_at 一致
+    #[inline]
+    fn find_pattern_at(
+        &self,
+        haystack: &[u8],
+        at: usize,
+    ) -> Result<Option<(Match, PatternID)>, NoError> {
+        let Some(regex) = self.pattern_regex.as_ref().and_then(PatternRegex::get) else {
+            return Ok(self.find_at(haystack, at)?.map(|m| (m, PatternID::ZERO)));
+        };
+        let input = Input::new(haystack).span(at..haystack.len());
+        Ok(regex.find(input).map(|m| {
+            (Match::new(m.start(), m.end()), PatternID::new(m.pattern().as_usize()))
+        }))
+    }
+
+    #[inline]
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        self.config.line_terminator
+    }
+}
+
+/// RegexMatcher 的捕获组，是对 regex_automata Captures 的封装
+#[derive(Clone, Debug)]
+pub struct RegexCaptures(AutomataCaptures);
+
+impl Captures for RegexCaptures {
+    #[inline]
+    fn len(&self) -> usize {
+        self.0.group_info().all_group_len()
+    }
+
+    #[inline]
+    fn get(&self, i: usize) -> Option<Match> {
+        self.0.get_group(i).map(|sp| Match::new(sp.start, sp.end))
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use grep_matcher::{Match, Matcher, NoError};
+    use grep_matcher::{Captures, LineTerminator, Match, Matcher, NoError, PatternID};
+    use crate::error::ErrorKind;
+    use crate::literals::LiteralStrategy;
     use crate::matcher::RegexMatcherBuilder;
 
     fn default_builder() -> RegexMatcherBuilder {
@@ -267,4 +437,173 @@ For more information, please refer to <http://unlicense.org/>
         // let matcher = builder.build_many(&patterns).unwrap();
         // let m = matcher.find_at("regex engine is complex\n".as_bytes(), 0);
     }
-}
\ No newline at end of file
+
+    /// 测试 CRLF 模式下 `$` 的匹配，以及匹配器报告的行终止符
+    #[test]
+    fn crlf_match() {
+        let mut builder = default_builder();
+        builder.crlf(true);
+
+        let matcher = builder.build("complex$").unwrap();
+        assert_eq!(Some(LineTerminator::crlf()), matcher.line_terminator());
+        let m = matcher.find_at("regex engine is complex\r\n".as_bytes(), 0);
+        assert_eq!(m, Ok(Some(Match::new(16, 23))));
+
+        // 不开启 CRLF 模式时 `$` 只能匹配在 `\n` 之前
+        let matcher = default_builder().build("complex$").unwrap();
+        let m = matcher.find_at("regex engine is complex\r\n".as_bytes(), 0);
+        assert_eq!(m, Ok(None));
+    }
+
+    /// 测试使用 NUL 作为行终止符，此时 pattern 中不允许出现 NUL
+    #[test]
+    fn null_data_line_terminator() {
+        let mut builder = RegexMatcherBuilder::new();
+        builder.multi_line(true).line_terminator(Some(b'\x00'));
+
+        let matcher = builder.build("complex$").unwrap();
+        assert_eq!(Some(LineTerminator::byte(b'\x00')), matcher.line_terminator());
+        let m = matcher.find_at("regex\x00engine is complex\x00".as_bytes(), 0);
+        assert_eq!(m, Ok(Some(Match::new(16, 23))));
+        assert!(builder.build("complex\\x00").is_err());
+    }
+
+    /// 按序号和名称获取捕获组
+    #[test]
+    fn captures() {
+        let matcher = RegexMatcherBuilder::new().build(r"(?P<key>\w+)=(\w+)?").unwrap();
+        assert_eq!(3, matcher.capture_count());
+        assert_eq!(Some(1), matcher.capture_index("key"));
+        assert_eq!(None, matcher.capture_index("value"));
+
+        let mut caps = matcher.new_captures().unwrap();
+        assert!(matcher.captures_at(b"x name=gs", 2, &mut caps).unwrap());
+        assert_eq!(3, caps.len());
+        assert_eq!(Some(Match::new(2, 9)), caps.get(0));
+        assert_eq!(Some(Match::new(2, 6)), caps.get(1));
+        assert_eq!(Some(Match::new(7, 9)), caps.get(2));
+
+        // 没有参与匹配的捕获组
+        assert!(matcher.captures(b"empty=", &mut caps).unwrap());
+        assert_eq!(None, caps.get(2));
+        assert!(!matcher.captures(b"nothing", &mut caps).unwrap());
+    }
+
+    #[test]
+    fn captures_iter() {
+        let matcher = RegexMatcherBuilder::new().build(r"(\w)(\d)?").unwrap();
+        let mut caps = matcher.new_captures().unwrap();
+        let mut groups = vec![];
+        matcher.captures_iter(b"a1 b c3", &mut caps, |caps| {
+            groups.push((caps.get(1), caps.get(2)));
+            true
+        }).unwrap();
+        assert_eq!(vec![
+            (Some(Match::new(0, 1)), Some(Match::new(1, 2))),
+            (Some(Match::new(3, 4)), None),
+            (Some(Match::new(5, 6)), Some(Match::new(6, 7))),
+        ], groups);
+    }
+
+    /// 通过 Captures::interpolate 实现 `$name` 引用的替换
+    #[test]
+    fn replace_with_captures() {
+        let matcher = RegexMatcherBuilder::new().build(r"(?P<key>\w+)=(?P<value>\w+)").unwrap();
+        let mut caps = matcher.new_captures().unwrap();
+        let haystack = b"a=1, b=2";
+        let mut dst = vec![];
+        matcher.replace_with_captures(haystack, &mut caps, &mut dst, |caps, dst| {
+            caps.interpolate(|name| matcher.capture_index(name), haystack, b"${value}:$key", dst);
+            true
+        }).unwrap();
+        assert_eq!("1:a, 2:b", String::from_utf8(dst).unwrap());
+
+        // 闭包返回 false 时停止替换，剩余内容原样保留
+        let mut dst = vec![];
+        matcher.replace_with_captures(haystack, &mut caps, &mut dst, |_, dst| {
+            dst.extend(b"X");
+            false
+        }).unwrap();
+        assert_eq!("X, b=2", String::from_utf8(dst).unwrap());
+    }
+
+    /// 多个模式构建的 RegexMatcher 可以知道匹配项是哪个模式匹配到的，匹配范围和拼接成一个模式时一致
+    #[test]
+    fn find_pattern_ids() {
+        let matcher = default_builder().build_many(&["foo", "fo+bar", "[0-9]+"]).unwrap();
+        assert_eq!(3, matcher.pattern_count());
+        let haystack = b"foobar 42 foo";
+        let mut found = vec![];
+        matcher.find_pattern_iter_at(haystack, 0, |m, pid| {
+            found.push((m, pid.as_usize()));
+            true
+        }).unwrap();
+        assert_eq!(vec![(Match::new(0, 3), 0), (Match::new(7, 9), 2), (Match::new(10, 13), 0)], found);
+        let mut all = vec![];
+        matcher.find_iter_at(haystack, 0, |m| {
+            all.push(m);
+            true
+        }).unwrap();
+        assert_eq!(found.iter().map(|&(m, _)| m).collect::<Vec<_>>(), all);
+
+        let matcher = default_builder().build("foo").unwrap();
+        assert_eq!(1, matcher.pattern_count());
+        assert_eq!(
+            Some((Match::new(4, 7), PatternID::ZERO)),
+            matcher.find_pattern_at(b"bar foo", 0).unwrap(),
+        );
+    }
+
+    /// explain() 报告字面量优化的选择和不可能匹配的字节
+    #[test]
+    fn explain() {
+        let explanation = default_builder().build("foo").unwrap().explain();
+        assert_eq!(LiteralStrategy::Accelerated, explanation.strategy());
+
+        let explanation = RegexMatcherBuilder::new().build("foo").unwrap().explain();
+        assert_eq!(LiteralStrategy::NoLineTerminator, explanation.strategy());
+        assert_eq!("(?:foo)", explanation.regex());
+        assert!(explanation.literals().is_none());
+        assert!(!explanation.non_matching_bytes().contains(b'f'));
+        assert!(explanation.non_matching_bytes().contains(b'a'));
+        assert_eq!(
+            "regex: (?:foo)\n\
+             literal strategy: none (no line terminator is set)\n\
+             literals: none\n\
+             non-matching bytes: \\x00-e g-n p-\\xff",
+            explanation.to_string(),
+        );
+    }
+
+    /// 超过大小和嵌套限制时通过 ErrorKind 报告是哪个限制
+    #[test]
+    fn limits() {
+        let err = RegexMatcherBuilder::new().size_limit(10).build(r"\w{100}").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::SizeLimitExceeded(10)), "{err:?}");
+        assert_eq!("compiled regex exceeds size limit of 10", err.to_string());
+        assert!(RegexMatcherBuilder::new().build(r"\w{100}").is_ok());
+
+        let err = RegexMatcherBuilder::new().nest_limit(2).build("((a))").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::NestLimitExceeded(2)), "{err:?}");
+
+        let matcher = RegexMatcherBuilder::new().ignore_whitespace(true).build("foo  bar # comment").unwrap();
+        assert!(matcher.is_match(b"foobar").unwrap());
+        let matcher = RegexMatcherBuilder::new().swap_greed(true).build("a+").unwrap();
+        assert_eq!(Some(Match::new(0, 1)), matcher.find_at(b"aaa", 0).unwrap());
+    }
+
+    /// 固定字符串中的空白和 `#` 不受忽略空白模式影响
+    #[test]
+    fn fixed_strings_ignore_whitespace() {
+        let matcher = RegexMatcherBuilder::new()
+            .fixed_strings(true)
+            .ignore_whitespace(true)
+            .build_many(&["foo bar", "a\tb # c"])
+            .unwrap();
+        assert!(matcher.is_match(b"foo bar").unwrap());
+        assert!(!matcher.is_match(b"foobar").unwrap());
+        assert!(matcher.is_match(b"a\tb # c").unwrap());
+        assert!(!matcher.is_match(b"ab").unwrap());
+        assert_eq!(Some((Match::new(2, 9), PatternID::new(1))), matcher.find_pattern_at(b"x a\tb # c", 0).unwrap());
+    }
+}
@@ -0,0 +1,132 @@
+//! 类似 `tail -F` 的文件读取器，读到文件末尾后不返回 EOF，而是定期检查文件是否有新数据
+//! 配合 ReadByLine 使用时 Core 不会被重建，所以行号和 absolute_byte_offset 在多次读取之间是连续的
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// 用于判断文件是否被轮转（比如 logrotate 将 app.log 重命名为 app.log.1 再创建新的 app.log）
+/// unix 下使用 (dev, inode)，其他平台无法判断，只能检测截断
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct FileId {
+    #[cfg(unix)]
+    dev: u64,
+    #[cfg(unix)]
+    ino: u64,
+}
+
+impl FileId {
+    fn from_metadata(md: &Metadata) -> FileId {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            FileId { dev: md.dev(), ino: md.ino() }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = md;
+            FileId {}
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct FollowReader {
+    path: PathBuf,
+    file: File,
+    id: FileId,
+    /// 当前打开的文件已经读取的字节数，文件长度小于它说明文件被截断了
+    pos: u64,
+    /// 读到文件末尾后，每次检查新数据的间隔
+    poll_interval: Duration,
+}
+
+impl FollowReader {
+    pub(crate) fn open(path: &Path, poll_interval: Duration) -> io::Result<FollowReader> {
+        let file = File::open(path)?;
+        let id = FileId::from_metadata(&file.metadata()?);
+        Ok(FollowReader { path: path.to_path_buf(), file, id, pos: 0, poll_interval })
+    }
+
+    /// 读到文件末尾后检查文件是否被轮转或截断，是的话重新从头读取，返回 true 表示需要立即重新读取
+    fn check_reopen(&mut self) -> io::Result<bool> {
+        // 轮转过程中文件可能暂时不存在，等下次检查
+        let Ok(md) = fs::metadata(&self.path) else { return Ok(false) };
+        let id = FileId::from_metadata(&md);
+        if id != self.id {
+            log::debug!("{}: file rotated, reopening", self.path.display());
+            self.file = File::open(&self.path)?;
+            self.id = id;
+            self.pos = 0;
+            return Ok(true);
+        }
+        if md.len() < self.pos {
+            log::debug!("{}: file truncated, reading from start", self.path.display());
+            self.file.seek(SeekFrom::Start(0))?;
+            self.pos = 0;
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+impl Read for FollowReader {
+    /// 一直阻塞到有新数据为止，永远不会返回 0（除非 buf 为空）
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            let n = self.file.read(buf)?;
+            if n > 0 {
+                self.pos += n as u64;
+                return Ok(n);
+            }
+            if !self.check_reopen()? {
+                std::thread::sleep(self.poll_interval);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{self, OpenOptions};
+    use std::io::{Read, Write};
+    use std::path::Path;
+    use std::time::Duration;
+    use crate::follow::FollowReader;
+
+    fn append(path: &Path, data: &str) {
+        let mut file = OpenOptions::new().append(true).create(true).open(path).unwrap();
+        file.write_all(data.as_bytes()).unwrap();
+    }
+
+    fn read_some(rdr: &mut FollowReader) -> String {
+        let mut buf = [0; 64];
+        let n = rdr.read(&mut buf).unwrap();
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    }
+
+    /// 读到末尾后可以继续读到追加的数据，文件被截断或者轮转后从新文件的开头读取
+    #[test]
+    fn follow_append_truncate_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("follow");
+        let rotated = dir.path().join("follow.1");
+        fs::write(&path, "first\n").unwrap();
+        let mut rdr = FollowReader::open(&path, Duration::from_millis(1)).unwrap();
+        assert_eq!("first\n", read_some(&mut rdr));
+
+        append(&path, "second\n");
+        assert_eq!("second\n", read_some(&mut rdr));
+
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!("new\n", read_some(&mut rdr));
+
+        fs::rename(&path, &rotated).unwrap();
+        append(&path, "rotated\n");
+        assert_eq!("rotated\n", read_some(&mut rdr));
+    }
+}
@@ -1,11 +1,15 @@
+pub use crate::lines::{LineIter, LineStep};
 pub use crate::searcher::{
-    Searcher, SearcherBuilder
+    BinaryDetection, ConfigError, Encoding, Searcher, SearcherBuilder
 };
 pub use crate::sink::{
-    Sink, SinkMatch, SinkError
+    sinks, Sink, SinkContext, SinkContextKind, SinkError, SinkFinish, SinkMatch
 };
 
+mod follow;
 mod line_buffer;
 mod searcher;
 mod sink;
-mod lines;
\ No newline at end of file
+mod lines;
+#[cfg(test)]
+mod testutil;
\ No newline at end of file
@@ -3,20 +3,47 @@ use bstr::ByteSlice;
 
 /// ripgrep 搜索流程的3个重要的类型之一 LineBufferReader
 /// LineBufferReader 用于从指定的 path file 中读取数据到缓冲 (LineBuffer)
-/// ripgrep 还支持二进制内容读取，还支持两种缓冲池容量扩容策略，不过这里先略
+/// 读取的同时按 BinaryDetection 检测二进制数据
 
 pub(crate) const DEFAULT_BUFFER_CAPACITY: usize = 64 * (1 << 10); // 64 KB
 
+/// 一行数据超过缓冲容量时的扩容策略
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum BufferAllocation {
+    /// 每次扩容增加之前的两倍容量，没有上限，直到能容纳一整行为止
+    #[default]
+    Eager,
+    /// 和 Eager 一样扩容，但是在初始容量之外最多再分配 limit 字节，超过后返回错误
+    Error(usize),
+}
+
+/// 二进制数据的检测方式，参考 searcher::BinaryDetection
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) enum BinaryDetection {
+    /// 不检测
+    #[default]
+    None,
+    /// 遇到这个字节时停止读取
+    Quit(u8),
+    /// 遇到这个字节时将它替换成行终止符后继续读取
+    Convert(u8),
+}
+
+/// 超过扩容上限时返回的错误
+pub(crate) fn alloc_error(limit: usize) -> io::Error {
+    io::Error::other(format!("configured allocation limit ({}) exceeded", limit))
+}
+
 #[derive(Clone, Copy, Debug)]
 struct Config {
-    ///
+    /// 缓冲的初始容量
     capacity: usize,
     /// 行终止符
     line_terminator: u8,
-    // The behavior for handling long lines.
-    // buffer_alloc: BufferAllocation,
-    // When set, the presence of the given byte indicates binary content.
-    // binary: BinaryDetection,
+    /// 一行数据超过缓冲容量时的扩容策略
+    buffer_alloc: BufferAllocation,
+    /// 二进制数据的检测方式
+    binary: BinaryDetection,
 }
 
 impl Default for Config {
@@ -24,6 +51,8 @@ impl Default for Config {
         Config {
             capacity: DEFAULT_BUFFER_CAPACITY,
             line_terminator: b'\n',
+            buffer_alloc: BufferAllocation::default(),
+            binary: BinaryDetection::default(),
         }
     }
 }
@@ -51,6 +80,16 @@ impl LineBufferBuilder {
         self
     }
 
+    pub(crate) fn buffer_alloc(&mut self, behavior: BufferAllocation) -> &mut LineBufferBuilder {
+        self.config.buffer_alloc = behavior;
+        self
+    }
+
+    pub(crate) fn binary_detection(&mut self, detection: BinaryDetection) -> &mut LineBufferBuilder {
+        self.config.binary = detection;
+        self
+    }
+
     pub(crate) fn build(&self) -> LineBuffer {
         LineBuffer {
             config: self.config,
@@ -59,7 +98,7 @@ impl LineBufferBuilder {
             last_line_terminator: 0,
             end: 0,
             absolute_byte_offset: 0,
-            // binary_byte_offset: None,
+            binary_byte_offset: None,
         }
     }
 }
@@ -75,17 +114,39 @@ pub(crate) struct LineBufferReader<'b, R> { //TODO 注意这里的作用域
     line_buffer: &'b mut LineBuffer,
 }
 
-impl<'b, R: io::Read> LineBufferReader<'b, R> {
+impl<'b, R> LineBufferReader<'b, R> {
     pub(crate) fn new(rdr: R, line_buffer: &'b mut LineBuffer) -> LineBufferReader<'b, R> {
         line_buffer.clear();
         LineBufferReader { rdr, line_buffer }
     }
 
     /// 读取 std::io::Read 数据到缓冲
-    pub(crate) fn fill(&mut self) -> Result<bool, io::Error> {
+    pub(crate) fn fill(&mut self) -> Result<bool, io::Error>
+    where
+        R: io::Read,
+    {
         self.line_buffer.fill(&mut self.rdr)
     }
 
+    /// 读取 tokio::io::AsyncRead 数据到缓冲
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn fill_async(&mut self) -> Result<bool, io::Error>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        self.line_buffer.fill_async(&mut self.rdr).await
+    }
+
+    /// 到目前为止已经消费的字节数
+    pub(crate) fn absolute_byte_offset(&self) -> u64 {
+        self.line_buffer.absolute_byte_offset()
+    }
+
+    /// 第一个二进制数据的绝对偏移量，没有检测到（或没有开启检测）时为 None
+    pub(crate) fn binary_byte_offset(&self) -> Option<u64> {
+        self.line_buffer.binary_byte_offset
+    }
+
     /// 查看缓冲可读取内容（即从pos到最后一个终止符，不会修改指针值）
     pub(crate) fn buffer(&self) -> &[u8] {
         self.line_buffer.buffer()
@@ -98,7 +159,8 @@ impl<'b, R: io::Read> LineBufferReader<'b, R> {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Default 只用于搜索时把行缓冲从 Searcher 中取出来后占位
+#[derive(Clone, Debug, Default)]
 pub(crate) struct LineBuffer {
     config: Config,
     /// 缓冲池
@@ -114,7 +176,8 @@ pub(crate) struct LineBuffer {
     end: usize,
     /// 自构造或执行clear()依赖绝对偏移量
     absolute_byte_offset: u64,
-    // binary_byte_offset: Option<u64>,
+    /// 第一个二进制数据的绝对偏移量
+    binary_byte_offset: Option<u64>,
 }
 
 impl LineBuffer {
@@ -123,11 +186,18 @@ impl LineBuffer {
         self.last_line_terminator = 0;
         self.end = 0;
         self.absolute_byte_offset = 0;
-        // self.binary_byte_offset = None;
+        self.binary_byte_offset = None;
+    }
+
+    fn absolute_byte_offset(&self) -> u64 {
+        self.absolute_byte_offset
     }
 
     /// 将数据从 std::io::Read 读取到 LineBuffer
     fn fill<R: io::Read>(&mut self, mut rdr: R) -> Result<bool, io::Error> {
+        if let Some(did_read) = self.binary_quit() {
+            return Ok(did_read);
+        }
         //将上次未消费的数据放到缓冲最前面，新读取的数据追加到后面
         self.roll();
         //这里退出循环的条件是要么没有数据可读，要么读取的新数据至少包含一个行终止符号
@@ -138,21 +208,90 @@ impl LineBuffer {
             //将文件内容读取到缓冲空闲的切片，返回实际读取的字节数量
             //注意 DecodeReaderBytes read() 方法第一次读取会尝试读取文件的 BOM 部分（3字节）
             let read_len = rdr.read(self.free_buffer().as_bytes_mut())?;    //数据读完或buffer读满为止，返回实际读取的长度（按元素个数算）
-            if read_len == 0 {  //说明文件读取完毕
-                self.last_line_terminator = self.end;   //将行终止符位置设置为最后一个字符的位置，这样即使没有行终止符也可以被消费了
-                return Ok(!self.buffer().is_empty());
+            if let Some(did_read) = self.advance(read_len) {
+                return Ok(did_read);
             }
-            //更新行缓冲中的指针
-            let old_end = self.end;
-            self.end += read_len;
-            let newbytes = &mut self.buf[old_end..self.end];
-            if let Some(i) = newbytes.rfind_byte(self.config.line_terminator) {    //寻找最后一个行终止符在 newbytes 中的索引
-                self.last_line_terminator = old_end + i + 1;
-                return Ok(true);
+        }
+    }
+
+    /// 和 fill() 一样，只是从 tokio 的 AsyncRead 读取数据，等待数据时不会阻塞线程
+    #[cfg(feature = "tokio")]
+    async fn fill_async<R>(&mut self, rdr: &mut R) -> Result<bool, io::Error>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+    {
+        use tokio::io::AsyncReadExt;
+
+        if let Some(did_read) = self.binary_quit() {
+            return Ok(did_read);
+        }
+        self.roll();
+        loop {
+            self.ensure_capacity()?;
+            let read_len = rdr.read(self.free_buffer()).await?;
+            if let Some(did_read) = self.advance(read_len) {
+                return Ok(did_read);
             }
         }
     }
 
+    /// 新读取了 read_len 字节到空闲缓冲后更新指针
+    /// 返回 Some 表示这次填充可以结束了（值和 fill() 的返回值含义相同），返回 None 表示还没读到行终止符需要继续读取
+    fn advance(&mut self, read_len: usize) -> Option<bool> {
+        if read_len == 0 {  //说明文件读取完毕
+            self.last_line_terminator = self.end;   //将行终止符位置设置为最后一个字符的位置，这样即使没有行终止符也可以被消费了
+            return Some(!self.buffer().is_empty());
+        }
+        //更新行缓冲中的指针
+        let old_end = self.end;
+        self.end += read_len;
+        if self.detect_binary(old_end) {
+            // 二进制数据之前的内容仍然可以消费，即使最后没有行终止符
+            self.last_line_terminator = self.end;
+            return Some(!self.buffer().is_empty());
+        }
+        let newbytes = &mut self.buf[old_end..self.end];
+        if let Some(i) = newbytes.rfind_byte(self.config.line_terminator) {    //寻找最后一个行终止符在 newbytes 中的索引
+            self.last_line_terminator = old_end + i + 1;
+            return Some(true);
+        }
+        None
+    }
+
+    /// 检查新读取的 buf[old_end..end] 中的二进制数据，返回 true 表示需要停止读取
+    /// Quit 时缓冲在二进制数据处截断；Convert 时二进制数据被替换成行终止符
+    fn detect_binary(&mut self, old_end: usize) -> bool {
+        let (byte, quit) = match self.config.binary {
+            BinaryDetection::None => return false,
+            BinaryDetection::Quit(byte) => (byte, true),
+            BinaryDetection::Convert(byte) => (byte, false),
+        };
+        let Some(i) = self.buf[old_end..self.end].find_byte(byte) else { return false };
+        if self.binary_byte_offset.is_none() {
+            // buf[pos] 的绝对偏移量就是已经消费的字节数
+            self.binary_byte_offset = Some(self.absolute_byte_offset + (old_end + i - self.pos) as u64);
+        }
+        if quit {
+            self.end = old_end + i;
+            return true;
+        }
+        let line_terminator = self.config.line_terminator;
+        for b in self.buf[old_end + i..self.end].iter_mut().filter(|b| **b == byte) {
+            *b = line_terminator;
+        }
+        false
+    }
+
+    /// Quit 模式下检测到二进制数据后不再读取，缓冲中剩余的数据（最后一行可能没有行终止符）全部可以消费
+    fn binary_quit(&mut self) -> Option<bool> {
+        if self.binary_byte_offset.is_none() || !matches!(self.config.binary, BinaryDetection::Quit(_)) {
+            return None;
+        }
+        self.roll();
+        self.last_line_terminator = self.end;
+        Some(!self.buffer().is_empty())
+    }
+
     /// 将行缓冲中还未消费的数据滚动到前面，因为每次消费只会消费到最后一个行终止符，后面还有数据会等下次消费
     /// 下次读取前需要先将这部分未消费的数据放到行缓冲最前面
     fn roll(&mut self) {
@@ -170,13 +309,24 @@ impl LineBuffer {
         self.end = roll_len;
     }
 
-    /// ripgrep 支持两种扩容机制（Eager、Error），这里只展示 Eager(每次扩容增加之前的两倍容量，相当于扩容为3倍)
+    /// 两种扩容机制：Eager 每次扩容增加之前的两倍容量（相当于扩容为3倍）；Error 在此基础上限制初始容量之外最多分配的字节数
     fn ensure_capacity(&mut self) -> Result<(), io::Error> {
         if !self.free_buffer().is_empty() { //有空余空间即可，不够的话下个循环可以继续扩容
             return Ok(());
         }
         let len = std::cmp::max(1, self.buf.len());
-        let new_len = self.buf.len() + len * 2;
+        let additional = match self.config.buffer_alloc {
+            BufferAllocation::Eager => len * 2,
+            BufferAllocation::Error(limit) => {
+                let used = self.buf.len() - self.config.capacity;
+                let n = std::cmp::min(len * 2, limit - used);
+                if n == 0 {
+                    return Err(alloc_error(self.config.capacity + limit));
+                }
+                n
+            }
+        };
+        let new_len = self.buf.len() + additional;
         self.buf.resize(new_len, 0);
         Ok(())
     }
@@ -200,7 +350,7 @@ impl LineBuffer {
 #[cfg(test)]
 mod tests {
     use bstr::ByteSlice;
-    use crate::line_buffer::{LineBuffer, LineBufferBuilder, LineBufferReader};
+    use crate::line_buffer::{BinaryDetection, BufferAllocation, LineBufferBuilder, LineBufferReader};
 
     #[test]
     fn bstr_rfind_byte() {
@@ -255,5 +405,53 @@ mod tests {
         assert_eq!("lisa_this_message_will_cause_buffer_resize\n", bstr);
         rdr.consume(bstr.len());
     }
-}
 
+    /// 限制扩容上限后，超过上限的行返回错误，没超过的行可以正常读取
+    #[test]
+    fn buffer_alloc_error() {
+        let lines = "homer\nlisa_is_too_long\nmaggie";
+        let mut line_buffer = LineBufferBuilder::new()
+            .capacity(4)
+            .buffer_alloc(BufferAllocation::Error(4))
+            .build();
+        let mut rdr = LineBufferReader::new(lines.as_bytes(), &mut line_buffer);
+        rdr.fill().unwrap();
+        assert_eq!("homer\n", rdr.buffer().as_bstr());
+        rdr.consume(6);
+
+        let err = rdr.fill().unwrap_err();
+        assert_eq!("configured allocation limit (8) exceeded", err.to_string());
+    }
+
+    /// Quit 在二进制数据处截断，之前的数据仍然可以消费
+    #[test]
+    fn buffer_binary_quit() {
+        let lines = "homer\nlisa\x00maggie\nbart\n";
+        let mut line_buffer = LineBufferBuilder::new()
+            .capacity(8)
+            .binary_detection(BinaryDetection::Quit(b'\x00'))
+            .build();
+        let mut rdr = LineBufferReader::new(lines.as_bytes(), &mut line_buffer);
+        assert!(rdr.fill().unwrap());
+        assert_eq!("homer\n", rdr.buffer().as_bstr());
+        rdr.consume(6);
+        assert!(rdr.fill().unwrap());
+        assert_eq!("lisa", rdr.buffer().as_bstr());
+        assert_eq!(Some(10), rdr.binary_byte_offset());
+        rdr.consume(4);
+        assert!(!rdr.fill().unwrap());
+    }
+
+    /// Convert 将二进制数据替换成行终止符后继续读取
+    #[test]
+    fn buffer_binary_convert() {
+        let lines = "homer\x00lisa\nmaggie\x00\n";
+        let mut line_buffer = LineBufferBuilder::new()
+            .binary_detection(BinaryDetection::Convert(b'\x00'))
+            .build();
+        let mut rdr = LineBufferReader::new(lines.as_bytes(), &mut line_buffer);
+        assert!(rdr.fill().unwrap());
+        assert_eq!("homer\nlisa\nmaggie\n\n", rdr.buffer().as_bstr());
+        assert_eq!(Some(5), rdr.binary_byte_offset());
+    }
+}
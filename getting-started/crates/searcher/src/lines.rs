@@ -1,7 +1,52 @@
 use bstr::ByteSlice;
-use grep_matcher::Match;
+use grep_matcher::{LineTerminator, Match};
 
-#[derive(Debug)]
+/// 按行迭代字节数组的迭代器，每次返回一行的内容，最后一行可能没有行终止符
+/// 默认返回的行包含行终止符，可以通过 without_terminator() 去掉
+#[derive(Clone, Debug)]
+pub struct LineIter<'b> {
+    bytes: &'b [u8],
+    line_term: LineTerminator,
+    strip_terminator: bool,
+    stepper: LineStep,
+}
+
+impl<'b> LineIter<'b> {
+    /// 按 line_term 迭代 bytes 中的每一行
+    /// CRLF 模式下仍然按 `\n` 切分行，`\r` 只在去掉行终止符时用到
+    pub fn new(line_term: LineTerminator, bytes: &'b [u8]) -> LineIter<'b> {
+        LineIter {
+            bytes,
+            line_term,
+            strip_terminator: false,
+            stepper: LineStep::new(line_term.as_byte(), 0, bytes.len()),
+        }
+    }
+
+    /// 返回的每一行都去掉末尾的行终止符（CRLF 模式下只去掉完整的 `\r\n`）
+    pub fn without_terminator(mut self) -> LineIter<'b> {
+        self.strip_terminator = true;
+        self
+    }
+}
+
+impl<'b> Iterator for LineIter<'b> {
+    type Item = &'b [u8];
+
+    fn next(&mut self) -> Option<&'b [u8]> {
+        let m = self.stepper.next_match(self.bytes)?;
+        let line = &self.bytes[m];
+        if self.strip_terminator {
+            Some(without_terminator(line, self.line_term))
+        } else {
+            Some(line)
+        }
+    }
+}
+
+/// 按行迭代 bytes[start..end] 的迭代器，每次返回一行（包含行终止符）在 bytes 中的范围
+/// 和 LineIter 不同，它不持有 bytes，每次调用 next_match 时传入，这样调用方可以在迭代过程中继续使用 bytes
+#[derive(Clone, Debug)]
 pub struct LineStep {
     line_term: u8,
     pos: usize,
@@ -9,7 +54,42 @@ pub struct LineStep {
 }
 
 impl LineStep {
+    /// 迭代 bytes[start..end] 范围内的行，start 需要是一行的开始位置
+    pub fn new(line_term: u8, start: usize, end: usize) -> LineStep {
+        LineStep { line_term, pos: start, end }
+    }
 
+    /// 返回下一行在 bytes 中的范围，最后一行可能没有行终止符
+    /// 每次调用都需要传入同一个 bytes
+    pub fn next_match(&mut self, bytes: &[u8]) -> Option<Match> {
+        let bytes = &bytes[..self.end];
+        match bytes[self.pos..].find_byte(self.line_term) {
+            None => {
+                if self.pos < bytes.len() {
+                    let m = Match::new(self.pos, bytes.len());
+                    self.pos = m.end();
+                    Some(m)
+                } else {
+                    None
+                }
+            }
+            Some(line_end) => {
+                let m = Match::new(self.pos, self.pos + line_end + 1);
+                self.pos = m.end();
+                Some(m)
+            }
+        }
+    }
+}
+
+/// 去掉 bytes 末尾的行终止符（CRLF 模式下需要末尾是完整的 `\r\n`）
+pub(crate) fn without_terminator(bytes: &[u8], line_term: LineTerminator) -> &[u8] {
+    let line_term = line_term.as_bytes();
+    let start = bytes.len().saturating_sub(line_term.len());
+    if bytes.get(start..) == Some(line_term) {
+        return &bytes[..bytes.len() - line_term.len()];
+    }
+    bytes
 }
 
 /// 返回 bytes 中从右往左数第 count+1 行的起始偏移
@@ -66,7 +146,46 @@ pub(crate) fn locate(bytes: &[u8], line_term: u8, range: Match) -> Match {
 
 #[cfg(test)]
 mod tests {
+    use grep_matcher::{LineTerminator, Match};
     use crate::lines;
+    use crate::lines::{LineIter, LineStep};
+
+    #[test]
+    fn line_step() {
+        let bytes = b"abc\nxyz\n\nlast";
+        let mut stepper = LineStep::new(b'\n', 0, bytes.len());
+        assert_eq!(Some(Match::new(0, 4)), stepper.next_match(bytes));
+        assert_eq!(Some(Match::new(4, 8)), stepper.next_match(bytes));
+        assert_eq!(Some(Match::new(8, 9)), stepper.next_match(bytes));
+        assert_eq!(Some(Match::new(9, 13)), stepper.next_match(bytes));
+        assert_eq!(None, stepper.next_match(bytes));
+
+        // 只迭代指定的范围
+        let mut stepper = LineStep::new(b'\n', 4, 9);
+        assert_eq!(Some(Match::new(4, 8)), stepper.next_match(bytes));
+        assert_eq!(Some(Match::new(8, 9)), stepper.next_match(bytes));
+        assert_eq!(None, stepper.next_match(bytes));
+    }
+
+    #[test]
+    fn line_iter() {
+        let bytes = b"abc\r\nxyz\n\nlast";
+        let lines: Vec<&[u8]> = LineIter::new(LineTerminator::default(), bytes).collect();
+        assert_eq!(vec![&b"abc\r\n"[..], b"xyz\n", b"\n", b"last"], lines);
+
+        let lines: Vec<&[u8]> = LineIter::new(LineTerminator::crlf(), bytes).without_terminator().collect();
+        assert_eq!(vec![&b"abc"[..], b"xyz\n", b"\n", b"last"], lines);
+
+        assert_eq!(0, LineIter::new(LineTerminator::default(), b"").count());
+    }
+
+    #[test]
+    fn without_terminator() {
+        assert_eq!(b"abc", lines::without_terminator(b"abc\n", LineTerminator::default()));
+        assert_eq!(b"abc", lines::without_terminator(b"abc", LineTerminator::default()));
+        assert_eq!(b"abc", lines::without_terminator(b"abc\r\n", LineTerminator::crlf()));
+        assert_eq!(b"abc\n", lines::without_terminator(b"abc\n", LineTerminator::crlf()));
+    }
 
     #[test]
     fn preceding() {
@@ -1,7 +1,10 @@
-use grep_matcher::{LineMatchKind, Matcher};
+use std::cell::OnceCell;
+use std::io;
+use grep_matcher::{LineMatchKind, Matcher, PatternID};
 use crate::searcher::{Config, Range};
 use crate::{lines, Searcher, Sink};
-use crate::sink::{SinkError, SinkMatch};
+use crate::lines::LineStep;
+use crate::sink::{PatternIds, SinkContext, SinkContextKind, SinkError, SinkFinish, SinkMatch};
 
 enum FastMatchResult {
     Continue,
@@ -27,8 +30,12 @@ pub(crate) struct Core<'s, M: 's, S> {
     pos: usize,
     /// 是否有匹配的行
     has_matched: bool,
-    // 匹配之后剩余需要搜索的行数，用于有打印行数量限制时使用
-    // after_context_left: usize,
+    /// 是否有交给 Sink 的行（匹配行或上下文行），用于判断是否需要调用 context_break
+    has_sunk: bool,
+    /// 上一个匹配行之后还需要输出的后置上下文行数
+    after_context_left: usize,
+    /// 是否已经通过 Sink::binary_data 报告过二进制数据
+    binary_reported: bool,
 }
 
 impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
@@ -51,20 +58,41 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
             last_line_counted: 0,
             last_line_visited: 0,
             has_matched: false,
-            // after_context_left: 0,
+            has_sunk: false,
+            after_context_left: 0,
+            binary_reported: false,
         };
         core
     }
 
-    pub(crate) fn begin(&self) -> Result<bool, S::Error>  {
-        //TODO
-        Ok(true)
+    /// 搜索开始，通知 Sink，Sink 返回 false 时不需要搜索
+    pub(crate) fn begin(&mut self) -> Result<bool, S::Error>  {
+        self.sink.begin(self.searcher)
+    }
+
+    /// 检测到二进制数据，通知 Sink，同一次搜索只通知一次，Sink 返回 false 时不需要继续搜索
+    pub(crate) fn binary_data(&mut self, binary_byte_offset: u64) -> Result<bool, S::Error> {
+        if self.binary_reported {
+            return Ok(true);
+        }
+        self.binary_reported = true;
+        self.sink.binary_data(self.searcher, binary_byte_offset)
+    }
+
+    /// 搜索结束，通知 Sink，byte_count 是本次搜索总共搜索过的字节数
+    pub(crate) fn finish(
+        &mut self,
+        byte_count: u64,
+        binary_byte_offset: Option<u64>,
+    ) -> Result<(), S::Error> {
+        self.sink.finish(self.searcher, &SinkFinish { byte_count, binary_byte_offset })
     }
 
     /// roll滚动的意思，这里是指指针的移动
     pub(crate) fn roll(&mut self, buf: &[u8]) -> usize {
         //上次消费的数据字节数
-        let consumed = if self.config.max_context() == 0 {  //TODO ???
+        // 没有开启上下文时缓冲中的数据可以全部消费，否则需要保留最后几行，因为它们可能是下一块数据中匹配行的前置上下文
+        let consumed = if self.config.max_context() == 0 {
             buf.len()
         } else {
             let context_start = lines::preceding(   // 返回 buf 中从右往左数第 count+1 行的起始偏移
@@ -101,11 +129,15 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         self.pos = pos;
     }
 
-    ///
+    /// 查找缓冲中匹配的行并输出到 Sink
+    /// 匹配器保证不会跨行匹配时，可以直接在整个缓冲上匹配（fast），否则需要逐行匹配（slow）
     pub(crate) fn match_by_line(
         &mut self,
         buf: &[u8],
     ) -> Result<bool, S::Error> {
+        if !self.is_line_by_line_fast() {
+            return self.match_by_line_slow(buf);
+        }
         match self.match_by_line_fast(buf)? {
             //TODO ripgrep SwitchToSlow
             FastMatchResult::Continue => {Ok(true)}
@@ -113,6 +145,45 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         }
     }
 
+    /// 匹配器报告的行终止符和 Searcher 一致时，说明匹配结果一定不会包含行终止符，即不会跨行
+    /// 比如 Rust Regex 会从 Hir 中剔除行终止符，而 PCRE2 做不到，所以 PCRE2 只能逐行匹配
+    fn is_line_by_line_fast(&self) -> bool {
+        match self.matcher.line_terminator() {
+            Some(line_term) => line_term == self.config.line_terminator,
+            None => false,
+        }
+    }
+
+    /// 逐行匹配，每行去掉行终止符后单独交给匹配器
+    fn match_by_line_slow(&mut self, buf: &[u8]) -> Result<bool, S::Error> {
+        let mut stepper = LineStep::new(
+            self.config.line_terminator.as_byte(),
+            self.pos,
+            buf.len(),
+        );
+        while let Some(line) = stepper.next_match(buf) {
+            // 去掉行终止符，避免比如 `(?m)^$` 匹配到 `a\n` 中行终止符后面的空位置
+            let slice = lines::without_terminator(&buf[line], self.config.line_terminator);
+            let matched = match self.matcher.shortest_match(slice) {
+                Err(err) => return Err(S::Error::error_message(err)),
+                Ok(result) => result.is_some(),
+            };
+            self.set_pos(line.end());
+            if matched {
+                self.has_matched = true;
+                if !self.before_context_by_line(buf, line.start())? {
+                    return Ok(false);
+                }
+                if !self.sink_matched(buf, &line)? {
+                    return Ok(false);
+                }
+            } else if self.after_context_left >= 1 && !self.sink_after_context(buf, &line)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     /// 查找匹配的行，并输出到 Sink
     fn match_by_line_fast(
         &mut self,
@@ -122,11 +193,12 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
             // 1 查找 buf[self.pos..] 中匹配的行（返回行在buf中范围Range）
             if let Some(line) = self.find_by_line_fast(buf)? {
                 self.has_matched = true;
-                if self.config.max_context() > 0 {  //TODO ???
-                    if !self.after_context_by_line(buf, line.start())? ||
-                        !self.before_context_by_line(buf, line.start())? {
-                        return Ok(FastMatchResult::Stop);
-                    }
+                // 先输出上一个匹配行的后置上下文，再输出这个匹配行的前置上下文
+                if self.config.max_context() > 0
+                    && (!self.after_context_by_line(buf, line.start())?
+                        || !self.before_context_by_line(buf, line.start())?)
+                {
+                    return Ok(FastMatchResult::Stop);
                 }
                 self.set_pos(line.end());
 
@@ -140,6 +212,10 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         }
         //ripgrep 这里还有检查搜索到匹配行数量是否达到最大限制，达到的话直接退出，返回 FastMatchResult::Stop 告诉外层循环不需要继续搜索了
 
+        // 缓冲中没有更多匹配行了，但是最后一个匹配行的后置上下文可能还没输出完
+        if self.config.max_context() > 0 && !self.after_context_by_line(buf, buf.len())? {
+            return Ok(FastMatchResult::Stop);
+        }
         self.set_pos(buf.len());
         Ok(FastMatchResult::Continue)
     }
@@ -174,66 +250,66 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         Ok(None)
     }
 
-    ///
+    /// 输出 buf[last_line_visited..upto] 中最后 before_context 行作为前置上下文
     pub(crate) fn before_context_by_line(
         &mut self,
         buf: &[u8],
         upto: usize,
     ) -> Result<bool, S::Error> {
-        // if self.config.before_context == 0 {
-        //     return Ok(true);
-        // }
-        // let range = Range::new(self.last_line_visited, upto);
-        // if range.is_empty() {
-        //     return Ok(true);
-        // }
-        // let before_context_start = range.start()
-        //     + lines::preceding(
-        //     &buf[range],
-        //     self.config.line_terminator.as_byte(),
-        //     self.config.before_context - 1,
-        // );
-        //
-        // let range = Range::new(before_context_start, range.end());
-        // let mut stepper = LineStep::new(
-        //     self.config.line_terminator.as_byte(),
-        //     range.start(),
-        //     range.end(),
-        // );
-        // while let Some(line) = stepper.next_match(buf) {
-        //     if !self.sink_break_context(line.start())? {
-        //         return Ok(false);
-        //     }
-        //     if !self.sink_before_context(buf, &line)? {
-        //         return Ok(false);
-        //     }
-        // }
+        if self.config.before_context == 0 {
+            return Ok(true);
+        }
+        let range = Range::new(self.last_line_visited, upto);
+        if range.is_empty() {
+            return Ok(true);
+        }
+        let before_context_start = range.start()
+            + lines::preceding(
+            &buf[range],
+            self.config.line_terminator.as_byte(),
+            self.config.before_context - 1,
+        );
+
+        let range = Range::new(before_context_start, range.end());
+        let mut stepper = LineStep::new(
+            self.config.line_terminator.as_byte(),
+            range.start(),
+            range.end(),
+        );
+        while let Some(line) = stepper.next_match(buf) {
+            if !self.sink_break_context(line.start())? {
+                return Ok(false);
+            }
+            if !self.sink_before_context(buf, &line)? {
+                return Ok(false);
+            }
+        }
         Ok(true)
     }
 
-    /// TODO ?
+    /// 输出 buf[last_line_visited..upto] 中最前面的 after_context_left 行作为上一个匹配行的后置上下文
     pub(crate) fn after_context_by_line(
         &mut self,
         buf: &[u8],
         upto: usize,
     ) -> Result<bool, S::Error> {
-        // if self.after_context_left == 0 {
-        //     return Ok(true);
-        // }
-        // let range = Range::new(self.last_line_visited, upto);
-        // let mut stepper = LineStep::new(
-        //     self.config.line_terminator.as_byte(),
-        //     range.start(),
-        //     range.end(),
-        // );
-        // while let Some(line) = stepper.next_match(buf) {
-        //     if !self.sink_after_context(buf, &line)? {
-        //         return Ok(false);
-        //     }
-        //     if self.after_context_left == 0 {
-        //         break;
-        //     }
-        // }
+        if self.after_context_left == 0 {
+            return Ok(true);
+        }
+        let range = Range::new(self.last_line_visited, upto);
+        let mut stepper = LineStep::new(
+            self.config.line_terminator.as_byte(),
+            range.start(),
+            range.end(),
+        );
+        while let Some(line) = stepper.next_match(buf) {
+            if !self.sink_after_context(buf, &line)? {
+                return Ok(false);
+            }
+            if self.after_context_left == 0 {
+                break;
+            }
+        }
         Ok(true)
     }
 
@@ -244,11 +320,20 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
         buf: &[u8],
         range: &Range,
     ) -> Result<bool, S::Error> {
+        if !self.sink_break_context(range.start())? {
+            return Ok(false);
+        }
         // 更新 line_counter last_line_counted absolute_byte_offset
         self.count_lines(buf, range.start());
         let offset = self.absolute_byte_offset + range.start() as u64;
 
         let line_buf = &buf[*range];
+        let line_pattern_ids = LinePatternIds::new(&self.matcher, buf, range, self.config);
+        let pattern_ids: &dyn PatternIds = if self.matcher.pattern_count() > 1 {
+            &line_pattern_ids
+        } else {
+            &PatternID::ZERO
+        };
         // 输出匹配的行
         let keep_going = self.sink.matched(
             &self.searcher,
@@ -259,12 +344,114 @@ impl<'s, M: Matcher, S: Sink> Core<'s, M, S> {
                 line_number: self.line_number,
                 buffer: buf,
                 bytes_range_in_buffer: range.start()..range.end(),
+                pattern_ids,
             })?;
         if !keep_going {
             return Ok(false);
         }
 
         self.last_line_visited = range.end();
+        self.after_context_left = self.config.after_context;
+        self.has_sunk = true;
         Ok(true)
     }
-}
\ No newline at end of file
+
+    /// 将前置上下文行通过 Sink 输出
+    fn sink_before_context(
+        &mut self,
+        buf: &[u8],
+        range: &Range,
+    ) -> Result<bool, S::Error> {
+        self.sink_context(buf, range, SinkContextKind::Before)
+    }
+
+    /// 将后置上下文行通过 Sink 输出
+    fn sink_after_context(
+        &mut self,
+        buf: &[u8],
+        range: &Range,
+    ) -> Result<bool, S::Error> {
+        assert!(self.after_context_left >= 1);
+        if !self.sink_context(buf, range, SinkContextKind::After)? {
+            return Ok(false);
+        }
+        self.after_context_left -= 1;
+        Ok(true)
+    }
+
+    fn sink_context(
+        &mut self,
+        buf: &[u8],
+        range: &Range,
+        kind: SinkContextKind,
+    ) -> Result<bool, S::Error> {
+        self.count_lines(buf, range.start());
+        let offset = self.absolute_byte_offset + range.start() as u64;
+        let keep_going = self.sink.context(
+            self.searcher,
+            &SinkContext {
+                line_term: self.config.line_terminator,
+                bytes: &buf[*range],
+                kind,
+                absolute_byte_offset: offset,
+                line_number: self.line_number,
+            },
+        )?;
+        if !keep_going {
+            return Ok(false);
+        }
+        self.last_line_visited = range.end();
+        self.has_sunk = true;
+        Ok(true)
+    }
+
+    /// 开启了上下文，并且这一行和上一次交给 Sink 的行不相邻时，通知 Sink 这里有个间断
+    fn sink_break_context(&mut self, start_of_line: usize) -> Result<bool, S::Error> {
+        let is_gap = self.last_line_visited < start_of_line;
+        let any_context = self.config.max_context() > 0;
+        if !any_context || !self.has_sunk || !is_gap {
+            Ok(true)
+        } else {
+            self.sink.context_break(self.searcher)
+        }
+    }
+}
+/// 匹配行中匹配到的模式序号，Sink 第一次调用 SinkMatch::pattern_ids 时才查找
+struct LinePatternIds<'a, M> {
+    matcher: &'a M,
+    /// 缓冲中到匹配行结尾（不含行终止符）为止的内容，保留行首之前的内容给 look-around 使用
+    haystack: &'a [u8],
+    /// 匹配行的行首
+    at: usize,
+    pattern_ids: OnceCell<Vec<PatternID>>,
+}
+
+impl<'a, M: Matcher> LinePatternIds<'a, M> {
+    fn new(matcher: &'a M, buf: &'a [u8], range: &Range, config: &Config) -> LinePatternIds<'a, M> {
+        let line = lines::without_terminator(&buf[*range], config.line_terminator);
+        LinePatternIds {
+            matcher,
+            haystack: &buf[..range.start() + line.len()],
+            at: range.start(),
+            pattern_ids: OnceCell::new(),
+        }
+    }
+}
+
+impl<M: Matcher> PatternIds for LinePatternIds<'_, M> {
+    /// 和 Printer 查找行中匹配项一样在缓冲中查找，按序号从小到大排列
+    fn pattern_ids(&self) -> io::Result<&[PatternID]> {
+        if let Some(pattern_ids) = self.pattern_ids.get() {
+            return Ok(pattern_ids);
+        }
+        let mut pattern_ids = vec![];
+        self.matcher.find_pattern_iter_at(self.haystack, self.at, |_, pid| {
+            if !pattern_ids.contains(&pid) {
+                pattern_ids.push(pid);
+            }
+            true
+        }).map_err(|err| io::Error::other(err.to_string()))?;
+        pattern_ids.sort();
+        Ok(self.pattern_ids.get_or_init(|| pattern_ids))
+    }
+}
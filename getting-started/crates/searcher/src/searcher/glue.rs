@@ -1,3 +1,4 @@
+use bstr::ByteSlice;
 use grep_matcher::Matcher;
 use crate::line_buffer::LineBufferReader;
 use crate::searcher::Config;
@@ -16,7 +17,6 @@ pub struct ReadByLine<'s, M, R, S> {
 impl<'s, M, R, S> ReadByLine<'s, M, R, S>
 where
     M: Matcher,
-    R: std::io::Read,
     S: Sink,
 {
     pub(crate) fn new(
@@ -31,7 +31,14 @@ where
             rdr: read_from,
         }
     }
+}
 
+impl<'s, M, R, S> ReadByLine<'s, M, R, S>
+where
+    M: Matcher,
+    R: std::io::Read,
+    S: Sink,
+{
     pub(crate) fn run(mut self) -> Result<(), S::Error> {
         if self.core.begin()? {
             // 先读取一块数据到缓冲，读取成功后对缓冲中的数据进行正则匹配及输出
@@ -39,12 +46,7 @@ where
             // while self.fill()? && self.core.match_by_line(self.rdr.buffer())? {}
         }
 
-        //TODO
-        // self.core.finish(
-        //     self.rdr.absolute_byte_offset(),
-        //     self.rdr.binary_byte_offset(),
-        // )
-        Ok(())
+        self.core.finish(self.rdr.absolute_byte_offset(), self.rdr.binary_byte_offset())
     }
 
     /// 内部会调用 LineBufferReader fill() 按缓冲容量读取文件内容到缓冲
@@ -58,6 +60,12 @@ where
             Err(err) => return Err(S::Error::error_io(err)),
             Ok(did_read) => did_read,
         };
+        // 这次读取的数据中检测到了二进制数据，Quit 模式下行缓冲已经在二进制数据处截断，之前的数据仍然会被搜索
+        if let Some(binary_byte_offset) = self.rdr.binary_byte_offset() {
+            if !self.core.binary_data(binary_byte_offset)? {
+                return Ok(false);
+            }
+        }
 
         if !did_read {
             return Ok(false)
@@ -68,4 +76,98 @@ where
         }
         Ok(true)
     }
-}
\ No newline at end of file
+}
+
+/// 和同步的 run() 流程一样，只是从 AsyncRead 填充缓冲，匹配和调用 Sink 仍然是同步的
+#[cfg(feature = "tokio")]
+impl<'s, M, R, S> ReadByLine<'s, M, R, S>
+where
+    M: Matcher,
+    R: tokio::io::AsyncRead + Unpin,
+    S: Sink,
+{
+    pub(crate) async fn run_async(mut self) -> Result<(), S::Error> {
+        if self.core.begin()? {
+            while self.fill_async().await? && self.core.match_by_line(self.rdr.buffer())? {}
+        }
+        self.core.finish(self.rdr.absolute_byte_offset(), self.rdr.binary_byte_offset())
+    }
+
+    async fn fill_async(&mut self) -> Result<bool, S::Error> {
+        let old_buf_len = self.rdr.buffer().len();
+        let consumed = self.core.roll(self.rdr.buffer());
+        self.rdr.consume(consumed);
+        let did_read = match self.rdr.fill_async().await {
+            Err(err) => return Err(S::Error::error_io(err)),
+            Ok(did_read) => did_read,
+        };
+        if let Some(binary_byte_offset) = self.rdr.binary_byte_offset() {
+            if !self.core.binary_data(binary_byte_offset)? {
+                return Ok(false);
+            }
+        }
+
+        if !did_read {
+            return Ok(false)
+        }
+        if consumed == 0 && old_buf_len == self.rdr.buffer().len() {
+            self.rdr.consume(old_buf_len);
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+/// 在内存中的字节数组上按行搜索，整个 slice 就是一块完整的缓冲，不需要 roll 也不需要额外的拷贝
+#[derive(Debug)]
+pub struct SliceByLine<'s, M, S> {
+    config: &'s Config,
+    core: Core<'s, M, S>,
+    slice: &'s [u8],
+}
+
+impl<'s, M, S> SliceByLine<'s, M, S>
+where
+    M: Matcher,
+    S: Sink,
+{
+    pub(crate) fn new(
+        searcher: &'s Searcher,
+        matcher: M,
+        slice: &'s [u8],
+        write_to: S,
+    ) -> SliceByLine<'s, M, S> {
+        SliceByLine {
+            config: &searcher.config,
+            core: Core::new(searcher, matcher, write_to, false),
+            slice,
+        }
+    }
+
+    /// 检测到二进制数据时 Quit 只搜索二进制数据之前的部分，
+    /// slice 不能修改，所以 Convert 在拷贝上把二进制数据替换成行终止符，和从 reader 搜索时看到的行一致
+    pub(crate) fn run(mut self) -> Result<(), S::Error> {
+        let mut slice = self.slice;
+        let binary_byte_offset = self.config.binary.byte().and_then(|byte| slice.find_byte(byte));
+        let converted: Vec<u8>;
+        if self.core.begin()? {
+            let keep_going = match binary_byte_offset {
+                None => true,
+                Some(offset) => {
+                    if self.config.binary.quit_byte().is_some() {
+                        slice = &slice[..offset];
+                    } else if let Some(byte) = self.config.binary.byte() {
+                        let line_terminator = self.config.line_terminator.as_byte();
+                        converted = slice.iter().map(|&b| if b == byte { line_terminator } else { b }).collect();
+                        slice = &converted;
+                    }
+                    self.core.binary_data(offset as u64)?
+                }
+            };
+            if keep_going {
+                self.core.match_by_line(slice)?;
+            }
+        }
+        self.core.finish(slice.len() as u64, binary_byte_offset.map(|offset| offset as u64))
+    }
+}
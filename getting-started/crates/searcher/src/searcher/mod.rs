@@ -1,12 +1,16 @@
-use std::cell::RefCell;
 use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::{cmp, io};
+use std::{cmp, io, mem};
+use std::io::{Read, Seek, SeekFrom};
 use std::path::Path;
+use std::time::Duration;
 use encoding_rs_io::DecodeReaderBytesBuilder;
 use grep_matcher::{LineTerminator, Match, Matcher};
-use crate::line_buffer::{LineBuffer, LineBufferBuilder, LineBufferReader};
-use crate::searcher::glue::ReadByLine;
+use crate::follow::FollowReader;
+use crate::line_buffer::{
+    self, BufferAllocation, DEFAULT_BUFFER_CAPACITY, LineBuffer, LineBufferBuilder, LineBufferReader,
+};
+use crate::searcher::glue::{ReadByLine, SliceByLine};
 use crate::sink::{Sink, SinkError};
 
 mod glue;
@@ -23,12 +27,16 @@ pub struct Config {
     /// BOM 是字节序标记，可以用于标记字节序，也可以表示编码模式
     bom_sniffing: bool,
     line_terminator: LineTerminator,
-    ///
+    /// 匹配行后面的上下文行数
     after_context: usize,
-    ///
+    /// 匹配行前面的上下文行数
     before_context: usize,
     /// 是否打印匹配行的行号
     line_number: bool,
+    /// 行缓冲最多可以使用的堆内存，None 表示没有限制
+    heap_limit: Option<usize>,
+    /// 二进制数据的检测方式
+    binary: BinaryDetection,
 }
 
 impl Default for Config {
@@ -41,6 +49,8 @@ impl Default for Config {
             after_context: 0,
             before_context: 0,
             line_number: true,
+            heap_limit: None,
+            binary: BinaryDetection::default(),
         }
     }
 }
@@ -48,8 +58,16 @@ impl Default for Config {
 impl Config {
     fn line_buffer(&self) -> LineBuffer {
         let mut builder = LineBufferBuilder::new();
-        builder
-            .line_terminator(self.line_terminator.as_byte()); // ripgrep 还可以设置缓冲容量，先忽略
+        builder.line_terminator(self.line_terminator.as_byte()).binary_detection(self.binary.0);
+        // 有堆内存限制时，初始容量不超过限制，剩余的部分作为扩容上限
+        if let Some(limit) = self.heap_limit {
+            let (capacity, additional) = if limit <= DEFAULT_BUFFER_CAPACITY {
+                (limit, 0)
+            } else {
+                (DEFAULT_BUFFER_CAPACITY, limit - DEFAULT_BUFFER_CAPACITY)
+            };
+            builder.capacity(capacity).buffer_alloc(BufferAllocation::Error(additional));
+        }
         builder.build()
     }
 
@@ -58,6 +76,44 @@ impl Config {
     }
 }
 
+/// 二进制数据的检测方式，默认不检测
+/// 检测到二进制数据时会调用 Sink::binary_data，并在 SinkFinish 中报告第一个二进制数据的偏移量
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BinaryDetection(line_buffer::BinaryDetection);
+
+impl BinaryDetection {
+    /// 不检测二进制数据，所有数据都当作文本搜索
+    pub fn none() -> BinaryDetection {
+        BinaryDetection(line_buffer::BinaryDetection::None)
+    }
+
+    /// 遇到 binary_byte 时停止搜索，binary_byte 之前的数据仍然会被搜索，通常使用 `\x00`
+    pub fn quit(binary_byte: u8) -> BinaryDetection {
+        BinaryDetection(line_buffer::BinaryDetection::Quit(binary_byte))
+    }
+
+    /// 将 binary_byte 替换成行终止符后继续搜索，search_slice 不会修改传入的数据，而是在拷贝上替换
+    pub fn convert(binary_byte: u8) -> BinaryDetection {
+        BinaryDetection(line_buffer::BinaryDetection::Convert(binary_byte))
+    }
+
+    /// 是否需要在 binary_byte 处停止搜索
+    pub(crate) fn quit_byte(&self) -> Option<u8> {
+        match self.0 {
+            line_buffer::BinaryDetection::Quit(byte) => Some(byte),
+            _ => None,
+        }
+    }
+
+    /// 检测的字节，不检测时为 None
+    pub(crate) fn byte(&self) -> Option<u8> {
+        match self.0 {
+            line_buffer::BinaryDetection::None => None,
+            line_buffer::BinaryDetection::Quit(byte) | line_buffer::BinaryDetection::Convert(byte) => Some(byte),
+        }
+    }
+}
+
 /// 相当于对 encoding_rs 中的 Encoding 进行重命名
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Encoding(&'static encoding_rs::Encoding);
@@ -110,16 +166,63 @@ impl SearcherBuilder {
         Searcher {
             config,
             decode_builder,
-            decode_buffer: RefCell::new(vec![0; 8 * (1 << 10)]),    //TODO
-            line_buffer: RefCell::new(self.config.line_buffer()),         //TODO
-            multi_line_buffer: RefCell::new(vec![]),
+            decode_buffer: vec![0; 8 * (1 << 10)],    //TODO
+            line_buffer: self.config.line_buffer(),         //TODO
+            multi_line_buffer: vec![],
         }
     }
 
+    /// 设置行缓冲最多可以使用的堆内存（字节），默认没有限制
+    /// 一行数据（比如没有换行的压缩过的文件）超过这个限制时搜索返回错误，而不是无限制地扩容
+    /// 设置为 0 时无法进行任何搜索，会返回 ConfigError::SearchUnavailable
+    pub fn heap_limit(&mut self, bytes: Option<usize>) -> &mut SearcherBuilder {
+        self.config.heap_limit = bytes;
+        self
+    }
+
+    /// 设置二进制数据的检测方式，默认不检测
+    pub fn binary_detection(&mut self, detection: BinaryDetection) -> &mut SearcherBuilder {
+        self.config.binary = detection;
+        self
+    }
+
+    /// 显式指定源数据的编码，设置后所有数据都会先转码成 UTF-8 再搜索（BOM 嗅探仍然优先）
+    pub fn encoding(&mut self, encoding: Option<Encoding>) -> &mut SearcherBuilder {
+        self.config.encoding = encoding;
+        self
+    }
+
+    /// 是否开启 BOM 嗅探，默认开启，开启后会根据 BOM 将 UTF-16 数据转码成 UTF-8，并去掉 UTF-8 的 BOM
+    pub fn bom_sniffing(&mut self, yes: bool) -> &mut SearcherBuilder {
+        self.config.bom_sniffing = yes;
+        self
+    }
+
     pub fn line_number(&mut self, yes: bool) -> &mut SearcherBuilder {
         self.config.line_number = yes;
         self
     }
+
+    /// 匹配行前面需要额外交给 Sink::context 的行数，默认是 0
+    pub fn before_context(&mut self, line_count: usize) -> &mut SearcherBuilder {
+        self.config.before_context = line_count;
+        self
+    }
+
+    /// 匹配行后面需要额外交给 Sink::context 的行数，默认是 0
+    pub fn after_context(&mut self, line_count: usize) -> &mut SearcherBuilder {
+        self.config.after_context = line_count;
+        self
+    }
+
+    /// 设置行终止符，默认是 `\n`
+    /// 设置为 CRLF 时仍然以 `\n` 切分行，只是打印时会将 `\r\n` 一起作为行终止符处理；
+    /// 设置为 NUL 可以搜索 `find -print0` 这类以 NUL 分隔的记录
+    /// 注意需要和 Matcher 报告的行终止符一致，否则搜索时会返回 ConfigError::MismatchedLineTerminators
+    pub fn line_terminator(&mut self, line_term: LineTerminator) -> &mut SearcherBuilder {
+        self.config.line_terminator = line_term;
+        self
+    }
 }
 
 /// Searcher 构建过程中的错误类型枚举
@@ -145,9 +248,28 @@ pub enum ConfigError {
     },
 }
 
+impl std::error::Error for ConfigError {}
+
 impl Display for ConfigError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        todo!()
+        match *self {
+            ConfigError::SearchUnavailable => {
+                write!(f, "grep config error: no available searchers")
+            }
+            ConfigError::MismatchedLineTerminators { matcher, searcher } => {
+                write!(
+                    f,
+                    "grep config error: mismatched line terminators, \
+                     matcher has {:?} but searcher has {:?}",
+                    matcher, searcher
+                )
+            }
+            ConfigError::UnknownEncoding { ref label } => write!(
+                f,
+                "grep config error: unknown encoding: {}",
+                String::from_utf8_lossy(label),
+            ),
+        }
     }
 }
 
@@ -159,13 +281,13 @@ pub struct Searcher {
     /// 当不需要转码时，构建的转码器将传递底层字节，而不会产生额外的开销。
     decode_builder: DecodeReaderBytesBuilder,
     /// 用于转码暂存空间的缓冲区
-    decode_buffer: RefCell<Vec<u8>>,
+    decode_buffer: Vec<u8>,
     /// 用于面向行搜索的行缓冲区
-    /// 我们将其包装在 RefCell 中，以允许将“Searcher”借用到接收器。
-    /// 我们仍然需要可变借用来执行搜索，因此我们静态地防止调用者由于借用违规而导致 RefCell 在运行时出现panic。
-    line_buffer: RefCell<LineBuffer>,
+    /// 搜索时需要可变借用行缓冲，同时将“Searcher”借用到接收器，所以搜索时先把缓冲从 Searcher 中取出来，搜索结束后再放回去。
+    /// 这样 Searcher 中没有 RefCell，是 Sync 的，异步搜索时跨越 await 持有 &Searcher 也不会让 Future 变成 !Send
+    line_buffer: LineBuffer,
     /// 执行多行搜索时用于存储读取器内容的缓冲区。特别是，多行搜索无法增量执行，并且需要一次性将整个干草堆存储在内存中
-    multi_line_buffer: RefCell<Vec<u8>>,
+    multi_line_buffer: Vec<u8>,
 }
 
 impl Searcher {
@@ -182,10 +304,46 @@ impl Searcher {
         M: Matcher,
         S: Sink,
     {
+        self.check_config(&matcher).map_err(S::Error::error_config)?;
         let file = File::open(path).map_err(S::Error::error_io)?;
         self.search_file_maybe_path(matcher, Some(path), &file, write_to)
     }
 
+    /// 类似 `tail -F | grep`，搜索完文件现有的内容后继续等待并搜索追加的内容，每隔 poll_interval 检查一次新数据
+    /// 文件被截断或者轮转（inode 变化）后从新文件的开头继续搜索，行号和字节偏移量继续累加，不会重新计数
+    /// 这个方法只有在 Sink 返回 false 或者出错时才会返回
+    pub fn search_path_follow<M, S>(
+        &mut self,
+        matcher: M,
+        path: &Path,
+        poll_interval: Duration,
+        write_to: S,
+    ) -> Result<(), S::Error>
+    where
+        M: Matcher,
+        S: Sink,
+    {
+        self.check_config(&matcher).map_err(S::Error::error_config)?;
+        let rdr = FollowReader::open(path, poll_interval).map_err(S::Error::error_io)?;
+        log::trace!("{:?}: searching in follow mode", path);
+        self.search_reader(matcher, rdr, write_to)
+    }
+
+    /// 执行基于已打开文件的搜索，和 search_path 一样，只是文件由调用方打开
+    pub fn search_file<M, S>(
+        &mut self,
+        matcher: M,
+        file: &File,
+        write_to: S,
+    ) -> Result<(), S::Error>
+    where
+        M: Matcher,
+        S: Sink,
+    {
+        self.check_config(&matcher).map_err(S::Error::error_config)?;
+        self.search_file_maybe_path(matcher, None, file, write_to)
+    }
+
     ///
     fn search_file_maybe_path<M, S>(
         &mut self,
@@ -202,10 +360,71 @@ impl Searcher {
         // 还支持多行匹配模式 TODO 后面研究
 
         // 这里展示传统的文件读取方式
+        // 不需要转码时（比如开启了 BOM 嗅探但文件开头没有 BOM），跳过 DecodeReaderBytes 直接将文件内容读到行缓冲，省去一次转码缓冲的拷贝
+        if !self.file_needs_transcoding(file).map_err(S::Error::error_io)? {
+            log::trace!("{:?}: searching using generic reader without transcoding", path);
+            let mut line_buffer = mem::take(&mut self.line_buffer);
+            let rdr = LineBufferReader::new(file, &mut line_buffer);
+            let result = ReadByLine::new(self, matcher, rdr, write_to).run();
+            self.line_buffer = line_buffer;
+            return result;
+        }
         log::trace!("{:?}: searching using generic reader", path);
         self.search_reader(matcher, file, write_to)
     }
 
+    /// 在内存中的字节数组上搜索
+    /// 不需要转码时直接在 slice 上匹配，不经过 DecodeReaderBytes 和行缓冲，否则退化成 search_reader
+    pub fn search_slice<M, S>(
+        &mut self,
+        matcher: M,
+        slice: &[u8],
+        write_to: S,
+    ) -> Result<(), S::Error>
+    where
+        M: Matcher,
+        S: Sink,
+    {
+        self.check_config(&matcher).map_err(S::Error::error_config)?;
+        if self.slice_needs_transcoding(slice) {
+            log::trace!("slice reader: needs transcoding, using generic reader");
+            return self.search_reader(matcher, slice, write_to);
+        }
+        log::trace!("slice reader: searching via slice-by-line strategy");
+        SliceByLine::new(self, matcher, slice, write_to).run()
+    }
+
+    /// 文件是否需要转码，显式指定了编码时总是需要，开启 BOM 嗅探时读取文件开头检查有没有 BOM，检查完后文件位置恢复原样
+    /// 文件不支持 seek（比如管道）时无法先检查 BOM，只能交给 DecodeReaderBytes 处理
+    fn file_needs_transcoding(&self, file: &File) -> io::Result<bool> {
+        if self.config.encoding.is_some() {
+            return Ok(true);
+        }
+        if !self.config.bom_sniffing {
+            return Ok(false);
+        }
+        let mut file = file;
+        let Ok(pos) = file.stream_position() else { return Ok(true) };
+        let mut bom = [0; 3];
+        let mut len = 0;
+        while len < bom.len() {
+            match file.read(&mut bom[len..]) {
+                Ok(0) => break,
+                Ok(n) => len += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        file.seek(SeekFrom::Start(pos))?;
+        Ok(encoding_rs::Encoding::for_bom(&bom[..len]).is_some())
+    }
+
+    /// slice 是否需要转码，和 file_needs_transcoding 一样检查 slice 开头有没有 BOM
+    fn slice_needs_transcoding(&self, slice: &[u8]) -> bool {
+        self.config.encoding.is_some()
+            || (self.config.bom_sniffing && encoding_rs::Encoding::for_bom(slice).is_some())
+    }
+
     /// 判断是否使用多行匹配模式
     pub fn multi_line_with_matcher<M: Matcher>(&self, matcher: M) -> bool {
         if !self.multi_line() {
@@ -233,23 +452,534 @@ impl Searcher {
         R: io::Read,
         S: Sink,
     {
+        self.check_config(&matcher).map_err(S::Error::error_config)?;
+
         // 1 创建编码转换器
-        let mut decode_buffer = self.decode_buffer.borrow_mut();
-        let decoder = self.decode_builder
-            .build_with_buffer(read_from, &mut *decode_buffer)
-            .map_err(S::Error::error_io)?;
+        let mut decode_buffer = mem::take(&mut self.decode_buffer);
+        let decoder = match self.decode_builder.build_with_buffer(read_from, &mut decode_buffer) {
+            Ok(decoder) => decoder,
+            Err(err) => {
+                self.decode_buffer = decode_buffer;
+                return Err(S::Error::error_io(err));
+            }
+        };
 
         // 2 创建 LineBufferReader 用于将文件内容读取到缓冲
-        let mut line_buffer = self.line_buffer.borrow_mut();
+        let mut line_buffer = mem::take(&mut self.line_buffer);
         // let rdr = LineBufferReader::new(read_from, &mut *line_buffer);
-        let rdr = LineBufferReader::new(decoder, &mut *line_buffer);
+        let rdr = LineBufferReader::new(decoder, &mut line_buffer);
 
         // 3
         log::trace!("generic reader: searching via roll buffer strategy");
-        ReadByLine::new(self, matcher, rdr, write_to).run()
+        let result = ReadByLine::new(self, matcher, rdr, write_to).run();
+        self.line_buffer = line_buffer;
+        self.decode_buffer = decode_buffer;
+        result
+    }
+
+    /// 从 tokio::io::AsyncRead（比如 HTTP 响应体、socket）读取数据并搜索，等待数据时不会阻塞线程
+    /// 只有读取是异步的，对读取到的数据进行匹配以及调用 Sink 都是同步的，所以 Sink 中不要做阻塞操作，
+    /// 需要异步处理匹配结果时可以在 Sink 中把结果发送到 channel，由其他任务处理
+    /// 注意异步搜索不支持转码，数据按原始字节搜索
+    #[cfg(feature = "tokio")]
+    pub async fn search_async_reader<M, R, S>(
+        &mut self,
+        matcher: M,
+        read_from: R,
+        write_to: S,
+    ) -> Result<(), S::Error>
+    where
+        M: Matcher,
+        R: tokio::io::AsyncRead + Unpin,
+        S: Sink,
+    {
+        self.check_config(&matcher).map_err(S::Error::error_config)?;
+
+        let mut line_buffer = mem::take(&mut self.line_buffer);
+        let rdr = LineBufferReader::new(read_from, &mut line_buffer);
+        log::trace!("async reader: searching via roll buffer strategy");
+        let result = ReadByLine::new(self, matcher, rdr, write_to).run_async().await;
+        self.line_buffer = line_buffer;
+        result
     }
 
     pub fn line_terminator(&self) -> LineTerminator {
         self.config.line_terminator
     }
-}
\ No newline at end of file
+
+    /// 检查 Searcher 配置和 Matcher 是否兼容
+    /// Matcher 报告了行终止符时，必须和 Searcher 的行终止符一致，否则按行搜索的结果是错误的
+    fn check_config<M: Matcher>(&self, matcher: M) -> Result<(), ConfigError> {
+        // ripgrep 在堆内存限制为 0 时还可以使用 mmap 搜索，这里只有基于行缓冲的搜索
+        if self.config.heap_limit == Some(0) {
+            return Err(ConfigError::SearchUnavailable);
+        }
+        let matcher_line_term = match matcher.line_terminator() {
+            None => return Ok(()),
+            Some(line_term) => line_term,
+        };
+        if matcher_line_term != self.config.line_terminator {
+            return Err(ConfigError::MismatchedLineTerminators {
+                matcher: matcher_line_term,
+                searcher: self.config.line_terminator,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// search_async_reader 返回的 Future 是 Send 的，可以交给 tokio::spawn 在多线程运行时中执行
+#[cfg(feature = "tokio")]
+#[allow(dead_code)]
+fn assert_search_async_reader_is_send<M, R, S>(searcher: &mut Searcher, matcher: M, read_from: R, write_to: S)
+where
+    M: Matcher + Send,
+    R: tokio::io::AsyncRead + Unpin + Send,
+    S: Sink + Send,
+{
+    fn is_send<T: Send>(_: T) {}
+    is_send(searcher.search_async_reader(matcher, read_from, write_to));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Seek, SeekFrom, Write};
+    use grep_matcher::LineTerminator;
+    use crate::searcher::{BinaryDetection, ConfigError, SearcherBuilder};
+    use crate::sinks;
+    use crate::testutil::{KitchenSink, LiteralMatcher};
+
+    const SHERLOCK: &str = "\
+For the Doctor Watsons of this world, as opposed to the Sherlock
+Holmeses, success in the province of detective work must always
+be, to a very large extent, the result of luck.
+";
+
+    #[test]
+    fn search_reader_basic() {
+        let mut searcher = SearcherBuilder::new().build();
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(LiteralMatcher::new("Sherlock"), SHERLOCK.as_bytes(), &mut sink).unwrap();
+        assert_eq!(
+            "1:For the Doctor Watsons of this world, as opposed to the Sherlock\n",
+            String::from_utf8_lossy(sink.as_bytes())
+        );
+    }
+
+    /// 搜索结束时 Sink::finish 报告搜索过的总字节数，包括最后一行没有行终止符的情况
+    #[test]
+    fn finish_byte_count() {
+        let mut searcher = SearcherBuilder::new().build();
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(LiteralMatcher::new("Sherlock"), SHERLOCK.as_bytes(), &mut sink).unwrap();
+        assert_eq!(Some(SHERLOCK.len() as u64), sink.byte_count());
+
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(LiteralMatcher::new("x"), "abc\ndef".as_bytes(), &mut sink).unwrap();
+        assert_eq!(Some(7), sink.byte_count());
+    }
+
+    /// 使用 CRLF 作为行终止符时仍然按 `\n` 切分行
+    #[test]
+    fn search_reader_crlf() {
+        let mut searcher = SearcherBuilder::new()
+            .line_terminator(LineTerminator::crlf())
+            .build();
+        let matcher = LiteralMatcher::new("b").line_terminator(Some(LineTerminator::crlf()));
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(matcher, "a\r\nb\r\nc\r\n".as_bytes(), &mut sink).unwrap();
+        assert_eq!("2:b\r\n", String::from_utf8_lossy(sink.as_bytes()));
+    }
+
+    /// 使用 NUL 作为行终止符，比如搜索 `find -print0` 的输出
+    #[test]
+    fn search_reader_null_data() {
+        let mut searcher = SearcherBuilder::new()
+            .line_terminator(LineTerminator::byte(b'\x00'))
+            .build();
+        let matcher = LiteralMatcher::new(".rs").line_terminator(Some(LineTerminator::byte(b'\x00')));
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(matcher, "./a.rs\x00./b.toml\x00./c.rs\x00".as_bytes(), &mut sink).unwrap();
+        assert_eq!("1:./a.rs\x003:./c.rs\x00", String::from_utf8_lossy(sink.as_bytes()));
+    }
+
+    /// 匹配器没有报告行终止符时逐行匹配，匹配结果不会跨行
+    #[test]
+    fn search_reader_line_by_line_slow() {
+        let mut searcher = SearcherBuilder::new().build();
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(LiteralMatcher::new("Sherlock\nHolmeses"), SHERLOCK.as_bytes(), &mut sink).unwrap();
+        assert!(sink.as_bytes().is_empty());
+
+        let matcher = LiteralMatcher::new("luck").line_terminator(Some(LineTerminator::default()));
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(matcher, SHERLOCK.as_bytes(), &mut sink).unwrap();
+        assert_eq!(
+            "3:be, to a very large extent, the result of luck.\n",
+            String::from_utf8_lossy(sink.as_bytes())
+        );
+    }
+
+    /// Matcher 和 Searcher 的行终止符不一致时返回 ConfigError::MismatchedLineTerminators
+    #[test]
+    fn mismatched_line_terminators() {
+        let mut searcher = SearcherBuilder::new().build();
+        let matcher = LiteralMatcher::new("a").line_terminator(Some(LineTerminator::byte(b'\x00')));
+        let err = searcher.search_reader(matcher, "a\n".as_bytes(), KitchenSink::new()).unwrap_err();
+        let expected = ConfigError::MismatchedLineTerminators {
+            matcher: LineTerminator::byte(b'\x00'),
+            searcher: LineTerminator::byte(b'\n'),
+        };
+        assert_eq!(expected.to_string(), err.to_string());
+    }
+
+    const CONTEXT: &str = "a\nb\nmatch1\nc\nd\ne\nf\nmatch2\ng\nmatch3\nh\n";
+
+    /// 前后上下文行通过 Sink::context 交给 Sink，不相邻的上下文之间调用 Sink::context_break
+    #[test]
+    fn context_before_after() {
+        let expected = "\
+2-b
+3:match1
+4-c
+--
+7-f
+8:match2
+9-g
+10:match3
+11-h
+";
+        let mut searcher = SearcherBuilder::new().before_context(1).after_context(1).build();
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(LiteralMatcher::new("match"), CONTEXT.as_bytes(), &mut sink).unwrap();
+        assert_eq!(expected, String::from_utf8_lossy(sink.as_bytes()));
+
+        // 逐行匹配的慢速路径结果一致
+        let matcher = LiteralMatcher::new("match").line_terminator(Some(LineTerminator::default()));
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(matcher, CONTEXT.as_bytes(), &mut sink).unwrap();
+        assert_eq!(expected, String::from_utf8_lossy(sink.as_bytes()));
+    }
+
+    /// 上下文相互重叠或者相邻时不会重复输出，也不会有 context_break
+    #[test]
+    fn context_overlap() {
+        let mut searcher = SearcherBuilder::new().before_context(2).build();
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(LiteralMatcher::new("match"), CONTEXT.as_bytes(), &mut sink).unwrap();
+        assert_eq!(
+            "1-a\n2-b\n3:match1\n--\n6-e\n7-f\n8:match2\n9-g\n10:match3\n",
+            String::from_utf8_lossy(sink.as_bytes())
+        );
+    }
+
+    /// Sink::begin 返回 false 时不进行搜索，但仍然调用 Sink::finish
+    #[test]
+    fn begin_stops_search() {
+        let mut searcher = SearcherBuilder::new().build();
+        let mut sink = KitchenSink::new().stop_at_begin();
+        searcher.search_reader(LiteralMatcher::new("Sherlock"), SHERLOCK.as_bytes(), &mut sink).unwrap();
+        assert!(sink.as_bytes().is_empty());
+        assert_eq!(Some(0), sink.byte_count());
+    }
+
+    #[test]
+    fn sinks_utf8_lossy_bytes() {
+        let mut searcher = SearcherBuilder::new().build();
+        let haystack = b"foo\nbar \xFF\nfoobar\n";
+
+        let mut lines = vec![];
+        searcher.search_reader(LiteralMatcher::new("foo"), &haystack[..], sinks::UTF8(|n, line| {
+            lines.push((n, line.to_string()));
+            Ok(true)
+        })).unwrap();
+        assert_eq!(vec![(1, "foo\n".to_string()), (3, "foobar\n".to_string())], lines);
+
+        // 无效的 UTF-8 使用 UTF8 时返回错误，使用 Lossy 时替换成 U+FFFD
+        let result = searcher.search_reader(LiteralMatcher::new("bar"), &haystack[..], sinks::UTF8(|_, _| Ok(true)));
+        assert!(result.is_err());
+        let mut lines = vec![];
+        searcher.search_reader(LiteralMatcher::new("bar"), &haystack[..], sinks::Lossy(|n, line| {
+            lines.push((n, line.to_string()));
+            Ok(true)
+        })).unwrap();
+        assert_eq!(vec![(2, "bar \u{FFFD}\n".to_string()), (3, "foobar\n".to_string())], lines);
+
+        // 闭包返回 false 时停止搜索
+        let mut lines = vec![];
+        searcher.search_reader(LiteralMatcher::new("bar"), &haystack[..], sinks::Bytes(|n, line| {
+            lines.push((n, line.to_vec()));
+            Ok(false)
+        })).unwrap();
+        assert_eq!(vec![(2, b"bar \xFF\n".to_vec())], lines);
+    }
+
+    /// sinks 中的 Sink 需要行号，没有开启行号时返回错误
+    #[test]
+    fn sinks_require_line_number() {
+        let mut searcher = SearcherBuilder::new().line_number(false).build();
+        let err = searcher
+            .search_reader(LiteralMatcher::new("foo"), "foo\n".as_bytes(), sinks::UTF8(|_, _| Ok(true)))
+            .unwrap_err();
+        assert!(err.to_string().contains("line numbers not enabled"), "{}", err);
+    }
+
+    /// 检测到二进制数据时调用 Sink::binary_data，Quit 只搜索二进制数据之前的部分，Convert 把它当作行终止符
+    #[test]
+    fn binary_detection() {
+        let haystack = b"match1\nfoo\x00bar\nmatch2\n";
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let path = file.path();
+        std::fs::write(path, haystack).unwrap();
+        let search = |detection: BinaryDetection| {
+            let mut searcher = SearcherBuilder::new().binary_detection(detection).build();
+            let matcher = LiteralMatcher::new("match");
+            let mut results = vec![];
+            let mut sink = KitchenSink::new();
+            searcher.search_path(&matcher, path, &mut sink).unwrap();
+            results.push(sink);
+            let mut sink = KitchenSink::new();
+            searcher.search_reader(&matcher, &haystack[..], &mut sink).unwrap();
+            results.push(sink);
+            let mut sink = KitchenSink::new();
+            searcher.search_slice(&matcher, haystack, &mut sink).unwrap();
+            results.push(sink);
+            results.into_iter()
+                .map(|sink| {
+                    let output = String::from_utf8_lossy(sink.as_bytes()).into_owned();
+                    (output, sink.binary_offsets().to_vec(), sink.binary_byte_offset())
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let none = ("1:match1\n3:match2\n".to_string(), vec![], None);
+        assert_eq!(vec![none.clone(), none.clone(), none], search(BinaryDetection::none()));
+        let quit = ("1:match1\n".to_string(), vec![10], Some(10));
+        assert_eq!(vec![quit.clone(), quit.clone(), quit], search(BinaryDetection::quit(b'\x00')));
+        let convert = ("1:match1\n4:match2\n".to_string(), vec![10], Some(10));
+        assert_eq!(vec![convert.clone(), convert.clone(), convert], search(BinaryDetection::convert(b'\x00')));
+    }
+
+    const ENTRY_POINTS: &str = "a\nmatch1\nb\nc\nd\nmatch2\ne";
+
+    /// 用 search_reader、search_slice、search_file 三种方式搜索，返回各自 Sink 收到的事件
+    fn search_all(builder: &SearcherBuilder, haystack: &[u8]) -> [(String, Option<u64>); 3] {
+        let matcher = LiteralMatcher::new("match");
+        let mut searcher = builder.build();
+        let collect = |sink: KitchenSink| {
+            (String::from_utf8_lossy(sink.as_bytes()).into_owned(), sink.byte_count())
+        };
+
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(&matcher, haystack, &mut sink).unwrap();
+        let reader = collect(sink);
+
+        let mut sink = KitchenSink::new();
+        searcher.search_slice(&matcher, haystack, &mut sink).unwrap();
+        let slice = collect(sink);
+
+        // 每次调用使用各自的匿名临时文件，并行运行的测试不会互相覆盖
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(haystack).unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        let mut sink = KitchenSink::new();
+        searcher.search_file(&matcher, &file, &mut sink).unwrap();
+        let file = collect(sink);
+
+        [reader, slice, file]
+    }
+
+    /// 三种搜索入口共用 Core，Sink 收到的事件完全一致
+    #[test]
+    fn entry_points_identical() {
+        let expected = (
+            "1-a\n2:match1\n3-b\n--\n5-d\n6:match2\n7-e".to_string(),
+            Some(ENTRY_POINTS.len() as u64),
+        );
+        let mut builder = SearcherBuilder::new();
+        builder.before_context(1).after_context(1);
+        for result in search_all(&builder, ENTRY_POINTS.as_bytes()) {
+            assert_eq!(expected, result);
+        }
+
+        // 关闭 BOM 嗅探后 search_file 不经过转码
+        let mut builder = SearcherBuilder::new();
+        builder.line_number(false).bom_sniffing(false);
+        let expected = ("match1\nmatch2\n".to_string(), Some(ENTRY_POINTS.len() as u64));
+        for result in search_all(&builder, ENTRY_POINTS.as_bytes()) {
+            assert_eq!(expected, result);
+        }
+    }
+
+    /// 带 BOM 的数据需要转码，三种入口都会去掉 BOM
+    #[test]
+    fn entry_points_bom() {
+        let haystack = b"\xEF\xBB\xBFmatch\nfoo\n";
+        for (output, _) in search_all(&SearcherBuilder::new(), haystack) {
+            assert_eq!("1:match\n", output);
+        }
+    }
+
+    /// 开启 BOM 嗅探时只有开头有 BOM 的文件需要转码，检查 BOM 不会改变文件的读取位置
+    #[test]
+    fn file_needs_transcoding() {
+        use crate::searcher::Encoding;
+
+        let temp = tempfile::NamedTempFile::new().unwrap();
+        let needs_transcoding = |builder: &SearcherBuilder, contents: &[u8]| {
+            std::fs::write(temp.path(), contents).unwrap();
+            let mut file = std::fs::File::open(temp.path()).unwrap();
+            file.seek(SeekFrom::Start(1)).unwrap();
+            let yes = builder.build().file_needs_transcoding(&file).unwrap();
+            assert_eq!(1, file.stream_position().unwrap());
+            file.seek(SeekFrom::Start(0)).unwrap();
+            (yes, builder.build().file_needs_transcoding(&file).unwrap())
+        };
+        let mut builder = SearcherBuilder::new();
+        assert_eq!((false, false), needs_transcoding(&builder, b"match\n"));
+        assert_eq!((false, false), needs_transcoding(&builder, b""));
+        assert_eq!((false, true), needs_transcoding(&builder, b"\xFF\xFEm\x00"));
+        builder.bom_sniffing(false);
+        assert_eq!((false, false), needs_transcoding(&builder, b"\xEF\xBB\xBFmatch\n"));
+        builder.encoding(Some(Encoding::new("utf-16le").unwrap()));
+        assert_eq!((true, true), needs_transcoding(&builder, b"match\n"));
+    }
+
+    /// 生成一个指定长度、没有行终止符的超长行，不需要真的分配这么多内存
+    struct LongLine {
+        remaining: u64,
+    }
+
+    impl std::io::Read for LongLine {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = std::cmp::min(buf.len() as u64, self.remaining) as usize;
+            buf[..n].fill(b'a');
+            self.remaining -= n as u64;
+            Ok(n)
+        }
+    }
+
+    /// 1GB 的单行数据超过堆内存限制时返回错误，不会一直扩容
+    #[test]
+    fn heap_limit_long_line() {
+        let mut searcher = SearcherBuilder::new().heap_limit(Some(1 << 20)).build();
+        let rdr = LongLine { remaining: 1 << 30 };
+        let err = searcher.search_reader(LiteralMatcher::new("b"), rdr, KitchenSink::new()).unwrap_err();
+        assert_eq!("configured allocation limit (1048576) exceeded", err.to_string());
+
+        // 没有超过限制的行可以正常搜索
+        let mut sink = KitchenSink::new();
+        searcher.search_reader(LiteralMatcher::new("Sherlock"), SHERLOCK.as_bytes(), &mut sink).unwrap();
+        assert!(!sink.as_bytes().is_empty());
+    }
+
+    #[test]
+    fn heap_limit_zero() {
+        let mut searcher = SearcherBuilder::new().heap_limit(Some(0)).build();
+        let err = searcher.search_slice(LiteralMatcher::new("a"), b"a\n", KitchenSink::new()).unwrap_err();
+        assert_eq!(ConfigError::SearchUnavailable.to_string(), err.to_string());
+    }
+
+    /// 从 tokio::io::duplex 的一端异步搜索，另一端分多次写入数据，行可能被拆分在两次写入中
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn search_async_reader_duplex() {
+        use tokio::io::AsyncWriteExt;
+
+        let (mut client, server) = tokio::io::duplex(16);
+        let writer = tokio::spawn(async move {
+            for chunk in SHERLOCK.as_bytes().chunks(10) {
+                client.write_all(chunk).await.unwrap();
+                tokio::task::yield_now().await;
+            }
+            // 关闭写端，读端才能读到 EOF
+            client.shutdown().await.unwrap();
+        });
+
+        let mut searcher = SearcherBuilder::new().after_context(1).build();
+        let mut sink = KitchenSink::new();
+        searcher.search_async_reader(LiteralMatcher::new("Sherlock"), server, &mut sink).await.unwrap();
+        writer.await.unwrap();
+        assert_eq!(
+            "\
+1:For the Doctor Watsons of this world, as opposed to the Sherlock
+2-Holmeses, success in the province of detective work must always
+",
+            String::from_utf8_lossy(sink.as_bytes())
+        );
+        assert_eq!(Some(SHERLOCK.len() as u64), sink.byte_count());
+    }
+
+    /// follow 模式下读到文件末尾后继续搜索追加的内容，行号是连续的
+    #[test]
+    fn search_path_follow() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), "match1\nfoo\nmat").unwrap();
+        let writer = {
+            let path = file.path().to_path_buf();
+            std::thread::spawn(move || {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                let mut file = std::fs::OpenOptions::new().append(true).open(&path).unwrap();
+                std::io::Write::write_all(&mut file, b"ch2\nbar\nmatch3\n").unwrap();
+            })
+        };
+
+        let mut searcher = SearcherBuilder::new().build();
+        let mut matches = vec![];
+        let sink = sinks::UTF8(|line_number, line| {
+            matches.push(format!("{}:{}", line_number, line));
+            Ok(matches.len() < 3)
+        });
+        let result = searcher.search_path_follow(
+            LiteralMatcher::new("match"),
+            file.path(),
+            std::time::Duration::from_millis(1),
+            sink,
+        );
+        writer.join().unwrap();
+        result.unwrap();
+        assert_eq!(vec!["1:match1\n", "3:match2\n", "5:match3\n"], matches);
+    }
+
+    /// 记录每个匹配行匹配到的模式序号
+    #[derive(Default)]
+    struct PatternIdSink(Vec<(u64, Vec<usize>)>);
+
+    impl crate::Sink for PatternIdSink {
+        type Error = std::io::Error;
+
+        fn matched(&mut self, _searcher: &crate::Searcher, mat: &crate::SinkMatch<'_>) -> Result<bool, std::io::Error> {
+            let pattern_ids = mat.pattern_ids()?.iter().map(|pid| pid.as_usize()).collect();
+            self.0.push((mat.line_number().unwrap(), pattern_ids));
+            Ok(true)
+        }
+    }
+
+    /// 组合匹配器的模式序号通过 SinkMatch 传给 Sink，逐行匹配和整块匹配结果一致
+    #[test]
+    fn sink_match_pattern_ids() {
+        use grep_matcher::{AndMatcher, NotMatcher, SetMatcher};
+
+        let haystack = "foo bar\nbaz\nbar foo baz\nqux\n";
+        for line_term in [None, Some(LineTerminator::byte(b'\n'))] {
+            let literal = |s| LiteralMatcher::new(s).line_terminator(line_term);
+            let mut searcher = SearcherBuilder::new().build();
+
+            let mut sink = PatternIdSink::default();
+            let matcher = SetMatcher::new(vec![literal("foo"), literal("bar"), literal("baz")]);
+            searcher.search_slice(matcher, haystack.as_bytes(), &mut sink).unwrap();
+            assert_eq!(vec![(1, vec![0, 1]), (2, vec![2]), (3, vec![0, 1, 2])], sink.0);
+
+            // 包含 foo 但不包含 baz 的行
+            let mut sink = PatternIdSink::default();
+            let matcher = AndMatcher::new(literal("foo"), NotMatcher::new(literal("baz")));
+            searcher.search_slice(matcher, haystack.as_bytes(), &mut sink).unwrap();
+            assert_eq!(vec![(1, vec![0])], sink.0);
+
+            // 只有一个模式时不需要查找
+            let mut sink = PatternIdSink::default();
+            searcher.search_slice(literal("qux"), haystack.as_bytes(), &mut sink).unwrap();
+            assert_eq!(vec![(4, vec![0])], sink.0);
+        }
+    }
+}
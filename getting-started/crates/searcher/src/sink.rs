@@ -1,6 +1,7 @@
-use std::io;
-use grep_matcher::LineTerminator;
+use std::{fmt, io};
+use grep_matcher::{LineTerminator, PatternID};
 use crate::Searcher;
+use crate::lines::LineIter;
 use crate::searcher::{ConfigError};
 
 /// Sink 代表输出的意思
@@ -14,6 +15,52 @@ pub trait Sink {
         _searcher: &Searcher,
         _mat: &SinkMatch<'_>,
     ) -> Result<bool, Self::Error>;
+
+    /// 找到上下文行（通过 SearcherBuilder::before_context/after_context 开启）后调用，返回 false 会终止搜索
+    #[inline]
+    fn context(
+        &mut self,
+        _searcher: &Searcher,
+        _context: &SinkContext<'_>,
+    ) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    /// 开启上下文时，两组不相邻的匹配行（包括它们的上下文行）之间调用，比如 grep 会在这里输出 `--`
+    #[inline]
+    fn context_break(
+        &mut self,
+        _searcher: &Searcher,
+    ) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    /// 检测到二进制数据（通过 SearcherBuilder::binary_detection 开启）时调用，每次搜索最多调用一次
+    /// binary_byte_offset 是第一个二进制数据的绝对偏移量，返回 false 会终止搜索
+    #[inline]
+    fn binary_data(
+        &mut self,
+        _searcher: &Searcher,
+        _binary_byte_offset: u64,
+    ) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    /// 一次搜索开始前调用，返回 false 会直接结束搜索（仍然会调用 finish）
+    #[inline]
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    /// 一次搜索结束时调用（无论是正常结束还是被 Sink 提前终止），默认什么也不做
+    #[inline]
+    fn finish(
+        &mut self,
+        _searcher: &Searcher,
+        _: &SinkFinish,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 pub trait SinkError: Sized {
@@ -48,9 +95,105 @@ impl<'a, S: Sink> Sink for &'a mut S {
     fn matched(&mut self, searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, Self::Error> {
         (**self).matched(searcher, mat)
     }
+
+    fn context(&mut self, searcher: &Searcher, context: &SinkContext<'_>) -> Result<bool, Self::Error> {
+        (**self).context(searcher, context)
+    }
+
+    fn context_break(&mut self, searcher: &Searcher) -> Result<bool, Self::Error> {
+        (**self).context_break(searcher)
+    }
+
+    fn binary_data(&mut self, searcher: &Searcher, binary_byte_offset: u64) -> Result<bool, Self::Error> {
+        (**self).binary_data(searcher, binary_byte_offset)
+    }
+
+    fn begin(&mut self, searcher: &Searcher) -> Result<bool, Self::Error> {
+        (**self).begin(searcher)
+    }
+
+    fn finish(&mut self, searcher: &Searcher, sink_finish: &SinkFinish) -> Result<(), Self::Error> {
+        (**self).finish(searcher, sink_finish)
+    }
+}
+
+/// 一次搜索结束时传给 Sink 的汇总信息
+#[derive(Clone, Debug)]
+pub struct SinkFinish {
+    pub(crate) byte_count: u64,
+    pub(crate) binary_byte_offset: Option<u64>,
+}
+
+impl SinkFinish {
+    /// 本次搜索总共搜索过的字节数
+    #[inline]
+    pub fn byte_count(&self) -> u64 {
+        self.byte_count
+    }
+
+    /// 检测到的二进制数据的绝对偏移量，没有检测到二进制数据（或没有开启二进制检测）时为 None
+    #[inline]
+    pub fn binary_byte_offset(&self) -> Option<u64> {
+        self.binary_byte_offset
+    }
+}
+
+/// 上下文行的类型
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SinkContextKind {
+    /// 匹配行前面的上下文行
+    Before,
+    /// 匹配行后面的上下文行
+    After,
+}
+
+/// 用于描述上下文行信息的类型
+#[derive(Clone, Debug)]
+pub struct SinkContext<'b> {
+    pub(crate) line_term: LineTerminator,
+    /// 上下文行的字节数组（包含行终止符）
+    pub(crate) bytes: &'b [u8],
+    pub(crate) kind: SinkContextKind,
+    pub(crate) absolute_byte_offset: u64,
+    pub(crate) line_number: Option<u64>,
+}
+
+impl<'b> SinkContext<'b> {
+    #[inline]
+    pub fn bytes(&self) -> &'b [u8] {
+        self.bytes
+    }
+
+    /// 搜索使用的行终止符，可以用来去掉上下文行末尾的行终止符
+    #[inline]
+    pub fn line_terminator(&self) -> LineTerminator {
+        self.line_term
+    }
+
+    /// 迭代上下文中的每一行，和 SinkMatch::lines() 一样
+    #[inline]
+    pub fn lines(&self) -> LineIter<'b> {
+        LineIter::new(self.line_term, self.bytes)
+    }
+
+    #[inline]
+    pub fn kind(&self) -> &SinkContextKind {
+        &self.kind
+    }
+
+    #[inline]
+    pub fn absolute_byte_offset(&self) -> u64 {
+        self.absolute_byte_offset
+    }
+
+    #[inline]
+    pub fn line_number(&self) -> Option<u64> {
+        self.line_number
+    }
 }
 
 /// 用于描述匹配行信息的类型
+/// 多行模式下一个匹配可能跨越多行，这时 bytes 包含所有这些行，可以用 lines() 逐行处理
 #[derive(Clone, Debug)]
 pub struct SinkMatch<'b> {
     pub(crate) line_term: LineTerminator,
@@ -62,6 +205,8 @@ pub struct SinkMatch<'b> {
     pub(crate) buffer: &'b [u8],
     /// 匹配的行在缓冲中的范围
     pub(crate) bytes_range_in_buffer: std::ops::Range<usize>,
+    /// 匹配行中匹配到的模式序号，Sink 需要时才查找
+    pub(crate) pattern_ids: &'b dyn PatternIds,
 }
 
 impl<'b> SinkMatch<'b> {
@@ -80,13 +225,155 @@ impl<'b> SinkMatch<'b> {
         self.bytes
     }
 
+    /// 迭代匹配中的每一行（包含行终止符），调用 without_terminator() 可以去掉行终止符
+    #[inline]
+    pub fn lines(&self) -> LineIter<'b> {
+        LineIter::new(self.line_term, self.bytes)
+    }
+
+    /// 搜索使用的行终止符
+    #[inline]
+    pub fn line_terminator(&self) -> LineTerminator {
+        self.line_term
+    }
+
     #[inline]
     pub fn absolute_byte_offset(&self) -> u64 {
         self.absolute_byte_offset
     }
 
+    /// 匹配中第一行的行号
     #[inline]
     pub fn line_number(&self) -> Option<u64> {
         self.line_number
     }
-}
\ No newline at end of file
+
+    /// 匹配行中匹配到的模式序号，按从小到大排列且不重复
+    /// 匹配器有多个模式时第一次调用才会在匹配行上重新查找，只有一个模式时总是 [PatternID::ZERO]
+    #[inline]
+    pub fn pattern_ids(&self) -> io::Result<&[PatternID]> {
+        self.pattern_ids.pattern_ids()
+    }
+}
+
+/// 按需查找匹配行中匹配到的模式序号，避免不关心模式序号的 Sink 也要在每个匹配行上重新搜索
+pub(crate) trait PatternIds {
+    fn pattern_ids(&self) -> io::Result<&[PatternID]>;
+}
+
+/// 只有一个模式时不需要查找
+impl PatternIds for PatternID {
+    #[inline]
+    fn pattern_ids(&self) -> io::Result<&[PatternID]> {
+        Ok(std::slice::from_ref(self))
+    }
+}
+
+impl fmt::Debug for dyn PatternIds + '_ {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PatternIds")
+    }
+}
+
+/// 几个简单的 Sink 实现，通过闭包处理每个匹配行，适合不需要自己实现 Sink 的简单场景
+/// 闭包参数是匹配行的行号和内容（包含行终止符），返回 false 会终止搜索
+/// 注意它们都需要行号，所以 Searcher 需要开启 line_number（默认开启），否则搜索会返回错误
+pub mod sinks {
+    use std::io;
+    use crate::Searcher;
+    use crate::sink::{Sink, SinkError, SinkMatch};
+
+    /// 匹配行必须是合法的 UTF-8，否则搜索会返回错误
+    #[derive(Clone, Debug)]
+    pub struct UTF8<F>(pub F)
+    where
+        F: FnMut(u64, &str) -> Result<bool, io::Error>;
+
+    impl<F> Sink for UTF8<F>
+    where
+        F: FnMut(u64, &str) -> Result<bool, io::Error>,
+    {
+        type Error = io::Error;
+
+        fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+            let matched = match std::str::from_utf8(mat.bytes()) {
+                Ok(matched) => matched,
+                Err(err) => return Err(io::Error::error_message(err)),
+            };
+            let line_number = line_number(mat)?;
+            (self.0)(line_number, matched)
+        }
+    }
+
+    /// 和 UTF8 类似，但是非法的 UTF-8 序列会被替换成 U+FFFD，而不是返回错误
+    #[derive(Clone, Debug)]
+    pub struct Lossy<F>(pub F)
+    where
+        F: FnMut(u64, &str) -> Result<bool, io::Error>;
+
+    impl<F> Sink for Lossy<F>
+    where
+        F: FnMut(u64, &str) -> Result<bool, io::Error>,
+    {
+        type Error = io::Error;
+
+        fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+            let line_number = line_number(mat)?;
+            match std::str::from_utf8(mat.bytes()) {
+                Ok(matched) => (self.0)(line_number, matched),
+                // 只有非法的 UTF-8 才需要分配内存
+                Err(_) => (self.0)(line_number, &String::from_utf8_lossy(mat.bytes())),
+            }
+        }
+    }
+
+    /// 直接将匹配行的原始字节交给闭包
+    #[derive(Clone, Debug)]
+    pub struct Bytes<F>(pub F)
+    where
+        F: FnMut(u64, &[u8]) -> Result<bool, io::Error>;
+
+    impl<F> Sink for Bytes<F>
+    where
+        F: FnMut(u64, &[u8]) -> Result<bool, io::Error>,
+    {
+        type Error = io::Error;
+
+        fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+            let line_number = line_number(mat)?;
+            (self.0)(line_number, mat.bytes())
+        }
+    }
+
+    fn line_number(mat: &SinkMatch<'_>) -> Result<u64, io::Error> {
+        match mat.line_number() {
+            Some(line_number) => Ok(line_number),
+            None => Err(io::Error::error_message("line numbers not enabled")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grep_matcher::{LineTerminator, PatternID};
+    use crate::sink::SinkMatch;
+
+    /// 跨越多行的匹配可以通过 lines() 逐行处理
+    #[test]
+    fn sink_match_lines() {
+        let buffer = b"foo\r\nbar\r\nbaz\r\n";
+        let mat = SinkMatch {
+            line_term: LineTerminator::crlf(),
+            bytes: &buffer[5..],
+            absolute_byte_offset: 5,
+            line_number: Some(2),
+            buffer,
+            bytes_range_in_buffer: 5..buffer.len(),
+            pattern_ids: &PatternID::ZERO,
+        };
+        let lines: Vec<&[u8]> = mat.lines().collect();
+        assert_eq!(vec![&b"bar\r\n"[..], b"baz\r\n"], lines);
+        let lines: Vec<&[u8]> = mat.lines().without_terminator().collect();
+        assert_eq!(vec![&b"bar"[..], b"baz"], lines);
+    }
+}
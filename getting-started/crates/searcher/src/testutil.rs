@@ -0,0 +1,129 @@
+/// 单元测试使用的辅助类型，只在 cargo test 时编译
+/// 为了不让 grep-searcher 依赖具体的正则引擎，这里提供一个按字面量查找的 Matcher 以及记录所有 Sink 事件的 Sink
+
+use std::io;
+use bstr::ByteSlice;
+use grep_matcher::{LineTerminator, Match, Matcher, NoCaptures, NoError};
+use crate::{Searcher, Sink, SinkContext, SinkFinish, SinkMatch};
+
+/// 按字面量查找的 Matcher
+#[derive(Clone, Debug)]
+pub(crate) struct LiteralMatcher {
+    literal: Vec<u8>,
+    line_term: Option<LineTerminator>,
+}
+
+impl LiteralMatcher {
+    pub(crate) fn new(literal: &str) -> LiteralMatcher {
+        LiteralMatcher { literal: literal.as_bytes().to_vec(), line_term: None }
+    }
+
+    /// 设置 Matcher 报告的行终止符，用于测试 Searcher 对行终止符一致性的检查
+    pub(crate) fn line_terminator(mut self, line_term: Option<LineTerminator>) -> LiteralMatcher {
+        self.line_term = line_term;
+        self
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    type Captures = NoCaptures;
+    type Error = NoError;
+
+    fn new_captures(&self) -> Result<NoCaptures, NoError> {
+        Ok(NoCaptures::new())
+    }
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>, NoError> {
+        Ok(haystack[at..]
+            .find(&self.literal)
+            .map(|i| Match::new(at + i, at + i + self.literal.len())))
+    }
+
+    fn line_terminator(&self) -> Option<LineTerminator> {
+        self.line_term
+    }
+}
+
+/// 将 Sink 收到的所有事件记录下来的 Sink，匹配行记为 "行号:匹配行"，上下文行记为 "行号-上下文行"，上下文间断记为 "--"
+#[derive(Clone, Debug, Default)]
+pub(crate) struct KitchenSink {
+    output: Vec<u8>,
+    /// begin() 的返回值，默认为 true，设置为 false 时不会进行搜索
+    stop_at_begin: bool,
+    /// 搜索结束时 SinkFinish 报告的字节数，搜索还没结束时为 None
+    byte_count: Option<u64>,
+    /// 每次 binary_data() 报告的二进制数据偏移量
+    binary_offsets: Vec<u64>,
+    /// 搜索结束时 SinkFinish 报告的二进制数据偏移量
+    binary_byte_offset: Option<u64>,
+}
+
+impl KitchenSink {
+    pub(crate) fn new() -> KitchenSink {
+        KitchenSink::default()
+    }
+
+    /// begin() 返回 false，用于测试 Sink 取消搜索
+    pub(crate) fn stop_at_begin(mut self) -> KitchenSink {
+        self.stop_at_begin = true;
+        self
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.output
+    }
+
+    pub(crate) fn byte_count(&self) -> Option<u64> {
+        self.byte_count
+    }
+
+    pub(crate) fn binary_offsets(&self) -> &[u64] {
+        &self.binary_offsets
+    }
+
+    pub(crate) fn binary_byte_offset(&self) -> Option<u64> {
+        self.binary_byte_offset
+    }
+}
+
+impl Sink for KitchenSink {
+    type Error = io::Error;
+
+    fn matched(&mut self, _searcher: &Searcher, mat: &SinkMatch<'_>) -> Result<bool, io::Error> {
+        if let Some(line_number) = mat.line_number() {
+            self.output.extend_from_slice(line_number.to_string().as_bytes());
+            self.output.push(b':');
+        }
+        self.output.extend_from_slice(mat.bytes());
+        Ok(true)
+    }
+
+    fn context(&mut self, _searcher: &Searcher, context: &SinkContext<'_>) -> Result<bool, io::Error> {
+        if let Some(line_number) = context.line_number() {
+            self.output.extend_from_slice(line_number.to_string().as_bytes());
+            self.output.push(b'-');
+        }
+        self.output.extend_from_slice(context.bytes());
+        Ok(true)
+    }
+
+    fn context_break(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        self.output.extend_from_slice(b"--\n");
+        Ok(true)
+    }
+
+    fn binary_data(&mut self, _searcher: &Searcher, binary_byte_offset: u64) -> Result<bool, io::Error> {
+        self.binary_offsets.push(binary_byte_offset);
+        Ok(true)
+    }
+
+    fn begin(&mut self, _searcher: &Searcher) -> Result<bool, io::Error> {
+        Ok(!self.stop_at_begin)
+    }
+
+    fn finish(&mut self, _searcher: &Searcher, finish: &SinkFinish) -> Result<(), io::Error> {
+        self.byte_count = Some(finish.byte_count());
+        self.binary_byte_offset = finish.binary_byte_offset();
+        Ok(())
+    }
+}